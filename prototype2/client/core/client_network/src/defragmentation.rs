@@ -1,12 +1,12 @@
 use std::io::Read;
 use std::mem;
 
-use serde_json;
 use flate2::read::GzDecoder;
 use itertools::Itertools;
 
+use common::codec::CodecKind;
 use common::util::Newness;
-use common::aspects::CommonWorld;
+use common::snapshot::WorldDelta;
 use common::protocol::StateFragment;
 
 /**
@@ -76,21 +76,21 @@ impl FragmentBuffer {
  * Indicates that the implementor can be constructed from FragmentBuffer partials
  *
  * Implementors:
- * - CommonWorld is received over the wire in this manner
+ * - WorldDelta is received over the wire in this manner
  */
 pub trait Defragmentable: Sized {
   /**
    * Builds a full object from the FragmentBuffer, returning the sequence number and the object
    */
-  fn defragment(buffer: &FragmentBuffer) -> Option<(u16, Self)>;
+  fn defragment(buffer: &FragmentBuffer, codec: CodecKind) -> Option<(u16, Self)>;
 }
 
-impl Defragmentable for CommonWorld {
+impl Defragmentable for WorldDelta {
   /**
-   * CommonWorld is Gzipped Json. Besides the common work of combining the partial bytes,
-   * the payload must also be un-gzipped, and then parsed into a CommonWorld
+   * WorldDelta is gzipped, in the connection's codec. Besides the common work of combining the
+   * partial bytes, the payload must also be un-gzipped, and then decoded into a WorldDelta
    */
-  fn defragment(buffer: &FragmentBuffer) -> Option<(u16, Self)> {
+  fn defragment(buffer: &FragmentBuffer, codec: CodecKind) -> Option<(u16, Self)> {
     match buffer {
       &FragmentBuffer::None => None,
       &FragmentBuffer::Partial { seq_num, ref pieces } => {
@@ -99,11 +99,11 @@ impl Defragmentable for CommonWorld {
           let mut full_buffer = Vec::new();
           pieces.iter().cloned().foreach(|p| full_buffer.append(&mut p.unwrap()));
           let bytes: &[u8] = full_buffer.as_ref();
-          let mut string = String::new();
+          let mut encoded = Vec::new();
           GzDecoder::new(bytes)
-            .and_then(|mut decoder| decoder.read_to_string(&mut string))
+            .and_then(|mut decoder| decoder.read_to_end(&mut encoded))
             .ok()
-            .and_then(|_| serde_json::from_str(&string).ok())
+            .and_then(|_| codec.decode(&encoded).ok())
             .map(|res| (seq_num, res))
         } else {
           None
@@ -3,7 +3,6 @@ extern crate specs;
 extern crate serde;
 extern crate serde_json;
 extern crate itertools;
-extern crate gaffer_udp;
 extern crate flate2;
 extern crate common;
 extern crate client_state as state;
@@ -15,87 +14,151 @@ mod defragmentation;
 mod system;
 
 pub use self::defragmentation::{Defragmentable, FragmentBuffer};
-pub use self::system::{ConnectionEvent, ConnectionStatus};
+pub use self::system::{AdminSecret, ConnectionEvent, ConnectionStatus, ConnectionTransition,
+                       ShutdownPolicy};
 pub use self::system::ConnectionSystem;
 pub use self::system::AdapterSystem;
-pub use self::system::KeepAliveSystem;
+pub use self::system::{KeepAliveReply, KeepAliveSystem, ServerTick};
 pub use self::system::EventDistributionSystem;
 
-use std::net::SocketAddr;
-use std::thread;
-use std::time::Duration as StdDuration;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-use gaffer_udp::GafferPacket;
-use gaffer_udp::non_blocking::GafferSocket;
-use itertools::Unfold;
-
-use common::protocol::{ClientNetworkEvent, ServerNetworkEvent, ServerPayload};
+use common::codec::CodecKind;
+use common::network::{Datagram, Endpoint, Transport, UdpTransport, now_ms};
+use common::protocol::{ClientMessage, ClientNetworkEvent, ServerNetworkEvent, ServerPayload,
+                       SessionToken};
 
 /**
  * Manages the connection to the game server
  *
- * Uses a GafferSocket (a wrapper around UDP for some reliability)
- * TODO: This has a lot of common functionality with the server variant: Move that into common as
- * a trait
+ * Uses the provided transport to reach the server (UDP, outside of tests), with an endpoint to ack
+ * and resend what needs to be reliable. Connecting is left to the ConnectionSystem, which sends
+ * Connects through here like any other event.
  */
 pub struct Network {
-  socket: GafferSocket,
+  transport: Box<Transport>,
   server_addr: SocketAddr,
+  codec: CodecKind,
+  endpoint: Endpoint,
+  session: Option<SessionToken>,
+  awaiting_connect: bool,
 }
 
 impl Network {
-  pub fn new(port: u16, server_addr: SocketAddr) -> Network {
-    let sock = GafferSocket::bind(("0.0.0.0", port)).unwrap();
+  pub fn new(port: u16, server_addr: SocketAddr, codec: CodecKind) -> Network {
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+    let transport = UdpTransport::bind(address).unwrap();
+    Network::with_transport(Box::new(transport), server_addr, codec)
+  }
+
+  pub fn with_transport(transport: Box<Transport>,
+                        server_addr: SocketAddr,
+                        codec: CodecKind)
+                        -> Network {
     Network {
-      socket: sock,
+      transport: transport,
       server_addr: server_addr,
+      codec: codec,
+      endpoint: Endpoint::new(),
+      session: None,
+      awaiting_connect: false,
     }
   }
 
+  /**
+   * Yields every event from the server that's ready, then sends any acks and resends that are due
+   */
   pub fn recv_pending(&mut self) -> Vec<ServerNetworkEvent> {
-    let server_addr = self.server_addr.clone();
-    Unfold::new((), |_| self.socket.recv().ok().and_then(|v| v))
-      .filter_map(|gaffer_packet| ServerPayload::from_gaffer_packet(gaffer_packet))
-      .filter(|payload| payload.address == server_addr)
-      .map(|payload| payload.event)
-      .collect()
+    let mut events = Vec::new();
+    while let Some(datagram) = self.transport.recv() {
+      if datagram.address != self.server_addr {
+        continue;
+      }
+      for bytes in self.endpoint.receive(&datagram.payload) {
+        let decoded = ServerPayload::from_datagram(Datagram::new(self.server_addr, bytes),
+                                                   self.codec);
+        decoded.ok().map(|payload| {
+          self.note_connect_response(&payload.event);
+          events.push(payload.event)
+        });
+      }
+    }
+
+    for frame in self.endpoint.outgoing(now_ms()) {
+      self.transport.send(Datagram::new(self.server_addr.clone(), frame));
+    }
+
+    events
   }
 
+  /**
+   * Sends the event, along with our session if we've been issued one
+   *
+   * A Connect starts the conversation with the server over (unless it's another try at a Connect
+   * still awaiting an answer on a live link), so a restarted server isn't addressed as the one we
+   * knew before. The session is still presented, reclaiming our player if the server remembers it.
+   */
   pub fn send(&mut self, payload: ClientNetworkEvent) {
-    let json = serde_json::to_string(&payload).unwrap();
-    let _ = self.socket.send(GafferPacket::new(self.server_addr.clone(), json.as_bytes().to_vec()));
+    if let ClientNetworkEvent::Connect(_) = payload {
+      if !self.awaiting_connect || self.endpoint.is_dead() {
+        self.endpoint = Endpoint::new();
+        self.awaiting_connect = true;
+      }
+    }
+
+    let now = now_ms();
+    let delivery = payload.delivery();
+    let message = ClientMessage {
+      session: self.session,
+      event: payload.restamped(now),
+    };
+    let bytes = self.codec.encode(&message);
+    let frame = self.endpoint.send(delivery, &bytes, now);
+    self.transport.send(Datagram::new(self.server_addr.clone(), frame));
   }
 
-  pub fn connect(&mut self) -> bool {
-    self.try_send(ClientNetworkEvent::Connect,
-                  ServerNetworkEvent::Connected,
-                  5)
+  /**
+   * Tells the server we're leaving
+   *
+   * Doesn't wait for an answer: the Disconnect is resent for as long as the network is polled,
+   * and the server's answer arrives like any other event.
+   */
+  pub fn disconnect(&mut self) {
+    self.send(ClientNetworkEvent::Disconnect);
   }
-  pub fn disconnect(&mut self) -> bool {
-    self.try_send(ClientNetworkEvent::Disconnect,
-                  ServerNetworkEvent::Disconnected,
-                  5)
+
+  /**
+   * The format everything from the server arrives in, snapshots included
+   */
+  pub fn codec(&self) -> CodecKind {
+    self.codec
   }
 
-  fn try_send(&mut self,
-              event: ClientNetworkEvent,
-              expected_event: ServerNetworkEvent,
-              tries: u32)
-              -> bool {
-    let mut tries_remaining = tries;
-    self.send(event.clone());
-    thread::sleep(StdDuration::from_millis(200));
-
-    while tries_remaining > 0 {
-      let success = self.recv_pending().into_iter().any(|payload| payload == expected_event);
-      if success {
-        return true;
-      }
-      tries_remaining = tries_remaining - 1;
-      self.send(event.clone());
-      thread::sleep(StdDuration::from_millis(200));
-    }
+  /**
+   * How many reliable events the server has yet to ack
+   */
+  pub fn unacked(&self) -> usize {
+    self.endpoint.unacked()
+  }
+
+  /**
+   * Whether the server has stopped acking what we send, until the next Connect starts over
+   */
+  pub fn link_lost(&self) -> bool {
+    self.endpoint.is_dead()
+  }
 
-    false
+  /**
+   * Takes the session from the server's answer to a Connect
+   */
+  fn note_connect_response(&mut self, event: &ServerNetworkEvent) {
+    match event {
+      &ServerNetworkEvent::Connected(ref accepted) => {
+        self.session = Some(accepted.session);
+        self.awaiting_connect = false;
+      },
+      &ServerNetworkEvent::Rejected(_) => self.awaiting_connect = false,
+      _ => {},
+    }
   }
 }
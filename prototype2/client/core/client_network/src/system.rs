@@ -3,9 +3,11 @@ use specs;
 use state::Delta;
 use time::{self, Duration, Tm};
 use Network;
-use std::net::SocketAddr;
-use std::ops::Deref;
-use common::protocol::{ClientNetworkEvent, ServerNetworkEvent, SnapshotEvent};
+use std::fmt;
+use common::codec::CodecKind;
+use common::network::{LinkStats, now_ms};
+use common::protocol::{ClientNetworkEvent, ConnectRejection, ConnectRequest, ServerEcho,
+                       ServerNetworkEvent, SnapshotEvent};
 use std::sync::mpsc::Receiver;
 
 use itertools::Itertools;
@@ -16,30 +18,264 @@ use pubsub::{PubSubStore, Publisher, SubscriberToken};
 pub enum ConnectionEvent {
   Connected,
   Disconnected,
+  /// Anything from the server showing it's still there
   KeepAlive,
+  Rejected(String),
+  /// The server is going away, in about eta_ms milliseconds
+  ServerShuttingDown {
+    reason: String,
+    eta_ms: u64,
+  },
+  /// We're about to tell the server we're leaving
+  Leaving,
+  /// The server stopped acking what we sent, so some of it will never arrive
+  LinkLost,
 }
 
-#[derive(Clone)]
+/**
+ * What to do when the server says it's shutting down
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShutdownPolicy {
+  /// Disconnect, just as if we'd left
+  Leave,
+  /// Try to reconnect, starting once the server should be gone (in case it's only restarting)
+  Reconnect,
+}
+
+/**
+ * What to present to be made an admin when connecting, if anything
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdminSecret(pub Option<String>);
+
+/// How long the server can go unheard before the connection is considered to be timing out
+const TIMING_OUT_AFTER_MS: i64 = 1000;
+
+/// How long the server can go unheard before we give up on the connection and reconnect
+const TIMED_OUT_AFTER_MS: i64 = 5000;
+
+/// How long to wait after the first Connect before trying again, doubling with each attempt
+const FIRST_RETRY_MS: i64 = 250;
+const MAX_RETRY_MS: i64 = 8000;
+
+/// How many Connects go unanswered before we give up on the server
+const MAX_ATTEMPTS: u32 = 10;
+
+/**
+ * Where we stand with the server
+ *
+ * Connecting and reconnecting both send a Connect on every attempt, backing off between them.
+ */
+#[derive(Clone, Debug)]
 pub enum ConnectionStatus {
+  Connecting {
+    attempts: u32,
+    next_attempt: Tm,
+  },
   Connected {
     last_message: Tm,
   },
-  Disconnected,
+  /// Still connected, but the server has gone quiet
+  TimingOut {
+    last_message: Tm,
+  },
+  Reconnecting {
+    attempts: u32,
+    next_attempt: Tm,
+  },
+  /// Turned away, or given up on
+  Failed(String),
+  /// Waiting for the server to acknowledge us leaving
+  Disconnecting,
+  /// Done with the server, with why if it wasn't our idea
+  Disconnected(Option<String>),
 }
 
 impl ConnectionStatus {
   pub fn new() -> ConnectionStatus {
-    ConnectionStatus::Disconnected
+    ConnectionStatus::Connecting {
+      attempts: 0,
+      next_attempt: time::now(),
+    }
+  }
+
+  /**
+   * Whether the server is (as far as we know) keeping us up to date
+   */
+  pub fn is_connected(&self) -> bool {
+    match self {
+      &ConnectionStatus::Connected { .. } |
+      &ConnectionStatus::TimingOut { .. } => true,
+      _ => false,
+    }
+  }
+
+  fn is_reconnecting(&self) -> bool {
+    match self {
+      &ConnectionStatus::Reconnecting { .. } => true,
+      _ => false,
+    }
+  }
+
+  /**
+   * A short description of the status, fit for showing the player
+   *
+   * Only changes when the status moves to a different state, so it also tells transitions apart
+   * from progress within a state.
+   */
+  pub fn describe(&self) -> &'static str {
+    match self {
+      &ConnectionStatus::Connecting { .. } => "Connecting…",
+      &ConnectionStatus::Connected { .. } => "Connected",
+      &ConnectionStatus::TimingOut { .. } => "Connection interrupted…",
+      &ConnectionStatus::Reconnecting { .. } => "Reconnecting…",
+      &ConnectionStatus::Failed(_) => "Connection failed",
+      &ConnectionStatus::Disconnecting => "Disconnecting…",
+      &ConnectionStatus::Disconnected(_) => "Disconnected",
+    }
+  }
+
+  /**
+   * Yields the status this event moves us to, if any
+   */
+  fn after_event(&self,
+                 event: ConnectionEvent,
+                 now: Tm,
+                 policy: &ShutdownPolicy)
+                 -> Option<ConnectionStatus> {
+    use self::ConnectionStatus::*;
+
+    match (self, event) {
+      (&Disconnecting, ConnectionEvent::Disconnected) |
+      (&Disconnecting, ConnectionEvent::LinkLost) => Some(Disconnected(None)),
+      (&Disconnecting, _) |
+      (&Disconnected(_), _) |
+      // Leaving keeps the failure, so it can still be told after the engine is finalized
+      (&Failed(_), _) => None,
+      (&Connected { .. }, ConnectionEvent::Leaving) |
+      (&TimingOut { .. }, ConnectionEvent::Leaving) => Some(Disconnecting),
+      (_, ConnectionEvent::Leaving) => Some(Disconnected(None)),
+      (_, ConnectionEvent::Rejected(reason)) => Some(Failed(reason)),
+      (_, ConnectionEvent::ServerShuttingDown { reason, eta_ms }) => {
+        match policy {
+          &ShutdownPolicy::Leave => {
+            Some(Disconnected(Some(format!("Server shut down: {}", reason))))
+          },
+          // Already backing off between attempts
+          &ShutdownPolicy::Reconnect if self.is_reconnecting() => None,
+          &ShutdownPolicy::Reconnect => {
+            Some(Reconnecting {
+              attempts: 0,
+              next_attempt: now + Duration::milliseconds(eta_ms as i64),
+            })
+          },
+        }
+      },
+      (_, ConnectionEvent::Connected) => Some(Connected { last_message: now }),
+      (&Connected { .. }, ConnectionEvent::KeepAlive) |
+      (&TimingOut { .. }, ConnectionEvent::KeepAlive) => Some(Connected { last_message: now }),
+      // The server dropped us without being asked to, or we can't reach it any more
+      (&Connected { .. }, ConnectionEvent::Disconnected) |
+      (&TimingOut { .. }, ConnectionEvent::Disconnected) |
+      (&Connected { .. }, ConnectionEvent::LinkLost) |
+      (&TimingOut { .. }, ConnectionEvent::LinkLost) => {
+        Some(Reconnecting {
+          attempts: 0,
+          next_attempt: now,
+        })
+      },
+      (_, ConnectionEvent::KeepAlive) |
+      (_, ConnectionEvent::Disconnected) |
+      (_, ConnectionEvent::LinkLost) => None,
+    }
+  }
+
+  /**
+   * Yields the status the passage of time moves us to (if any), and whether to send a Connect
+   */
+  fn after_time(&self, now: Tm) -> (Option<ConnectionStatus>, bool) {
+    use self::ConnectionStatus::*;
+
+    let timing_out_after = Duration::milliseconds(TIMING_OUT_AFTER_MS);
+    let timed_out_after = Duration::milliseconds(TIMED_OUT_AFTER_MS);
+
+    match self {
+      &Connecting { attempts, next_attempt } if now >= next_attempt => {
+        retry(attempts, now, |attempts, next_attempt| {
+          Connecting {
+            attempts: attempts,
+            next_attempt: next_attempt,
+          }
+        })
+      },
+      &Reconnecting { attempts, next_attempt } if now >= next_attempt => {
+        retry(attempts, now, |attempts, next_attempt| {
+          Reconnecting {
+            attempts: attempts,
+            next_attempt: next_attempt,
+          }
+        })
+      },
+      &Connected { last_message } if now - last_message > timing_out_after => {
+        (Some(TimingOut { last_message: last_message }), false)
+      },
+      &TimingOut { last_message } if now - last_message > timed_out_after => {
+        let status = Reconnecting {
+          attempts: 0,
+          next_attempt: now,
+        };
+        (Some(status), false)
+      },
+      _ => (None, false),
+    }
+  }
+}
+
+impl fmt::Display for ConnectionStatus {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      &ConnectionStatus::Failed(ref reason) |
+      &ConnectionStatus::Disconnected(Some(ref reason)) => {
+        write!(f, "{}: {}", self.describe(), reason)
+      },
+      _ => write!(f, "{}", self.describe()),
+    }
+  }
+}
+
+/**
+ * Makes another attempt to connect, or gives up if we've made enough of them
+ */
+fn retry<F>(attempts: u32, now: Tm, status: F) -> (Option<ConnectionStatus>, bool)
+  where F: FnOnce(u32, Tm) -> ConnectionStatus
+{
+  if attempts >= MAX_ATTEMPTS {
+    let reason = format!("Server did not respond after {} attempts", attempts);
+    return (Some(ConnectionStatus::Failed(reason)), false);
   }
+
+  let backoff_ms = (FIRST_RETRY_MS << attempts.min(16)).min(MAX_RETRY_MS);
+  (Some(status(attempts + 1, now + Duration::milliseconds(backoff_ms))), true)
+}
+
+/**
+ * The connection moving from one state to another
+ */
+#[derive(Clone, Debug)]
+pub struct ConnectionTransition {
+  pub from: ConnectionStatus,
+  pub to: ConnectionStatus,
 }
 
 /**
  * Manages the network adapter, broadcasting pending outgoing events and accepting incoming events
  *
- * Also handles telling the server we're disconnecting.
+ * Also handles telling the server we're disconnecting, and tells the ConnectionSystem whenever
+ * the link is dead.
  *
- * Input: ServerNetworkEvent,
- * Output: ClientNetworkEvent
+ * Input: ClientNetworkEvent
+ * Output: ServerNetworkEvent, ConnectionEvent
  */
 pub struct AdapterSystem {
   network: Network,
@@ -49,18 +285,17 @@ pub struct AdapterSystem {
 declare_dependencies!(AdapterSystem, []);
 
 impl AdapterSystem {
-  pub fn new(port: u16,
-             server_addr: SocketAddr,
+  /**
+   * Takes over the network, which the ConnectionSystem will connect to the server
+   *
+   * The network's codec is shared as a resource, for the systems decoding what it delivers.
+   */
+  pub fn new(network: Network,
              network_kill_signal: Receiver<()>,
              world: &mut specs::World)
              -> AdapterSystem {
-    world.add_resource::<ConnectionStatus>(ConnectionStatus::new());
-    let mut network = Network::new(port, server_addr);
-    let success = network.connect();
-    if !success {
-      println!("Could not connect to {}", server_addr.to_string());
-      panic!("could not connect, i need to make this not out of band");
-    }
+    world.add_resource::<CodecKind>(network.codec());
+
     AdapterSystem {
       network: network,
       network_kill_signal: network_kill_signal,
@@ -75,24 +310,35 @@ impl AdapterSystem {
 
 impl specs::System<Delta> for AdapterSystem {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    let (mut outbound_events, mut inbound_events) = arg.fetch(|w| {
+    let (mut outbound_events, mut inbound_events, mut connection_events) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.client_event_sub_token).collected(),
-       w.fetch_publisher::<ServerNetworkEvent>())
+       w.fetch_publisher::<ServerNetworkEvent>(),
+       w.fetch_publisher::<ConnectionEvent>())
     });
 
     outbound_events.drain(..)
       .foreach(|event| self.network.send(event));
 
     self.network.recv_pending().into_iter().foreach(|e| inbound_events.push(e));
+    if self.network.link_lost() {
+      connection_events.push(ConnectionEvent::LinkLost);
+    }
 
     if let Ok(()) = self.network_kill_signal.try_recv() {
+      connection_events.push(ConnectionEvent::Leaving);
       self.network.disconnect();
     }
   }
 }
 
 /**
- * Handles internal representation of connnection status
+ * Drives the connection through its states, connecting (and reconnecting) as needed
+ *
+ * Connects are retried with backoff until the server answers, and a server that goes unheard for
+ * too long is reconnected to. Each Connect presents the AdminSecret, if there is one.
+ *
+ * Input: ConnectionEvent
+ * Output: ClientNetworkEvent, ConnectionTransition
  */
 pub struct ConnectionSystem {
   connection_event_sub_token: SubscriberToken<ConnectionEvent>,
@@ -102,6 +348,10 @@ standalone_installer_from_new!(ConnectionSystem, Delta);
 
 impl ConnectionSystem {
   pub fn new(world: &mut specs::World) -> ConnectionSystem {
+    world.add_resource::<ConnectionStatus>(ConnectionStatus::new());
+    world.add_resource::<ShutdownPolicy>(ShutdownPolicy::Leave);
+    world.add_resource::<AdminSecret>(AdminSecret(None));
+
     ConnectionSystem { connection_event_sub_token: world.register_subscriber::<ConnectionEvent>() }
   }
 
@@ -110,51 +360,94 @@ impl ConnectionSystem {
   }
 }
 
-#[allow(unused_imports, unused_variables)]
 impl specs::System<Delta> for ConnectionSystem {
-  fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    use specs::Join;
-    use itertools::Itertools;
-
-    let (mut connection_status, mut connection_events) = arg.fetch(|w| {
+  fn run(&mut self, arg: specs::RunArg, delta: Delta) {
+    let (mut connection_status,
+         shutdown_policy,
+         admin_secret,
+         mut connection_events,
+         mut outbound_events,
+         mut transitions) = arg.fetch(|w| {
       (w.write_resource::<ConnectionStatus>(),
-       w.fetch_subscriber(&self.connection_event_sub_token).collected())
+       w.read_resource::<ShutdownPolicy>(),
+       w.read_resource::<AdminSecret>(),
+       w.fetch_subscriber(&self.connection_event_sub_token).collected(),
+       w.fetch_publisher::<ClientNetworkEvent>(),
+       w.fetch_publisher::<ConnectionTransition>())
     });
 
-    connection_events.drain(..).foreach(|event| {
-      let mut out_status = None;
-      match (connection_status.deref(), event) {
-        (&ConnectionStatus::Connected { .. }, ConnectionEvent::Connected) |
-        (&ConnectionStatus::Connected { .. }, ConnectionEvent::KeepAlive) |
-        (&ConnectionStatus::Disconnected, ConnectionEvent::Connected) => {
-          out_status = Some(ConnectionStatus::Connected { last_message: time::now() })
-        },
-        (&ConnectionStatus::Connected { .. }, ConnectionEvent::Disconnected) => {
-          out_status = Some(ConnectionStatus::Disconnected)
-        },
-        (&ConnectionStatus::Disconnected, ConnectionEvent::KeepAlive) |
-        (&ConnectionStatus::Disconnected, ConnectionEvent::Disconnected) => {},
-      }
+    let previous_status = connection_status.clone();
 
-      out_status.map(|new_status| *connection_status = new_status);
+    connection_events.drain(..).foreach(|event| {
+      let new_status = connection_status.after_event(event, delta.now, &shutdown_policy);
+      new_status.map(|new_status| *connection_status = new_status);
     });
+
+    let (new_status, should_connect) = connection_status.after_time(delta.now);
+    new_status.map(|new_status| *connection_status = new_status);
+    if should_connect {
+      let mut request = ConnectRequest::current();
+      request.admin_secret = admin_secret.0.clone();
+      outbound_events.push(ClientNetworkEvent::Connect(request));
+    }
+
+    if previous_status.describe() != connection_status.describe() {
+      transitions.push(ConnectionTransition {
+        from: previous_status,
+        to: connection_status.clone(),
+      });
+    }
   }
 }
 
 /**
- * Ping back to notify server we're still alive
+ * The server's answer to one of our KeepAlives
+ */
+#[derive(Clone, Debug)]
+pub struct KeepAliveReply {
+  pub client_ms: u64,
+  pub server_tick: u64,
+  pub server_ms: u64,
+  /// How long the server held on to our KeepAlive before answering
+  pub held_ms: u64,
+  /// When it arrived, by our clock
+  pub received_ms: u64,
+}
+
+/// The latest tick the server has told us it's on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ServerTick(pub u64);
+
+/**
+ * Pings the server regularly to show we're still alive, timing the link from the answers
+ *
+ * Each KeepAlive hands back the server's clock reading from the last answer, so the server can
+ * time the link from its end too.
+ *
+ * Input: KeepAliveReply
+ * Output: ClientNetworkEvent, LinkStats, ServerTick
  */
 pub struct KeepAliveSystem {
   next_keepalive_time: Tm,
+  // The server's clock reading from the latest answer, and when (by ours) it arrived
+  pending_echo: Option<(u64, u64)>,
+  keep_alive_reply_sub_token: SubscriberToken<KeepAliveReply>,
 }
-// NOTE: Not directly dependent on AdapterSystem, but would like to guarantee
-// that we don't push this event on this tick
-declare_dependencies!(KeepAliveSystem, [AdapterSystem]);
+// NOTE: Runs after this tick's replies have been routed, and so after the
+// AdapterSystem, which guarantees that we don't send this event on this tick
+declare_dependencies!(KeepAliveSystem, [EventDistributionSystem]);
 standalone_installer_from_new!(KeepAliveSystem, Delta);
 
 impl KeepAliveSystem {
-  pub fn new(_: &mut specs::World) -> KeepAliveSystem {
-    KeepAliveSystem { next_keepalive_time: time::now() }
+  pub fn new(world: &mut specs::World) -> KeepAliveSystem {
+    world.add_resource::<LinkStats>(LinkStats::new());
+    world.add_resource::<ServerTick>(ServerTick(0));
+
+    KeepAliveSystem {
+      next_keepalive_time: time::now(),
+      pending_echo: None,
+      keep_alive_reply_sub_token: world.register_subscriber::<KeepAliveReply>(),
+    }
   }
 
   pub fn name() -> &'static str {
@@ -162,16 +455,38 @@ impl KeepAliveSystem {
   }
 }
 
-#[allow(unused_imports, unused_variables)]
 impl specs::System<Delta> for KeepAliveSystem {
   fn run(&mut self, arg: specs::RunArg, delta: Delta) {
-    use specs::Join;
-    use itertools::Itertools;
+    let (mut replies, mut outbound_events, mut link_stats, mut server_tick) = arg.fetch(|w| {
+      (w.fetch_subscriber(&self.keep_alive_reply_sub_token).collected(),
+       w.fetch_publisher::<ClientNetworkEvent>(),
+       w.write_resource::<LinkStats>(),
+       w.write_resource::<ServerTick>())
+    });
 
-    let mut outbound_events = arg.fetch(|w| w.fetch_publisher::<ClientNetworkEvent>());
+    replies.drain(..).foreach(|reply| {
+      link_stats.record(reply.client_ms,
+                        reply.received_ms,
+                        reply.held_ms,
+                        Some(reply.server_ms));
+      if reply.server_tick > server_tick.0 {
+        *server_tick = ServerTick(reply.server_tick);
+      }
+      self.pending_echo = Some((reply.server_ms, reply.received_ms));
+    });
 
     if delta.now > self.next_keepalive_time {
-      outbound_events.push(ClientNetworkEvent::KeepAlive);
+      let now = now_ms();
+      let echo = self.pending_echo.take().map(|(server_ms, received_ms)| {
+        ServerEcho {
+          server_ms: server_ms,
+          held_ms: now.saturating_sub(received_ms),
+        }
+      });
+      outbound_events.push(ClientNetworkEvent::KeepAlive {
+        client_ms: now,
+        echo: echo,
+      });
       self.next_keepalive_time = delta.now + Duration::milliseconds(20);
     }
   }
@@ -201,13 +516,15 @@ impl EventDistributionSystem {
 impl specs::System<Delta> for EventDistributionSystem {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
 
-    let (mut inbound_events, connection_events, snapshot_events) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.server_event_sub_token).collected(),
-       w.fetch_publisher::<ConnectionEvent>(),
-       w.fetch_publisher::<SnapshotEvent>())
-    });
+    let (mut inbound_events, connection_events, snapshot_events, keep_alive_replies) =
+      arg.fetch(|w| {
+        (w.fetch_subscriber(&self.server_event_sub_token).collected(),
+         w.fetch_publisher::<ConnectionEvent>(),
+         w.fetch_publisher::<SnapshotEvent>(),
+         w.fetch_publisher::<KeepAliveReply>())
+      });
 
-    let mut router = EventRouter::new(connection_events, snapshot_events);
+    let mut router = EventRouter::new(connection_events, snapshot_events, keep_alive_replies);
     inbound_events.drain(..).foreach(|e| router.route_network_event(e));
   }
 }
@@ -216,15 +533,18 @@ impl specs::System<Delta> for EventDistributionSystem {
 struct EventRouter<'a> {
   connection_events: Publisher<'a, ConnectionEvent>,
   snapshot_events: Publisher<'a, SnapshotEvent>,
+  keep_alive_replies: Publisher<'a, KeepAliveReply>,
 }
 
 impl<'a> EventRouter<'a> {
   pub fn new(connection_events: Publisher<'a, ConnectionEvent>,
-             snapshot_events: Publisher<'a, SnapshotEvent>)
+             snapshot_events: Publisher<'a, SnapshotEvent>,
+             keep_alive_replies: Publisher<'a, KeepAliveReply>)
              -> EventRouter<'a> {
     EventRouter {
       connection_events: connection_events,
       snapshot_events: snapshot_events,
+      keep_alive_replies: keep_alive_replies,
     }
   }
 
@@ -232,11 +552,137 @@ impl<'a> EventRouter<'a> {
     use common::protocol::ServerNetworkEvent::*;
 
     match network_event {
-      Connected => self.connection_events.push(ConnectionEvent::Connected),
+      Connected(_) => self.connection_events.push(ConnectionEvent::Connected),
+      Rejected(ConnectRejection::ShuttingDown(reason)) => {
+        // Not a rejection of us in particular, so it's handled like any other shutdown
+        self.connection_events.push(ConnectionEvent::ServerShuttingDown {
+          reason: reason,
+          eta_ms: 0,
+        })
+      },
+      Rejected(rejection) => {
+        self.connection_events.push(ConnectionEvent::Rejected(rejection.to_string()))
+      },
       Disconnected => self.connection_events.push(ConnectionEvent::Disconnected),
-      KeepAlive => self.connection_events.push(ConnectionEvent::KeepAlive),
+      KeepAlive { client_ms, server_tick, server_ms, held_ms } => {
+        self.connection_events.push(ConnectionEvent::KeepAlive);
+        self.keep_alive_replies.push(KeepAliveReply {
+          client_ms: client_ms,
+          server_tick: server_tick,
+          server_ms: server_ms,
+          held_ms: held_ms,
+          received_ms: now_ms(),
+        })
+      },
       Error(msg) => println!("Server Error?: {}", msg),
-      Snapshot(event) => self.snapshot_events.push(event),
+      ShuttingDown { reason, eta_ms } => {
+        self.connection_events.push(ConnectionEvent::ServerShuttingDown {
+          reason: reason,
+          eta_ms: eta_ms,
+        })
+      },
+      Snapshot(event) => {
+        // Snapshots show the server is there just as well as its KeepAlives do
+        self.connection_events.push(ConnectionEvent::KeepAlive);
+        self.snapshot_events.push(event)
+      },
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use time::{self, Duration};
+
+  #[test]
+  fn connects_are_retried_with_backoff_until_given_up() {
+    let now = time::now();
+    let mut status = ConnectionStatus::Connecting {
+      attempts: 0,
+      next_attempt: now,
+    };
+    let mut sent = Vec::new();
+
+    // Step a minute, 50ms at a time
+    for step in 0..1200 {
+      let step_time = now + Duration::milliseconds(step * 50);
+      let (new_status, should_connect) = status.after_time(step_time);
+      if should_connect {
+        sent.push(step * 50);
+      }
+      new_status.map(|new_status| status = new_status);
+    }
+
+    assert_eq!(&sent[..4], &[0, 250, 750, 1750]);
+    assert_eq!(sent.len(), MAX_ATTEMPTS as usize);
+    assert_eq!(status.describe(), "Connection failed");
+  }
+
+  #[test]
+  fn a_quiet_server_is_reconnected_to() {
+    let now = time::now();
+    let status = ConnectionStatus::new()
+      .after_event(ConnectionEvent::Connected, now, &ShutdownPolicy::Leave)
+      .unwrap();
+
+    let later = now + Duration::milliseconds(TIMING_OUT_AFTER_MS + 1);
+    let status = status.after_time(later).0.unwrap();
+    assert_eq!(status.describe(), "Connection interrupted…");
+    assert!(status.is_connected());
+
+    let much_later = now + Duration::milliseconds(TIMED_OUT_AFTER_MS + 1);
+    let (status, should_connect) = status.after_time(much_later);
+    let status = status.unwrap();
+    assert_eq!(status.describe(), "Reconnecting…");
+    assert!(!should_connect);
+    assert!(status.after_time(much_later).1);
+  }
+
+  #[test]
+  fn a_dead_link_is_reconnected() {
+    let now = time::now();
+    let status = ConnectionStatus::new()
+      .after_event(ConnectionEvent::Connected, now, &ShutdownPolicy::Leave)
+      .unwrap();
+
+    let status =
+      status.after_event(ConnectionEvent::LinkLost, now, &ShutdownPolicy::Leave).unwrap();
+    assert_eq!(status.describe(), "Reconnecting…");
+    assert!(status.after_event(ConnectionEvent::LinkLost, now, &ShutdownPolicy::Leave).is_none());
+    assert!(status.after_time(now).1);
+  }
+
+  #[test]
+  fn leaving_a_shutdown_is_a_disconnect() {
+    let now = time::now();
+    let shutdown = ConnectionEvent::ServerShuttingDown {
+      reason: "Maintenance".to_owned(),
+      eta_ms: 2000,
+    };
+    let status = ConnectionStatus::new()
+      .after_event(ConnectionEvent::Connected, now, &ShutdownPolicy::Leave)
+      .unwrap();
+
+    let status = status.after_event(shutdown, now, &ShutdownPolicy::Leave).unwrap();
+    assert_eq!(status.describe(), "Disconnected");
+    assert_eq!(status.to_string(), "Disconnected: Server shut down: Maintenance");
+  }
+
+  #[test]
+  fn failures_are_kept_through_leaving() {
+    let now = time::now();
+    let rejected = ConnectionStatus::new()
+      .after_event(ConnectionEvent::Rejected("Server is full".to_owned()),
+                   now,
+                   &ShutdownPolicy::Leave)
+      .unwrap();
+    assert_eq!(rejected.describe(), "Connection failed");
+    assert!(rejected.after_event(ConnectionEvent::Leaving, now, &ShutdownPolicy::Leave).is_none());
+
+    let (timed_out, _) = retry(MAX_ATTEMPTS, now, |_, _| ConnectionStatus::new());
+    let timed_out = timed_out.unwrap();
+    assert_eq!(timed_out.describe(), "Connection failed");
+    assert!(timed_out.after_event(ConnectionEvent::Leaving, now, &ShutdownPolicy::Leave).is_none());
+  }
+}
@@ -2,8 +2,13 @@ extern crate itertools;
 extern crate common;
 extern crate time;
 
+use std::collections::VecDeque;
+
 use common::aspects::SynchronizedAspect;
 
+/// Most moves held waiting for the server, beyond which the oldest are given up on
+const MAX_PENDING_INPUTS: usize = 256;
+
 #[derive(Debug, Clone)]
 pub struct ExitFlag(pub bool);
 
@@ -15,3 +20,79 @@ pub struct Delta {
   pub dt: time::Duration,
   pub now: time::Tm,
 }
+
+/**
+ * An input the player sent, which the server may not have applied yet
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingInput {
+  pub seq: u32,
+  /// Where the player asked to go, as in PlayerInput
+  pub movement: (f32, f32, f32),
+  /// When it was made, by the client's clock
+  pub sent_ms: u64,
+}
+
+/**
+ * The player's inputs that the server hasn't yet included in a snapshot, numbered in the order
+ * they were sent
+ *
+ * Kept so the player's own entity can be drawn ahead of the latest snapshot, with these inputs
+ * replayed on top of it. The latest input the server has applied is kept too, since it stands
+ * until the next one arrives.
+ */
+#[derive(Debug, Clone)]
+pub struct InputLog {
+  next_seq: u32,
+  held: Option<PendingInput>,
+  pending: VecDeque<PendingInput>,
+}
+
+impl InputLog {
+  pub fn new() -> InputLog {
+    InputLog {
+      next_seq: 0,
+      held: None,
+      pending: VecDeque::new(),
+    }
+  }
+
+  /**
+   * Notes an input, and yields the number it should be sent with
+   */
+  pub fn record(&mut self, movement: (f32, f32, f32), sent_ms: u64) -> u32 {
+    let seq = self.next_seq;
+    self.next_seq = self.next_seq.wrapping_add(1);
+    self.pending.push_back(PendingInput {
+      seq: seq,
+      movement: movement,
+      sent_ms: sent_ms,
+    });
+
+    while self.pending.len() > MAX_PENDING_INPUTS {
+      self.pending.pop_front();
+    }
+    seq
+  }
+
+  /**
+   * Forgets every input up to and including the given one, which the server has applied
+   */
+  pub fn acknowledge(&mut self, seq: u32) {
+    let applied = self.pending.iter().position(|input| input.seq == seq);
+    if let Some(idx) = applied {
+      self.held = self.pending.drain(..(idx + 1)).last();
+    }
+  }
+
+  /**
+   * The latest input the server has applied
+   */
+  pub fn held(&self) -> Option<&PendingInput> {
+    self.held.as_ref()
+  }
+
+  pub fn pending(&self) -> &VecDeque<PendingInput> {
+    &self.pending
+  }
+}
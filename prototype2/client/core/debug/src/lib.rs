@@ -8,6 +8,7 @@ extern crate automatic_system_installer;
 
 use state::Delta;
 use common::aspects::{DisabledAspect, PhysicalAspect, RenderAspect};
+use common::network::LinkStats;
 
 const FRAME_WAIT: u32 = 60;
 
@@ -44,18 +45,22 @@ impl specs::System<Delta> for System {
     use specs::Join;
     use itertools::Itertools;
 
-    let (entities, physical, disableds, renderables, mut debug_msg) = arg.fetch(|w| {
+    let (entities, physical, disableds, renderables, link_stats, mut debug_msg) = arg.fetch(|w| {
       (w.entities(),
        w.read::<PhysicalAspect>(),
        w.read::<DisabledAspect>(),
        w.read::<RenderAspect>(),
+       w.read_resource::<LinkStats>(),
        w.write_resource::<DebugMessage>())
     });
 
     if self.frames_waited >= FRAME_WAIT {
       self.frames_waited = 0;
 
-      let mut message = String::new();
+      let mut message = format!("rtt: {:.1}ms, jitter: {:.1}ms, server clock: {:+.0}ms\n",
+                                link_stats.rtt_ms,
+                                link_stats.jitter_ms,
+                                link_stats.clock_offset_ms);
       (&entities, &physical).iter().foreach(|(ent, phys)| {
         message.push_str(&format!("{:?}: {:?}\n", ent, phys));
       });
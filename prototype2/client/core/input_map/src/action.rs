@@ -0,0 +1,100 @@
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * Something the player can do, which any number of keys or buttons may be bound to
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Action {
+  MoveForward,
+  MoveBackward,
+  MoveLeft,
+  MoveRight,
+  Jump,
+  Primary,
+  Secondary,
+  TogglePause,
+  OpenConsole,
+  ConsoleSubmit,
+  ConsoleBackspace,
+  ConsoleDelete,
+  ConsoleLeft,
+  ConsoleRight,
+  ConsoleHome,
+  ConsoleEnd,
+}
+
+/// Every action, in the order they're listed
+pub const ALL_ACTIONS: &'static [Action] = &[Action::MoveForward,
+                                             Action::MoveBackward,
+                                             Action::MoveLeft,
+                                             Action::MoveRight,
+                                             Action::Jump,
+                                             Action::Primary,
+                                             Action::Secondary,
+                                             Action::TogglePause,
+                                             Action::OpenConsole,
+                                             Action::ConsoleSubmit,
+                                             Action::ConsoleBackspace,
+                                             Action::ConsoleDelete,
+                                             Action::ConsoleLeft,
+                                             Action::ConsoleRight,
+                                             Action::ConsoleHome,
+                                             Action::ConsoleEnd];
+
+impl Action {
+  /**
+   * Whether holding the key down sets the action off again each time the key repeats, as it does
+   * for editing the console's line
+   */
+  pub fn repeats(&self) -> bool {
+    match self {
+      &Action::ConsoleBackspace |
+      &Action::ConsoleDelete |
+      &Action::ConsoleLeft |
+      &Action::ConsoleRight => true,
+      _ => false,
+    }
+  }
+
+  /**
+   * What the action is called in bindings files and console commands
+   */
+  pub fn name(&self) -> &'static str {
+    match self {
+      &Action::MoveForward => "move_forward",
+      &Action::MoveBackward => "move_backward",
+      &Action::MoveLeft => "move_left",
+      &Action::MoveRight => "move_right",
+      &Action::Jump => "jump",
+      &Action::Primary => "primary",
+      &Action::Secondary => "secondary",
+      &Action::TogglePause => "toggle_pause",
+      &Action::OpenConsole => "open_console",
+      &Action::ConsoleSubmit => "console_submit",
+      &Action::ConsoleBackspace => "console_backspace",
+      &Action::ConsoleDelete => "console_delete",
+      &Action::ConsoleLeft => "console_left",
+      &Action::ConsoleRight => "console_right",
+      &Action::ConsoleHome => "console_home",
+      &Action::ConsoleEnd => "console_end",
+    }
+  }
+}
+
+impl fmt::Display for Action {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+impl FromStr for Action {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Action, String> {
+    ALL_ACTIONS.iter()
+      .find(|action| action.name() == s)
+      .cloned()
+      .ok_or(format!("Unknown action: {}", s))
+  }
+}
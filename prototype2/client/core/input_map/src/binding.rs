@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use glutin::{MouseButton, VirtualKeyCode};
+use itertools::Itertools;
+use toml;
+
+use action::{ALL_ACTIONS, Action};
+
+macro_rules! named_keys {
+  ($($key:ident),*) => { &[$((stringify!($key), VirtualKeyCode::$key)),*] }
+}
+
+/// The keys that can be bound, by the names glutin gives them
+const KEY_NAMES: &'static [(&'static str, VirtualKeyCode)] =
+  named_keys!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+              Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+              F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+              Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
+              Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+              Escape, Tab, Capital, Space, Back, Return, Insert, Delete, Home, End, PageUp,
+              PageDown, Left, Right, Up, Down, LShift, RShift, LControl, RControl, LAlt, RAlt,
+              Grave, Subtract, Equals, LBracket, RBracket, Backslash, Semicolon, Apostrophe,
+              Comma, Period, Slash);
+
+/**
+ * A key or mouse button that can set off an action
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Trigger {
+  Key(VirtualKeyCode),
+  Mouse(MouseButton),
+}
+
+impl fmt::Display for Trigger {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      &Trigger::Key(key) => {
+        match KEY_NAMES.iter().find(|&&(_, named)| named == key) {
+          Some(&(name, _)) => write!(f, "{}", name),
+          None => write!(f, "{:?}", key),
+        }
+      },
+      &Trigger::Mouse(MouseButton::Left) => write!(f, "MouseLeft"),
+      &Trigger::Mouse(MouseButton::Right) => write!(f, "MouseRight"),
+      &Trigger::Mouse(MouseButton::Middle) => write!(f, "MouseMiddle"),
+      &Trigger::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{}", button),
+    }
+  }
+}
+
+impl FromStr for Trigger {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Trigger, String> {
+    let lowered = s.to_lowercase();
+    match lowered.as_str() {
+      "mouseleft" => return Ok(Trigger::Mouse(MouseButton::Left)),
+      "mouseright" => return Ok(Trigger::Mouse(MouseButton::Right)),
+      "mousemiddle" => return Ok(Trigger::Mouse(MouseButton::Middle)),
+      _ => {},
+    }
+
+    if lowered.starts_with("mouse") {
+      if let Ok(button) = u8::from_str(&lowered["mouse".len()..]) {
+        return Ok(Trigger::Mouse(MouseButton::Other(button)));
+      }
+    }
+
+    KEY_NAMES.iter()
+      .find(|&&(name, _)| name.to_lowercase() == lowered)
+      .map(|&(_, key)| Trigger::Key(key))
+      .ok_or(format!("Unknown key: {}", s))
+  }
+}
+
+/**
+ * The modifier keys that are down, or that a binding needs down
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Modifiers {
+  pub ctrl: bool,
+  pub shift: bool,
+  pub alt: bool,
+}
+
+impl Modifiers {
+  pub fn none() -> Modifiers {
+    Modifiers {
+      ctrl: false,
+      shift: false,
+      alt: false,
+    }
+  }
+
+  /**
+   * Whether every modifier the other needs is down here
+   */
+  pub fn covers(&self, other: &Modifiers) -> bool {
+    (self.ctrl || !other.ctrl) && (self.shift || !other.shift) && (self.alt || !other.alt)
+  }
+
+  fn count(&self) -> usize {
+    vec![self.ctrl, self.shift, self.alt].into_iter().filter(|&held| held).count()
+  }
+}
+
+/**
+ * A key or button, with the modifiers that have to be held along with it
+ *
+ * Written like "W", "Ctrl+S" or "Shift+MouseLeft".
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Binding {
+  pub modifiers: Modifiers,
+  pub trigger: Trigger,
+}
+
+impl Binding {
+  pub fn new(trigger: Trigger) -> Binding {
+    Binding {
+      modifiers: Modifiers::none(),
+      trigger: trigger,
+    }
+  }
+
+  pub fn key(key: VirtualKeyCode) -> Binding {
+    Binding::new(Trigger::Key(key))
+  }
+
+  pub fn mouse(button: MouseButton) -> Binding {
+    Binding::new(Trigger::Mouse(button))
+  }
+}
+
+impl fmt::Display for Binding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.modifiers.ctrl {
+      try!(write!(f, "Ctrl+"));
+    }
+    if self.modifiers.shift {
+      try!(write!(f, "Shift+"));
+    }
+    if self.modifiers.alt {
+      try!(write!(f, "Alt+"));
+    }
+    write!(f, "{}", self.trigger)
+  }
+}
+
+impl FromStr for Binding {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Binding, String> {
+    let mut parts = s.split('+').map(|part| part.trim()).collect::<Vec<&str>>();
+    let trigger = try!(Trigger::from_str(parts.pop().unwrap_or("")));
+
+    let mut modifiers = Modifiers::none();
+    for part in parts {
+      match part.to_lowercase().as_str() {
+        "ctrl" | "control" => modifiers.ctrl = true,
+        "shift" => modifiers.shift = true,
+        "alt" => modifiers.alt = true,
+        _ => return Err(format!("Unknown modifier: {}", part)),
+      }
+    }
+
+    Ok(Binding {
+      modifiers: modifiers,
+      trigger: trigger,
+    })
+  }
+}
+
+/**
+ * Which keys and buttons set off which actions
+ *
+ * Loaded from a TOML file like the one below. Actions left out keep these defaults, and an
+ * empty list unbinds one.
+ *
+ * ```toml
+ * [bindings]
+ * move_forward = ["W"]
+ * move_backward = ["S"]
+ * move_left = ["A"]
+ * move_right = ["D"]
+ * jump = ["Space"]
+ * primary = ["MouseLeft"]
+ * secondary = ["MouseRight"]
+ * toggle_pause = ["Escape"]
+ * open_console = ["Grave"]
+ * console_submit = ["Return"]
+ * console_backspace = ["Back"]
+ * console_delete = ["Delete"]
+ * console_left = ["Left"]
+ * console_right = ["Right"]
+ * console_home = ["Home"]
+ * console_end = ["End"]
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+  bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl Bindings {
+  /**
+   * Bindings with nothing bound
+   */
+  pub fn new() -> Bindings {
+    Bindings { bindings: HashMap::new() }
+  }
+
+  pub fn defaults() -> Bindings {
+    use glutin::VirtualKeyCode::*;
+
+    let mut bindings = Bindings::new();
+    bindings.bind(Action::MoveForward, Binding::key(W));
+    bindings.bind(Action::MoveBackward, Binding::key(S));
+    bindings.bind(Action::MoveLeft, Binding::key(A));
+    bindings.bind(Action::MoveRight, Binding::key(D));
+    bindings.bind(Action::Jump, Binding::key(Space));
+    bindings.bind(Action::Primary, Binding::mouse(MouseButton::Left));
+    bindings.bind(Action::Secondary, Binding::mouse(MouseButton::Right));
+    bindings.bind(Action::TogglePause, Binding::key(Escape));
+    bindings.bind(Action::OpenConsole, Binding::key(Grave));
+    bindings.bind(Action::ConsoleSubmit, Binding::key(Return));
+    bindings.bind(Action::ConsoleBackspace, Binding::key(Back));
+    bindings.bind(Action::ConsoleDelete, Binding::key(Delete));
+    bindings.bind(Action::ConsoleLeft, Binding::key(Left));
+    bindings.bind(Action::ConsoleRight, Binding::key(Right));
+    bindings.bind(Action::ConsoleHome, Binding::key(Home));
+    bindings.bind(Action::ConsoleEnd, Binding::key(End));
+    bindings
+  }
+
+  pub fn bind(&mut self, action: Action, binding: Binding) {
+    let bound = self.bindings.entry(action).or_insert(Vec::new());
+    if !bound.contains(&binding) {
+      bound.push(binding);
+    }
+  }
+
+  /**
+   * Unbinds one binding from the action, yielding whether it was bound
+   */
+  pub fn unbind(&mut self, action: Action, binding: &Binding) -> bool {
+    let bound = self.bindings.entry(action).or_insert(Vec::new());
+    let before = bound.len();
+    bound.retain(|existing| existing != binding);
+    bound.len() != before
+  }
+
+  pub fn unbind_all(&mut self, action: Action) {
+    self.bindings.insert(action, Vec::new());
+  }
+
+  pub fn bindings_for(&self, action: Action) -> &[Binding] {
+    self.bindings.get(&action).map(|bound| bound.as_slice()).unwrap_or(&[])
+  }
+
+  /**
+   * The actions set off by the trigger while the given modifiers are down
+   *
+   * Only the bindings needing the most modifiers count, so that Ctrl+S doesn't also set off
+   * whatever S is bound to.
+   */
+  pub fn actions_for(&self, trigger: Trigger, held: &Modifiers) -> Vec<Action> {
+    let matching = ALL_ACTIONS.iter()
+      .flat_map(|action| {
+        self.bindings_for(*action)
+          .iter()
+          .filter(|binding| binding.trigger == trigger && held.covers(&binding.modifiers))
+          .map(|binding| (*action, binding.modifiers.count()))
+          .collect::<Vec<(Action, usize)>>()
+      })
+      .collect::<Vec<(Action, usize)>>();
+
+    let most_modifiers = matching.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    matching.into_iter()
+      .filter(|&(_, count)| count == most_modifiers)
+      .map(|(action, _)| action)
+      .unique()
+      .collect()
+  }
+
+  pub fn load(path: &Path) -> Result<Bindings, String> {
+    let mut contents = String::new();
+    try!(File::open(path)
+      .and_then(|mut file| file.read_to_string(&mut contents))
+      .map_err(|err| err.to_string()));
+
+    let mut parser = toml::Parser::new(&contents);
+    match parser.parse() {
+      Some(table) => Bindings::from_toml(&toml::Value::Table(table)),
+      None => {
+        let errors = parser.errors.iter().map(|err| err.to_string()).collect::<Vec<_>>();
+        Err(errors.join(", "))
+      },
+    }
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), String> {
+    File::create(path)
+      .and_then(|mut file| file.write_all(self.to_toml().to_string().as_bytes()))
+      .map_err(|err| err.to_string())
+  }
+
+  /**
+   * Reads bindings, using the default for any action left out
+   */
+  pub fn from_toml(value: &toml::Value) -> Result<Bindings, String> {
+    let mut bindings = Bindings::defaults();
+    let table = match value.lookup("bindings") {
+      Some(found) => try!(found.as_table().ok_or("bindings should be a table".to_owned())),
+      None => return Ok(bindings),
+    };
+
+    for (name, bound) in table.iter() {
+      let action = try!(Action::from_str(name));
+      let expected = format!("bindings.{} should be a list of keys, like [\"W\", \"Ctrl+S\"]",
+                             name);
+      let values = try!(bound.as_slice().ok_or(expected.clone()));
+
+      bindings.unbind_all(action);
+      for value in values.iter() {
+        let binding_str = try!(value.as_str().ok_or(expected.clone()));
+        let binding = try!(Binding::from_str(binding_str)
+          .map_err(|err| format!("bindings.{}: {}", name, err)));
+        bindings.bind(action, binding);
+      }
+    }
+
+    Ok(bindings)
+  }
+
+  pub fn to_toml(&self) -> toml::Value {
+    let mut table = toml::Table::new();
+    ALL_ACTIONS.iter().foreach(|action| {
+      let bound = self.bindings_for(*action)
+        .iter()
+        .map(|binding| toml::Value::String(binding.to_string()))
+        .collect();
+      table.insert(action.name().to_owned(), toml::Value::Array(bound));
+    });
+
+    let mut root = toml::Table::new();
+    root.insert("bindings".to_owned(), toml::Value::Table(table));
+    toml::Value::Table(root)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{Binding, Bindings, Modifiers, Trigger};
+  use action::Action;
+  use glutin::{MouseButton, VirtualKeyCode};
+  use std::str::FromStr;
+  use toml;
+
+  fn parse(contents: &str) -> Result<Bindings, String> {
+    let table = toml::Parser::new(contents).parse().unwrap();
+    Bindings::from_toml(&toml::Value::Table(table))
+  }
+
+  #[test]
+  fn bindings_read_back_as_written() {
+    let binding = Binding::from_str("ctrl+shift+s").unwrap();
+    assert_eq!(binding.trigger, Trigger::Key(VirtualKeyCode::S));
+    assert!(binding.modifiers.ctrl && binding.modifiers.shift && !binding.modifiers.alt);
+    assert_eq!(binding.to_string(), "Ctrl+Shift+S");
+    assert_eq!(Binding::from_str("MouseRight"), Ok(Binding::mouse(MouseButton::Right)));
+    assert!(Binding::from_str("Hyper+S").is_err());
+    assert!(Binding::from_str("Q1").is_err());
+  }
+
+  #[test]
+  fn bindings_needing_more_modifiers_win() {
+    let mut bindings = Bindings::new();
+    bindings.bind(Action::MoveBackward, Binding::key(VirtualKeyCode::S));
+    bindings.bind(Action::OpenConsole, Binding::from_str("Ctrl+S").unwrap());
+    let mut ctrl = Modifiers::none();
+    ctrl.ctrl = true;
+    let s = Trigger::Key(VirtualKeyCode::S);
+
+    assert_eq!(bindings.actions_for(s, &Modifiers::none()), vec![Action::MoveBackward]);
+    assert_eq!(bindings.actions_for(s, &ctrl), vec![Action::OpenConsole]);
+  }
+
+  #[test]
+  fn printed_bindings_read_back() {
+    let mut bindings = Bindings::defaults();
+    bindings.bind(Action::MoveForward, Binding::key(VirtualKeyCode::Comma));
+    bindings.unbind_all(Action::Jump);
+
+    assert_eq!(parse(&bindings.to_toml().to_string()), Ok(bindings));
+  }
+
+  #[test]
+  fn listed_actions_replace_their_defaults() {
+    let bindings = parse("[bindings]\nmove_forward = [\"Comma\", \"Up\"]\njump = []").unwrap();
+
+    assert_eq!(bindings.bindings_for(Action::MoveForward),
+               &[Binding::key(VirtualKeyCode::Comma), Binding::key(VirtualKeyCode::Up)]);
+    assert!(bindings.bindings_for(Action::Jump).is_empty());
+    assert_eq!(bindings.bindings_for(Action::MoveLeft),
+               Bindings::defaults().bindings_for(Action::MoveLeft));
+    assert!(parse("[bindings]\nfly = [\"F\"]").is_err());
+    assert!(parse("[bindings]\njump = \"Space\"").is_err());
+  }
+}
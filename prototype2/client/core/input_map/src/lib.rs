@@ -0,0 +1,203 @@
+extern crate specs;
+extern crate glutin;
+extern crate itertools;
+extern crate toml;
+extern crate pubsub;
+extern crate client_state as state;
+#[macro_use(declare_dependencies, standalone_installer_from_new)]
+extern crate automatic_system_installer;
+
+mod action;
+mod binding;
+
+pub use action::{ALL_ACTIONS, Action};
+pub use binding::{Binding, Bindings, Modifiers, Trigger};
+
+use std::collections::{HashMap, HashSet};
+
+use glutin::VirtualKeyCode;
+use pubsub::{PubSubStore, SubscriberToken};
+use state::Delta;
+
+/**
+ * Where the bindings were loaded from, and where changes to them are saved
+ */
+#[derive(Debug, Clone)]
+pub struct BindingsPath(pub Option<String>);
+
+/**
+ * An action being set off, or let go of
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionEvent {
+  Pressed(Action),
+  Released(Action),
+}
+
+/**
+ * The actions whose keys or buttons are being held down
+ */
+#[derive(Debug, Clone)]
+pub struct HeldActions(pub HashSet<Action>);
+
+impl HeldActions {
+  pub fn is_held(&self, action: Action) -> bool {
+    self.0.contains(&action)
+  }
+}
+
+/**
+ * Turns key and mouse button events into the actions they're bound to
+ *
+ * A held key's repeats set off again only the actions that repeat (see Action::repeats).
+ * Whatever a press set off is let go of when that key or button is released, even if the
+ * bindings or modifiers have changed in the meantime. Everything is let go of when the window
+ * loses focus.
+ *
+ * Input: glutin::Event, Bindings
+ * Output: ActionEvent, HeldActions
+ */
+pub struct System {
+  window_event_sub_token: SubscriberToken<glutin::Event>,
+  held_keys: HashSet<VirtualKeyCode>,
+  pressed: HashMap<Trigger, Vec<Action>>,
+}
+// NOTE: This depends on a window emitter that lives in the main thread
+declare_dependencies!(System, []);
+standalone_installer_from_new!(System, Delta);
+
+impl System {
+  pub fn new(world: &mut specs::World) -> System {
+    world.add_resource::<Bindings>(Bindings::defaults());
+    world.add_resource::<BindingsPath>(BindingsPath(None));
+    world.add_resource::<HeldActions>(HeldActions(HashSet::new()));
+
+    System {
+      window_event_sub_token: world.register_subscriber::<glutin::Event>(),
+      held_keys: HashSet::new(),
+      pressed: HashMap::new(),
+    }
+  }
+
+  pub fn name() -> &'static str {
+    "input_map::System"
+  }
+
+  fn modifiers(&self) -> Modifiers {
+    use glutin::VirtualKeyCode::{LAlt, LControl, LShift, RAlt, RControl, RShift};
+
+    let held = |left, right| self.held_keys.contains(&left) || self.held_keys.contains(&right);
+    Modifiers {
+      ctrl: held(LControl, RControl),
+      shift: held(LShift, RShift),
+      alt: held(LAlt, RAlt),
+    }
+  }
+
+  fn is_held(&self, action: Action) -> bool {
+    self.pressed.values().any(|actions| actions.contains(&action))
+  }
+
+  fn press(&mut self, trigger: Trigger, bindings: &Bindings) -> Vec<ActionEvent> {
+    if let Some(actions) = self.pressed.get(&trigger) {
+      // Held keys repeat, which only sets off again the actions that repeat
+      return actions.iter()
+        .cloned()
+        .filter(|action| action.repeats())
+        .map(ActionEvent::Pressed)
+        .collect();
+    }
+
+    let actions = bindings.actions_for(trigger, &self.modifiers());
+    let events = actions.iter().cloned().map(ActionEvent::Pressed).collect();
+    self.pressed.insert(trigger, actions);
+    events
+  }
+
+  fn release(&mut self, trigger: Trigger) -> Vec<ActionEvent> {
+    self.pressed
+      .remove(&trigger)
+      .unwrap_or(Vec::new())
+      .into_iter()
+      .map(ActionEvent::Released)
+      .collect()
+  }
+
+  fn release_all(&mut self) -> Vec<ActionEvent> {
+    self.held_keys.clear();
+    self.pressed
+      .drain()
+      .flat_map(|(_, actions)| actions.into_iter())
+      .map(ActionEvent::Released)
+      .collect()
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+    use glutin::ElementState::{Pressed, Released};
+    use glutin::Event::{Focused, KeyboardInput, MouseInput};
+    use itertools::Itertools;
+
+    let (mut glutin_events, bindings, mut held_actions, mut action_events) = arg.fetch(|w| {
+      (w.fetch_subscriber(&self.window_event_sub_token).collected(),
+       w.read_resource::<Bindings>(),
+       w.write_resource::<HeldActions>(),
+       w.fetch_publisher::<ActionEvent>())
+    });
+
+    glutin_events.drain(..).foreach(|e| {
+      let events = match e {
+        KeyboardInput(Pressed, _, Some(key)) => {
+          let events = self.press(Trigger::Key(key), &bindings);
+          self.held_keys.insert(key);
+          events
+        },
+        KeyboardInput(Released, _, Some(key)) => {
+          self.held_keys.remove(&key);
+          self.release(Trigger::Key(key))
+        },
+        MouseInput(Pressed, button) => self.press(Trigger::Mouse(button), &bindings),
+        MouseInput(Released, button) => self.release(Trigger::Mouse(button)),
+        Focused(false) => self.release_all(),
+        _ => Vec::new(),
+      };
+
+      events.into_iter().foreach(|event| {
+        // Another key bound to the same action may still be down
+        let still_held = match event {
+          ActionEvent::Released(action) => self.is_held(action),
+          ActionEvent::Pressed(_) => false,
+        };
+        if !still_held {
+          action_events.push(event);
+        }
+      });
+    });
+
+    let held = self.pressed.values().flat_map(|actions| actions.iter().cloned()).collect();
+    *held_actions = HeldActions(held);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{ActionEvent, Action, Bindings, System, Trigger};
+  use glutin::VirtualKeyCode;
+  use specs;
+
+  #[test]
+  fn only_repeating_actions_are_set_off_by_a_held_key() {
+    let mut system = System::new(&mut specs::World::new());
+    let bindings = Bindings::defaults();
+    let (back, forward) = (Trigger::Key(VirtualKeyCode::Back), Trigger::Key(VirtualKeyCode::W));
+
+    system.press(forward, &bindings);
+    assert!(system.press(forward, &bindings).is_empty());
+
+    let backspace = vec![ActionEvent::Pressed(Action::ConsoleBackspace)];
+    assert_eq!(system.press(back, &bindings), backspace);
+    assert_eq!(system.press(back, &bindings), backspace);
+    assert_eq!(system.release(back), vec![ActionEvent::Released(Action::ConsoleBackspace)]);
+  }
+}
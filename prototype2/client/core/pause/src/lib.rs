@@ -1,5 +1,5 @@
 extern crate specs;
-extern crate glutin;
+extern crate input_map;
 extern crate itertools;
 extern crate pubsub;
 extern crate client_state as state;
@@ -8,6 +8,7 @@ extern crate automatic_system_installer;
 
 use pubsub::{PubSubStore, SubscriberToken};
 
+use input_map::{Action, ActionEvent};
 use state::Delta;
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -25,18 +26,25 @@ impl PauseState {
   }
 }
 
+/**
+ * Pauses and unpauses the game
+ *
+ * Opening the console pauses the game too, as the console is shown while paused.
+ *
+ * Input: ActionEvent
+ * Output: PauseState
+ */
 pub struct System {
-  window_event_sub_token: SubscriberToken<glutin::Event>,
+  action_event_sub_token: SubscriberToken<ActionEvent>,
 }
-// NOTE: This depends on a window emitter that lives in the main thread
-declare_dependencies!(System, []);
+declare_dependencies!(System, [input_map::System]);
 standalone_installer_from_new!(System, Delta);
 
 impl System {
   pub fn new(world: &mut specs::World) -> System {
     world.add_resource::<PauseState>(PauseState::NotPaused);
 
-    System { window_event_sub_token: world.register_subscriber::<glutin::Event>() }
+    System { action_event_sub_token: world.register_subscriber::<ActionEvent>() }
   }
 
   pub fn name() -> &'static str {
@@ -47,21 +55,17 @@ impl System {
 impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
     use itertools::Itertools;
-    use glutin::VirtualKeyCode::Escape;
-    use glutin::ElementState;
-    use glutin::Event::KeyboardInput;
 
-    let (mut glutin_events, mut pause_state) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.window_event_sub_token).collected(),
+    let (mut action_events, mut pause_state) = arg.fetch(|w| {
+      (w.fetch_subscriber(&self.action_event_sub_token).collected(),
        w.write_resource::<PauseState>())
     });
 
 
-    glutin_events.drain(..).foreach(|e| {
+    action_events.drain(..).foreach(|e| {
       match e {
-        KeyboardInput(ElementState::Pressed, _, Some(Escape)) => {
-          *pause_state = pause_state.toggled()
-        },
+        ActionEvent::Pressed(Action::TogglePause) => *pause_state = pause_state.toggled(),
+        ActionEvent::Pressed(Action::OpenConsole) => *pause_state = PauseState::Paused,
         _ => {}, // I threw it on the ground
       }
     });
@@ -14,6 +14,7 @@ extern crate console;
 extern crate pause;
 extern crate debug;
 extern crate camera;
+extern crate synchronization;
 extern crate client_state as state;
 extern crate automatic_system_installer;
 
@@ -13,8 +13,7 @@ use gfx_device_gl;
 use debug;
 use pause;
 use console;
-use cgmath::{Matrix4, Rad};
-use cgmath::Euler;
+use cgmath::{Matrix4, Quaternion};
 use cgmath::Transform;
 use cgmath::SquareMatrix;
 use cgmath::Matrix;
@@ -120,8 +119,9 @@ impl OpenGlRenderer {
                    0.0, 1.02, 0.0,  0.0,
                    0.0, 0.0, 1.02, 0.0,
                    x, y, (z + 1.0), 1.0);
-    let (rx, ry, rz) = physical_aspect.ang;
-    let rotation = Matrix4::from(Euler::new(Rad::new(-rx), Rad::new(-rz), Rad::new(-ry)));
+    // The scene is drawn in game space, so the orientation applies as is
+    let (w, rx, ry, rz) = physical_aspect.ang;
+    let rotation = Matrix4::from(Quaternion::new(w, rx, ry, rz));
     let model = translation.concat(&rotation);
 
     let &mut (ref mut data, ref slice) = self.models.get_mut(&render_aspect.model).unwrap();
@@ -13,7 +13,8 @@ use camera;
 use glutin;
 use debug;
 use state::OwnEntity;
-use common::aspects::{DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
+use synchronization::InterpolatedAspect;
+use common::aspects::{DisabledAspect, RenderAspect, SynchronizedAspect};
 use opengl::OpenGlRenderer;
 
 // NOTE: This isn't a "real" system. It's not Send, so it has to be invoked in
@@ -43,7 +44,7 @@ impl System {
          console_log,
          synchronized,
          entities,
-         physical,
+         interpolated,
          disabled,
          render) = (w.write_resource::<glutin::Window>(),
                     w.read_resource::<camera::CameraPos>(),
@@ -54,12 +55,15 @@ impl System {
                     w.read_resource::<console::ConsoleLog>(),
                     w.read::<SynchronizedAspect>(),
                     w.entities(),
-                    w.read::<PhysicalAspect>(),
+                    w.read::<InterpolatedAspect>(),
                     w.read::<DisabledAspect>(),
                     w.read::<RenderAspect>());
 
-    let camera_target = SelfRetriever::new(own_entity.clone(), &entities, &synchronized, &physical)
-      .find_own_pos();
+    // Entities are drawn where they've been interpolated to, rather than where the latest
+    // snapshot put them
+    let camera_target =
+      SelfRetriever::new(own_entity.clone(), &entities, &synchronized, &interpolated)
+        .find_own_pos();
 
     RenderWrapper::new(&mut self.renderer,
                        encoder,
@@ -71,7 +75,7 @@ impl System {
                        &command_buffer,
                        &console_log,
                        &render,
-                       &physical,
+                       &interpolated,
                        &disabled)
       .render_frame();
 
@@ -92,21 +96,21 @@ struct SelfRetriever<'a> {
   own_entity: Option<OwnEntity>,
   entities: &'a specs::Entities<'a>,
   synchronized: &'a AspectStorageRead<'a, SynchronizedAspect>,
-  physical: &'a AspectStorageRead<'a, PhysicalAspect>,
+  interpolated: &'a AspectStorageRead<'a, InterpolatedAspect>,
 }
 
 impl<'a> SelfRetriever<'a> {
   pub fn new(own_entity: Option<OwnEntity>,
              entities: &'a specs::Entities<'a>,
              synchronized: &'a AspectStorageRead<'a, SynchronizedAspect>,
-             physical: &'a AspectStorageRead<'a, PhysicalAspect>)
+             interpolated: &'a AspectStorageRead<'a, InterpolatedAspect>)
              -> SelfRetriever<'a> {
 
     SelfRetriever {
       own_entity: own_entity,
       entities: entities,
       synchronized: synchronized,
-      physical: physical,
+      interpolated: interpolated,
     }
   }
 
@@ -125,8 +129,8 @@ impl<'a> SelfRetriever<'a> {
           .next()
       })
       .map(|(entity, _)| entity)
-      .and_then(|true_ent| self.physical.get(true_ent))
-      .map(|physical_aspect| physical_aspect.pos)
+      .and_then(|true_ent| self.interpolated.get(true_ent))
+      .map(|&InterpolatedAspect(ref physical_aspect)| physical_aspect.pos)
   }
 }
 
@@ -144,7 +148,7 @@ struct RenderWrapper<'a> {
   command_buffer: &'a console::CommandBuffer,
   console_log: &'a console::ConsoleLog,
   render: &'a AspectStorageRead<'a, RenderAspect>,
-  physical: &'a AspectStorageRead<'a, PhysicalAspect>,
+  interpolated: &'a AspectStorageRead<'a, InterpolatedAspect>,
   disabled: &'a AspectStorageRead<'a, DisabledAspect>,
 }
 
@@ -159,7 +163,7 @@ impl<'a> RenderWrapper<'a> {
              command_buffer: &'a console::CommandBuffer,
              console_log: &'a console::ConsoleLog,
              render: &'a AspectStorageRead<'a, RenderAspect>,
-             physical: &'a AspectStorageRead<'a, PhysicalAspect>,
+             interpolated: &'a AspectStorageRead<'a, InterpolatedAspect>,
              disabled: &'a AspectStorageRead<'a, DisabledAspect>)
              -> RenderWrapper<'a> {
     RenderWrapper {
@@ -173,7 +177,7 @@ impl<'a> RenderWrapper<'a> {
       command_buffer: command_buffer,
       console_log: console_log,
       render: render,
-      physical: physical,
+      interpolated: interpolated,
       disabled: disabled,
     }
   }
@@ -190,9 +194,9 @@ impl<'a> RenderWrapper<'a> {
                                &(x, y, z),
                                self.camera_target);
 
-    (self.physical, self.disabled.not(), self.render)
+    (self.interpolated, self.disabled.not(), self.render)
       .iter()
-      .foreach(|(physical_aspect, _, render_aspect)| {
+      .foreach(|(&InterpolatedAspect(ref physical_aspect), _, render_aspect)| {
         self.renderer.render_model(&mut self.encoder,
                                    &mut self.window,
                                    physical_aspect,
@@ -0,0 +1,242 @@
+use specs;
+
+use std::collections::{BTreeMap, HashMap};
+
+use common::aspects::{PhysicalAspect, SynchronizedAspect};
+use common::network::{LinkStats, now_ms};
+use common::space;
+use itertools::Itertools;
+use network;
+use state::Delta;
+
+/// How far behind the server's clock remote entities are drawn by default, in milliseconds
+pub const DEFAULT_INTERPOLATION_DELAY_MS: u64 = 100;
+
+/// How many received snapshots are kept to interpolate between
+const MAX_BUFFERED_SNAPSHOTS: usize = 32;
+
+/// Longest an entity is carried forward on its last known velocity when snapshots stop coming
+const MAX_EXTRAPOLATION_MS: f64 = 250.0;
+
+/**
+ * How far behind the server's clock remote entities are drawn, in milliseconds
+ *
+ * Should be a couple of snapshot intervals, so that there is nearly always a snapshot on either
+ * side of the time being drawn, even when one is lost.
+ */
+#[derive(Debug, Clone)]
+pub struct InterpolationDelay(pub u64);
+
+/**
+ * Where an entity is drawn, as opposed to where the latest snapshot put it
+ */
+#[derive(Debug, Clone)]
+pub struct InterpolatedAspect(pub PhysicalAspect);
+
+impl specs::Component for InterpolatedAspect {
+  type Storage = specs::VecStorage<InterpolatedAspect>;
+}
+
+/**
+ * The physical aspects from a single snapshot, and when the server took it
+ */
+struct BufferedSnapshot {
+  server_ms: u64,
+  physical: HashMap<String, PhysicalAspect>,
+}
+
+/**
+ * The last few snapshots received, keyed by the server tick they were taken on
+ */
+pub struct SnapshotBuffer {
+  snapshots: BTreeMap<u64, BufferedSnapshot>,
+}
+
+impl SnapshotBuffer {
+  pub fn new() -> SnapshotBuffer {
+    SnapshotBuffer { snapshots: BTreeMap::new() }
+  }
+
+  /**
+   * Holds on to a snapshot's physical aspects (keyed by stringified synchro), forgetting the
+   * oldest snapshot once the buffer is full
+   */
+  pub fn insert(&mut self, tick: u64, server_ms: u64, physical: HashMap<String, PhysicalAspect>) {
+    self.snapshots.insert(tick,
+                          BufferedSnapshot {
+                            server_ms: server_ms,
+                            physical: physical,
+                          });
+
+    while self.snapshots.len() > MAX_BUFFERED_SNAPSHOTS {
+      let oldest = self.snapshots.keys().next().cloned().unwrap();
+      self.snapshots.remove(&oldest);
+    }
+  }
+
+  /**
+   * When the server took the newest snapshot, by its clock
+   */
+  pub fn newest_server_ms(&self) -> Option<u64> {
+    self.snapshots.values().next_back().map(|snapshot| snapshot.server_ms)
+  }
+
+  /**
+   * Works out where an entity was at the given time (by the server's clock)
+   *
+   * Blends the two snapshots bracketing that time. Past the newest snapshot the entity carries on
+   * at its last velocity for a short while, and then holds still until snapshots resume.
+   */
+  pub fn sample(&self, synchro: &SynchronizedAspect, server_ms: f64) -> Option<PhysicalAspect> {
+    let key = synchro.to_string();
+    let mut before = None;
+    let mut after = None;
+
+    for snapshot in self.snapshots.values() {
+      if let Some(aspect) = snapshot.physical.get(&key) {
+        if (snapshot.server_ms as f64) <= server_ms {
+          before = Some((snapshot.server_ms as f64, aspect));
+        } else {
+          after = Some((snapshot.server_ms as f64, aspect));
+          break;
+        }
+      }
+    }
+
+    match (before, after) {
+      (Some((from_ms, from)), Some((to_ms, to))) => {
+        let fraction = ((server_ms - from_ms) / (to_ms - from_ms)) as f32;
+        Some(interpolate(from, to, fraction))
+      },
+      (Some((from_ms, from)), None) => {
+        let elapsed_ms = (server_ms - from_ms).min(MAX_EXTRAPOLATION_MS);
+        Some(extrapolate(from, (elapsed_ms / 1000.0) as f32))
+      },
+      (None, Some((_, to))) => Some(to.clone()),
+      (None, None) => None,
+    }
+  }
+}
+
+fn interpolate(from: &PhysicalAspect, to: &PhysicalAspect, fraction: f32) -> PhysicalAspect {
+  let mut aspect = to.clone();
+  aspect.pos = lerp(from.pos, to.pos, fraction);
+  aspect.ang = space::slerp(from.ang, to.ang, fraction);
+  aspect
+}
+
+fn extrapolate(from: &PhysicalAspect, dt_s: f32) -> PhysicalAspect {
+  let mut aspect = from.clone();
+  aspect.pos = (from.pos.0 + from.vel.0 * dt_s,
+                from.pos.1 + from.vel.1 * dt_s,
+                from.pos.2 + from.vel.2 * dt_s);
+  aspect.ang = space::integrate(from.ang, from.ang_vel, dt_s);
+  aspect
+}
+
+fn lerp(from: (f32, f32, f32), to: (f32, f32, f32), fraction: f32) -> (f32, f32, f32) {
+  (from.0 + (to.0 - from.0) * fraction,
+   from.1 + (to.1 - from.1) * fraction,
+   from.2 + (to.2 - from.2) * fraction)
+}
+
+/**
+ * Places each synchronized entity where it was a short delay ago, by the server's clock
+ *
+ * Drawing slightly in the past means motion can be blended between snapshots that have already
+ * arrived, instead of jumping whenever one does. Until the server's clock offset is known,
+ * entities are drawn where the latest snapshot put them.
+ *
+ * Input: SnapshotBuffer, InterpolationDelay, LinkStats, PhysicalAspect, SynchronizedAspect
+ * Output: InterpolatedAspect
+ */
+pub struct System;
+declare_dependencies!(System, [super::System, network::KeepAliveSystem]);
+standalone_installer_from_new!(System, Delta);
+
+impl System {
+  pub fn new(world: &mut specs::World) -> System {
+    world.add_resource::<InterpolationDelay>(InterpolationDelay(DEFAULT_INTERPOLATION_DELAY_MS));
+    System
+  }
+
+  pub fn name() -> &'static str {
+    "synchronization::InterpolationSystem"
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+    use specs::Join;
+
+    let (buffer, delay, link_stats, entities, synchronized, physical, mut interpolated) =
+      arg.fetch(|w| {
+        (w.read_resource::<SnapshotBuffer>(),
+         w.read_resource::<InterpolationDelay>(),
+         w.read_resource::<LinkStats>(),
+         w.entities(),
+         w.read::<SynchronizedAspect>(),
+         w.read::<PhysicalAspect>(),
+         w.write::<InterpolatedAspect>())
+      });
+
+    let render_server_ms = if link_stats.samples > 0 {
+      Some(now_ms() as f64 + link_stats.clock_offset_ms - delay.0 as f64)
+    } else {
+      None
+    };
+
+    (&entities, &synchronized, &physical).iter().foreach(|(entity, synchro, aspect)| {
+      let drawn = render_server_ms.and_then(|server_ms| buffer.sample(synchro, server_ms))
+        .unwrap_or_else(|| aspect.clone());
+      interpolated.insert(entity, InterpolatedAspect(drawn));
+    });
+
+    let undrawn = (&entities, &interpolated, !&physical)
+      .iter()
+      .map(|(entity, _, _)| entity)
+      .collect::<Vec<specs::Entity>>();
+    undrawn.into_iter().foreach(|entity| {
+      interpolated.remove(entity);
+    });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::SnapshotBuffer;
+  use itertools::Itertools;
+  use std::collections::HashMap;
+  use common::aspects::{PhysicalAspect, SynchronizedAspect};
+
+  fn buffer_with(synchro: &SynchronizedAspect, positions: Vec<(u64, u64, f32)>) -> SnapshotBuffer {
+    let mut buffer = SnapshotBuffer::new();
+    positions.into_iter().foreach(|(tick, server_ms, x)| {
+      let mut physical = HashMap::new();
+      physical.insert(synchro.to_string(),
+                      PhysicalAspect::new((x, 0.0, 0.0), (10.0, 0.0, 0.0)));
+      buffer.insert(tick, server_ms, physical);
+    });
+    buffer
+  }
+
+  #[test]
+  fn blends_between_bracketing_snapshots() {
+    let synchro = SynchronizedAspect::new();
+    let buffer = buffer_with(&synchro, vec![(1, 1000, 0.0), (2, 1050, 1.0), (3, 1100, 3.0)]);
+
+    assert_eq!(buffer.sample(&synchro, 1025.0).unwrap().pos, (0.5, 0.0, 0.0));
+    assert_eq!(buffer.sample(&synchro, 1075.0).unwrap().pos, (2.0, 0.0, 0.0));
+    assert_eq!(buffer.sample(&synchro, 900.0).unwrap().pos, (0.0, 0.0, 0.0));
+    assert_eq!(buffer.sample(&SynchronizedAspect::new(), 1025.0), None);
+  }
+
+  #[test]
+  fn extrapolates_briefly_past_the_newest_snapshot() {
+    let synchro = SynchronizedAspect::new();
+    let buffer = buffer_with(&synchro, vec![(1, 1000, 0.0), (2, 1050, 1.0)]);
+
+    assert_eq!(buffer.sample(&synchro, 1150.0).unwrap().pos, (2.0, 0.0, 0.0));
+    assert_eq!(buffer.sample(&synchro, 5000.0).unwrap().pos, (3.5, 0.0, 0.0));
+  }
+}
@@ -8,9 +8,20 @@ extern crate client_state as state;
 #[macro_use(declare_dependencies, standalone_installer_from_new)]
 extern crate automatic_system_installer;
 
+mod interpolation;
+mod prediction;
+
+pub use interpolation::{DEFAULT_INTERPOLATION_DELAY_MS, InterpolatedAspect, InterpolationDelay,
+                        SnapshotBuffer};
+pub use interpolation::System as InterpolationSystem;
+pub use prediction::System as PredictionSystem;
+
 use state::{Delta, OwnEntity};
+use common::codec::CodecKind;
 use common::protocol::{ClientNetworkEvent, SnapshotEvent};
 use common::aspects::CommonWorld;
+use common::snapshot::WorldDelta;
+use common::util::Newness;
 use network::FragmentBuffer;
 use network::Defragmentable;
 use itertools::Itertools;
@@ -27,12 +38,30 @@ type AspectStorageRead<'a, T> = specs::Storage<T,
 type AspectStorageWrite<'a, T> = specs::Storage<T,
                                                 RwLockReadGuard<'a, specs::Allocator>,
                                                 RwLockWriteGuard<'a, specs::MaskedStorage<T>>>;
+/**
+ * How many rebuilt snapshots we hold on to as potential baselines for incoming deltas
+ *
+ * Should comfortably exceed the server's own window of unacked snapshots.
+ */
+const MAX_RETAINED_SNAPSHOTS: u16 = 128;
+
+/**
+ * The latest of our own moves that the server included in the latest snapshot
+ */
+#[derive(Debug, Clone)]
+pub struct AckedInput(pub Option<u32>);
+
 /**
  * Collects state snapshot messages from the server, collating them and integrating them into
  * current client state
+ *
+ * Snapshots arrive as deltas against a world we've acked, so the worlds we've rebuilt are kept
+ * around (by seq_num) until the server can no longer diff against them. Each rebuilt world is
+ * also buffered by server tick, for the InterpolationSystem to draw from.
  */
 pub struct System {
   partial_snapshot: FragmentBuffer,
+  acked_worlds: HashMap<u16, CommonWorld>,
   snapshot_event_sub_token: SubscriberToken<SnapshotEvent>,
 }
 declare_dependencies!(System, [network::EventDistributionSystem]);
@@ -40,8 +69,12 @@ standalone_installer_from_new!(System, Delta);
 
 impl System {
   pub fn new(world: &mut specs::World) -> System {
+    world.add_resource::<SnapshotBuffer>(SnapshotBuffer::new());
+    world.add_resource::<AckedInput>(AckedInput(None));
+
     System {
       partial_snapshot: FragmentBuffer::None,
+      acked_worlds: HashMap::new(),
       snapshot_event_sub_token: world.register_subscriber::<SnapshotEvent>(),
     }
   }
@@ -51,8 +84,11 @@ impl System {
   }
 
   fn process_snapshots(&mut self,
+                       codec: CodecKind,
                        snapshot_events: &mut Vec<SnapshotEvent>,
-                       outbound_events: &mut Publisher<ClientNetworkEvent>)
+                       outbound_events: &mut Publisher<ClientNetworkEvent>,
+                       snapshot_buffer: &mut SnapshotBuffer,
+                       acked_input: &mut AckedInput)
                        -> Option<CommonWorld> {
     // Order the events by seq_num and idx (so we dont drop messages when we get
     // them out of order)
@@ -63,20 +99,57 @@ impl System {
         match event {
           SnapshotEvent::PartialSnapshot(state_fragment) => {
             self.partial_snapshot.integrate(state_fragment);
-            let world_opt = CommonWorld::defragment(&self.partial_snapshot);
-            if world_opt.is_some() {
-              let (seq_num, world) = world_opt.unwrap();
-              outbound_events.push(ClientNetworkEvent::SnapshotAck(seq_num));
-              Some(world)
-            } else {
-              None
-            }
+            WorldDelta::defragment(&self.partial_snapshot, codec)
+              .and_then(|(seq_num, delta)| {
+                let (tick, server_ms, last_input) = (delta.tick, delta.server_ms, delta.last_input);
+                self.apply_delta(seq_num, delta).map(|world| {
+                  outbound_events.push(ClientNetworkEvent::SnapshotAck(seq_num));
+                  snapshot_buffer.insert(tick, server_ms, world.physical.clone());
+                  *acked_input = AckedInput(last_input);
+                  world
+                })
+              })
           },
         }
       })
       .last()
   }
 
+  /**
+   * Rebuilds the world the server sent from the baseline it was diffed against
+   *
+   * Deltas against a baseline we no longer hold can't be applied, and are dropped without an ack.
+   * The server falls back to a full snapshot once its baseline gets old enough.
+   */
+  fn apply_delta(&mut self, seq_num: u16, delta: WorldDelta) -> Option<CommonWorld> {
+    let baseline_idx = delta.baseline.clone();
+    let baseline_world = match baseline_idx {
+      Some(idx) => self.acked_worlds.get(&idx).cloned(),
+      None => Some(CommonWorld::new()),
+    };
+
+    baseline_world.map(|baseline_world| {
+      let world = delta.apply_to(&baseline_world);
+
+      // The server only diffs against the newest ack it has seen, so anything older than the
+      // baseline it just used will never be needed again
+      let forgotten = self.acked_worlds
+        .keys()
+        .cloned()
+        .filter(|idx| {
+          let older_than_baseline = baseline_idx.map(|b| b.is_newer_than(idx)).unwrap_or(false);
+          older_than_baseline || seq_num.wrapping_sub(*idx) >= MAX_RETAINED_SNAPSHOTS
+        })
+        .collect::<Vec<u16>>();
+      forgotten.iter().foreach(|idx| {
+        self.acked_worlds.remove(idx);
+      });
+
+      self.acked_worlds.insert(seq_num, world.clone());
+      world
+    })
+  }
+
   fn incorporate_recvd_synchros(&mut self,
                                 arg: &specs::RunArg,
                                 world: &mut CommonWorld,
@@ -142,16 +215,22 @@ impl System {
 
 impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    let (mut snapshot_events,
+    let (codec,
+         mut snapshot_events,
          mut outbound_events,
+         mut snapshot_buffer,
+         mut acked_input,
          mut own_entity,
          entities,
          synchronized,
          mut disabled,
          mut physical,
          mut rendered) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.snapshot_event_sub_token).collected(),
+      (*w.read_resource::<CodecKind>(),
+       w.fetch_subscriber(&self.snapshot_event_sub_token).collected(),
        w.fetch_publisher::<ClientNetworkEvent>(),
+       w.write_resource::<SnapshotBuffer>(),
+       w.write_resource::<AckedInput>(),
        w.write_resource::<Option<OwnEntity>>(),
        w.entities(),
        w.write::<SynchronizedAspect>(),
@@ -160,7 +239,11 @@ impl specs::System<Delta> for System {
        w.write::<RenderAspect>())
     });
 
-    let last_world = self.process_snapshots(&mut snapshot_events, &mut outbound_events);
+    let last_world = self.process_snapshots(codec,
+                                            &mut snapshot_events,
+                                            &mut outbound_events,
+                                            &mut snapshot_buffer,
+                                            &mut acked_input);
 
     if let Some(mut world) = last_world {
 
@@ -0,0 +1,170 @@
+use specs;
+
+use std::collections::VecDeque;
+
+use common::aspects::{PhysicalAspect, SynchronizedAspect};
+use common::movement;
+use common::network::{LinkStats, now_ms};
+use interpolation::{self, InterpolatedAspect, SnapshotBuffer};
+use itertools::Itertools;
+use state::{Delta, InputLog, OwnEntity, PendingInput};
+use super::AckedInput;
+
+/// Furthest the player's own entity is run ahead of the latest snapshot
+const MAX_PREDICTION_MS: f64 = 500.0;
+
+/// Longest stretch the local simulation covers in a single step, in seconds
+const PREDICTION_STEP_S: f32 = 0.016;
+
+/**
+ * Runs the player's own entity forward from where the latest snapshot put it, steering it with
+ * the input the server had applied and then with each input it hadn't, from when it was sent
+ *
+ * The local simulation only steers and carries the entity along, so anything else the server
+ * does to it (collisions, gravity) shows up as a correction when the next snapshot arrives.
+ */
+fn predict(from: &PhysicalAspect,
+           from_ms: f64,
+           held: Option<&PendingInput>,
+           inputs: &VecDeque<PendingInput>,
+           now_ms: f64)
+           -> PhysicalAspect {
+  let mut aspect = from.clone();
+  let mut movement = held.map(|input| input.movement);
+  let mut sim_ms = from_ms.max(now_ms - MAX_PREDICTION_MS).min(now_ms);
+  inputs.iter().foreach(|input| {
+    let input_ms = (input.sent_ms as f64).max(sim_ms).min(now_ms);
+    simulate(&mut aspect, movement, ((input_ms - sim_ms) / 1000.0) as f32);
+    sim_ms = input_ms;
+    movement = Some(input.movement);
+  });
+  simulate(&mut aspect, movement, ((now_ms - sim_ms) / 1000.0) as f32);
+
+  aspect
+}
+
+fn simulate(aspect: &mut PhysicalAspect, movement: Option<(f32, f32, f32)>, dt_s: f32) {
+  let mut remaining_s = dt_s;
+  while remaining_s > 0.0 {
+    let step_s = remaining_s.min(PREDICTION_STEP_S);
+    if let Some(movement) = movement {
+      aspect.vel = movement::steer(aspect.vel, movement, step_s);
+    }
+    aspect.pos = (aspect.pos.0 + aspect.vel.0 * step_s,
+                  aspect.pos.1 + aspect.vel.1 * step_s,
+                  aspect.pos.2 + aspect.vel.2 * step_s);
+    remaining_s = remaining_s - step_s;
+  }
+}
+
+/**
+ * Draws the player's own entity where their inputs will have taken it, rather than where the
+ * server last saw it
+ *
+ * Each snapshot says which of our inputs it includes. Those are forgotten, and the rest are
+ * replayed on top of the snapshot, so the server's view always wins once it catches up.
+ *
+ * Input: AckedInput, InputLog, SnapshotBuffer, LinkStats, OwnEntity, PhysicalAspect
+ * Output: InterpolatedAspect (own entity only)
+ */
+pub struct System;
+// NOTE: Runs after the InterpolationSystem so that our own entity isn't drawn in the past
+declare_dependencies!(System, [interpolation::System]);
+standalone_installer_from_new!(System, Delta);
+
+impl System {
+  pub fn new(_: &mut specs::World) -> System {
+    System
+  }
+
+  pub fn name() -> &'static str {
+    "synchronization::PredictionSystem"
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+    use specs::Join;
+
+    let (acked_input,
+         mut input_log,
+         buffer,
+         link_stats,
+         own_entity,
+         entities,
+         synchronized,
+         physical,
+         mut interpolated) = arg.fetch(|w| {
+      (w.read_resource::<AckedInput>(),
+       w.write_resource::<InputLog>(),
+       w.read_resource::<SnapshotBuffer>(),
+       w.read_resource::<LinkStats>(),
+       w.read_resource::<Option<OwnEntity>>(),
+       w.entities(),
+       w.read::<SynchronizedAspect>(),
+       w.read::<PhysicalAspect>(),
+       w.write::<InterpolatedAspect>())
+    });
+
+    if let AckedInput(Some(seq)) = *acked_input {
+      input_log.acknowledge(seq);
+    }
+
+    let own_synchro = match *own_entity {
+      Some(OwnEntity(ref synchro)) => synchro,
+      None => return,
+    };
+
+    let now = now_ms() as f64;
+    // Until the server's clock offset is known, the latest snapshot is taken to be current
+    let snapshot_ms = if link_stats.samples > 0 {
+      buffer.newest_server_ms().map(|server_ms| link_stats.to_local_ms(server_ms)).unwrap_or(now)
+    } else {
+      now
+    };
+
+    (&entities, &synchronized, &physical)
+      .iter()
+      .filter(|&(_, synchro, _)| synchro == own_synchro)
+      .foreach(|(entity, _, aspect)| {
+        let predicted =
+          predict(aspect, snapshot_ms, input_log.held(), input_log.pending(), now);
+        interpolated.insert(entity, InterpolatedAspect(predicted));
+      });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::predict;
+  use common::aspects::PhysicalAspect;
+  use common::movement::{MAX_WALK_ACCELERATION, MAX_WALK_SPEED};
+  use state::InputLog;
+
+  #[test]
+  fn replays_unacknowledged_inputs() {
+    let snapshot = PhysicalAspect::new((0.0, 0.0, 0.0), (0.0, 0.0, 0.0));
+    let mut log = InputLog::new();
+    let applied = log.record((0.0, 0.0, 0.0), 900);
+    log.record((1.0, 0.0, 0.0), 1000);
+    log.acknowledge(applied);
+
+    // Standing still until the unapplied input, then speeding up for 100ms
+    let predicted = predict(&snapshot, 1000.0, log.held(), log.pending(), 1100.0);
+    assert!((predicted.vel.0 - MAX_WALK_ACCELERATION * 0.1).abs() < 0.0001);
+    assert!(predicted.pos.0 > 0.09 && predicted.pos.0 < 0.12);
+    assert_eq!(predicted.pos.1, 0.0);
+  }
+
+  #[test]
+  fn keeps_steering_with_the_applied_input() {
+    let snapshot = PhysicalAspect::new((0.0, 0.0, 0.0), (MAX_WALK_SPEED, 0.0, 0.0));
+    let mut log = InputLog::new();
+    let applied = log.record((1.0, 0.0, 0.0), 900);
+    log.acknowledge(applied);
+
+    let predicted = predict(&snapshot, 1000.0, log.held(), log.pending(), 1100.0);
+    assert_eq!(predicted.vel, (MAX_WALK_SPEED, 0.0, 0.0));
+    assert!((predicted.pos.0 - MAX_WALK_SPEED * 0.1).abs() < 0.0001);
+  }
+}
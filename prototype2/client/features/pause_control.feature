@@ -1,7 +1,8 @@
 Feature: Pausing and Unpausing
   Background:
     Given an engine with:
-      | pause::System |
+      | input_map::System |
+      | pause::System     |
 
   Scenario: Hitting esc while paused
     Given the game is set as paused
@@ -55,6 +55,7 @@ fn build_system_lookup() -> HashMap<String, Box<Fn(&mut AutoInstaller<Delta>) +
   let mut map = HashMap::new();
   insert_system!(map, network::EventDistributionSystem);
   insert_system!(map, network::ConnectionSystem);
+  insert_system!(map, input_map::System);
   insert_system!(map, pause::System);
   insert_system!(map, player::PreprocessorSystem);
   insert_system!(map, camera::PreprocessorSystem);
@@ -61,18 +61,11 @@ pub fn register_steps(c: &mut CucumberRegistrar<ClientWorld>) {
 fn check_for_domain_event(events: &Vec<ClientNetworkEvent>, row: &Vec<String>) {
   match row.get(1).map(|x| x.deref()) {
     None => panic!("Need domain event type as second column"),
-    Some("SelfMove") => {
+    Some("PlayerInput") => {
       match (row.get(2).and_then(|x| f32::from_str(x).ok()),
              row.get(3).and_then(|y| f32::from_str(y).ok()),
              row.get(4).and_then(|z| f32::from_str(z).ok())) {
-        (Some(x), Some(y), Some(z)) => {
-          check_for_exact_domain_event(events,
-                                       ClientEvent::SelfMove {
-                                         x_d: x,
-                                         y_d: y,
-                                         z_d: z,
-                                       })
-        },
+        (Some(x), Some(y), Some(z)) => check_for_player_input(events, (x, y, z)),
         _ => panic!("Need numbers for third, fourth, and fifth column"),
       }
     },
@@ -80,9 +73,18 @@ fn check_for_domain_event(events: &Vec<ClientNetworkEvent>, row: &Vec<String>) {
   }
 }
 
-fn check_for_exact_domain_event(events: &Vec<ClientNetworkEvent>, self_move: ClientEvent) {
-  let full_event = ClientNetworkEvent::DomainEvent(self_move);
-  assert!(events.iter().any(|x| *x == full_event),
-          "Expect list to contain event {:?}",
-          full_event);
+// Inputs are numbered as they're sampled, so any number will do
+fn check_for_player_input(events: &Vec<ClientNetworkEvent>, expected: (f32, f32, f32)) {
+  let close = |a: f32, b: f32| (a - b).abs() < 0.00001;
+  assert!(events.iter().any(|x| {
+            match x {
+              &ClientNetworkEvent::DomainEvent(ClientEvent::PlayerInput(ref input)) => {
+                let (x, y, z) = input.movement;
+                close(x, expected.0) && close(y, expected.1) && close(z, expected.2)
+              },
+              _ => false,
+            }
+          }),
+          "Expect list to contain a PlayerInput moving {:?}",
+          expected);
 }
@@ -4,9 +4,20 @@ use support::{ClientWorld, window};
 use std::str::FromStr;
 use pubsub::PubSubStore;
 use glutin::{ElementState, Event, VirtualKeyCode, Window};
+use client::input_map::{Action, Binding, Bindings};
 use mouse_lock::RelativeMouseMovementEvent;
 
 pub fn register_steps(c: &mut CucumberRegistrar<ClientWorld>) {
+  Given!(c,
+         "^(.*) is bound to only \'(.*)\'$",
+         |_, world: &mut ClientWorld, (action, ch): (String, String)| {
+    let action = Action::from_str(&action).unwrap();
+    let code = window::str_to_virtual_key_code(&ch).unwrap();
+    let mut bindings = world.planner.mut_world().write_resource::<Bindings>();
+
+    bindings.unbind_all(action);
+    bindings.bind(action, Binding::key(code));
+  });
 
   When!(c,
         "^the \'(.*)\' key is pressed$",
@@ -14,7 +25,15 @@ pub fn register_steps(c: &mut CucumberRegistrar<ClientWorld>) {
     let code = window::str_to_virtual_key_code(&ch).unwrap();
     let mut key_pub = world.planner.mut_world().fetch_publisher::<Event>();
 
-    key_pub.push(Event::KeyboardInput(ElementState::Pressed, 0, Some(code.clone())));
+    key_pub.push(Event::KeyboardInput(ElementState::Pressed, 0, Some(code)));
+  });
+
+  When!(c,
+        "^the \'(.*)\' key is released$",
+        |_, world: &mut ClientWorld, (ch,): (String,)| {
+    let code = window::str_to_virtual_key_code(&ch).unwrap();
+    let mut key_pub = world.planner.mut_world().fetch_publisher::<Event>();
+
     key_pub.push(Event::KeyboardInput(ElementState::Released, 0, Some(code)));
   });
 
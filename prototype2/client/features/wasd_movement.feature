@@ -1,6 +1,7 @@
 Feature: WASD player controls
   Background:
     Given an engine with:
+      | input_map::System          |
       | pause::System              |
       | camera::MovementSystem     |
       | player::MoveSystem         |
@@ -8,44 +9,67 @@ Feature: WASD player controls
     And we monitor outgoing network events
     And the camera is at 1, 0, 0
 
-  Scenario: W emits the forward moving event
+  Scenario: W moves forward
     When the 'W' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | -0.1 | 0 | 0 |
+      | DomainEvent | PlayerInput | -1 | 0 | 0 |
 
-  Scenario: A emits the left moving event
+  Scenario: A moves left
     When the 'A' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | 0 | -0.1 | 0 |
+      | DomainEvent | PlayerInput | 0 | -1 | 0 |
 
-  Scenario: S emits the backward moving event
+  Scenario: S moves backward
     When the 'S' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | 0.1 | 0 | 0 |
+      | DomainEvent | PlayerInput | 1 | 0 | 0 |
 
-  Scenario: D emits the right moving event
+  Scenario: D moves right
     When the 'D' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | 0 | 0.1 | 0 |
+      | DomainEvent | PlayerInput | 0 | 1 | 0 |
 
   Scenario: The movements are camera relative
     Given the camera is at 2, 2, 0
     When the 'W' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | -0.07071068 | -0.07071068 | 0 |
+      | DomainEvent | PlayerInput | -0.70710677 | -0.70710677 | 0 |
 
-  Scenario: More than one may be emitted at once
+  Scenario: Keys move the way they're bound
+    Given move_forward is bound to only 'A'
+    When the 'A' key is pressed
+    And the engine runs once
+    Then the following network events are emitted:
+      | DomainEvent | PlayerInput | -1 | 0 | 0 |
+
+  Scenario: Opposite keys cancel out
     When the 'W' key is pressed
     And the 'S' key is pressed
     And the engine runs once
     Then the following network events are emitted:
-      | DomainEvent | SelfMove | 0.1 | 0 | 0 |
-      | DomainEvent | SelfMove | -0.1 | 0 | 0 |
+      | DomainEvent | PlayerInput | 0 | 0 | 0 |
+
+  Scenario: Held keys keep moving
+    When the 'W' key is pressed
+    And the engine runs once
+    And the engine runs once
+    Then the following network events are emitted:
+      | DomainEvent | PlayerInput | -1 | 0 | 0 |
+      | DomainEvent | PlayerInput | -1 | 0 | 0 |
+
+  Scenario: Releasing a key stops the movement
+    When the 'W' key is pressed
+    And the engine runs once
+    And the 'W' key is released
+    And the engine runs once
+    Then the following network events are emitted:
+      | DomainEvent | PlayerInput | -1 | 0 | 0 |
+      | DomainEvent | PlayerInput | 0 | 0 | 0 |
 
   Scenario: No movement keys work while paused
     When the game is set as paused
@@ -54,4 +78,5 @@ Feature: WASD player controls
     And the 'S' key is pressed
     And the 'D' key is pressed
     And the engine runs once
-    Then no network events are emitted
+    Then the following network events are emitted:
+      | DomainEvent | PlayerInput | 0 | 0 | 0 |
@@ -0,0 +1,153 @@
+#![feature(slice_patterns)]
+extern crate specs;
+extern crate itertools;
+extern crate synchronization;
+extern crate pubsub;
+extern crate client_state as state;
+extern crate client_network as network;
+extern crate common;
+#[macro_use(declare_dependencies, standalone_installer_from_new)]
+extern crate automatic_system_installer;
+
+mod script;
+
+pub use script::{Action, Comparison, Expectation, Runner, Script, Target};
+
+use state::{Delta, ExitFlag, InputLog, OwnEntity};
+use network::ConnectionStatus;
+use pubsub::PubSubStore;
+use common::aspects::{RenderAspect, SynchronizedAspect};
+use common::network::now_ms;
+use common::protocol::{ClientEvent, ClientNetworkEvent, PlayerInput};
+use specs::Join;
+use itertools::Itertools;
+
+/**
+ * What a bot has found wrong so far, and whether it has finished its script
+ */
+#[derive(Debug, Clone)]
+pub struct BotReport {
+  pub failures: Vec<String>,
+  pub finished: bool,
+}
+
+impl BotReport {
+  pub fn new() -> BotReport {
+    BotReport {
+      failures: Vec::new(),
+      finished: false,
+    }
+  }
+}
+
+/**
+ * Plays a bot's script, sending its actions to the server as domain events
+ *
+ * The script starts once the bot has connected, and pauses while it's reconnecting. Asserts are
+ * checked against the world as of the latest snapshot. When the script runs out, the client exits.
+ * Like a player, the bot sends its input every tick, walking the way it last moved.
+ *
+ * Output: ClientNetworkEvent, InputLog
+ */
+pub struct System {
+  runner: Runner,
+  movement: (f32, f32, f32),
+}
+declare_dependencies!(System, [synchronization::System, network::ConnectionSystem]);
+
+impl System {
+  pub fn new(script: Script, world: &mut specs::World) -> System {
+    world.add_resource::<BotReport>(BotReport::new());
+    world.add_resource::<InputLog>(InputLog::new());
+
+    System {
+      runner: Runner::new(script),
+      movement: (0.0, 0.0, 0.0),
+    }
+  }
+
+  pub fn name() -> &'static str {
+    "bot::System"
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+    let (synchros,
+         own_entity,
+         connection_status,
+         mut client_events,
+         mut input_log,
+         mut report,
+         mut exit_flag) = arg.fetch(|w| {
+      (w.read::<SynchronizedAspect>(),
+       w.read_resource::<Option<OwnEntity>>(),
+       w.read_resource::<ConnectionStatus>(),
+       w.fetch_publisher::<ClientNetworkEvent>(),
+       w.write_resource::<InputLog>(),
+       w.write_resource::<BotReport>(),
+       w.write_resource::<ExitFlag>())
+    });
+
+    if !connection_status.is_connected() {
+      return;
+    }
+
+    let own_synchro = own_entity.as_ref().map(|&OwnEntity(ref synchro)| synchro.clone());
+
+    self.runner.tick().into_iter().foreach(|action| {
+      match action {
+        Action::Move(x, y, z) => self.movement = (x, y, z),
+        Action::Create => {
+          client_events.push(ClientNetworkEvent::DomainEvent(ClientEvent::CreateEntity))
+        },
+        Action::SetModel(target, model) => {
+          let synchro = match target {
+            Target::Own => own_synchro.clone(),
+            Target::Entity(id) => synchros.iter().find(|s| s.starts_with(&id)).cloned(),
+          };
+          match synchro {
+            Some(synchro) => {
+              let render = RenderAspect::new_with(model);
+              client_events.push(ClientNetworkEvent::DomainEvent(
+                ClientEvent::MutateRenderAspect(synchro, render)))
+            },
+            None => report.failures.push("No entity to set the model of".to_owned()),
+          }
+        },
+        Action::Assert(expectation) => {
+          let failure = match expectation {
+            Expectation::OwnEntity if own_synchro.is_none() => {
+              Some("Expected to own an entity".to_owned())
+            },
+            Expectation::EntityCount(comparison, expected) => {
+              let actual = synchros.iter().count();
+              if comparison.holds(actual, expected) {
+                None
+              } else {
+                Some(format!("Expected {:?} {} entities, saw {}", comparison, expected, actual))
+              }
+            },
+            _ => None,
+          };
+          failure.map(|failure| report.failures.push(failure));
+        },
+        // Waits and repeats are handled by the runner
+        Action::Wait(_) | Action::Repeat(..) => {},
+      }
+    });
+
+    let seq = input_log.record(self.movement, now_ms());
+    client_events.push(ClientNetworkEvent::DomainEvent(ClientEvent::PlayerInput(PlayerInput {
+      seq: seq,
+      movement: self.movement,
+      look: (0.0, 0.0),
+      buttons: 0,
+    })));
+
+    if self.runner.finished() && !report.finished {
+      report.finished = true;
+      *exit_flag = ExitFlag(true);
+    }
+  }
+}
@@ -0,0 +1,314 @@
+use std::str::FromStr;
+
+use common::model::ModelType;
+
+/**
+ * Something a bot does, one line of its script
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+  /// Walk the bot's own entity in this direction, until the next move
+  Move(f32, f32, f32),
+  Create,
+  SetModel(Target, ModelType),
+  /// Do nothing for this many ticks
+  Wait(u32),
+  Assert(Expectation),
+  /// Run the actions this many times, or forever if there's no count
+  Repeat(Option<u32>, Vec<Action>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+  /// The entity the server gave this bot
+  Own,
+  /// The first entity whose id starts with this
+  Entity(String),
+}
+
+/**
+ * Something that should be true of the bot's latest snapshot
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+  EntityCount(Comparison, usize),
+  OwnEntity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+  AtLeast,
+  AtMost,
+  Exactly,
+}
+
+impl Comparison {
+  pub fn holds(&self, actual: usize, expected: usize) -> bool {
+    match self {
+      &Comparison::AtLeast => actual >= expected,
+      &Comparison::AtMost => actual <= expected,
+      &Comparison::Exactly => actual == expected,
+    }
+  }
+}
+
+/**
+ * A bot's behaviour, parsed from a file like:
+ *
+ * ```text
+ * # Lines starting with # are ignored
+ * create
+ * wait 30
+ * assert entities >= 2
+ * set_model own sphere
+ * repeat 10
+ *   move 1 0 0
+ *   wait 5
+ * end
+ * loop
+ *   move 0 1 0
+ *   wait 1
+ * end
+ * ```
+ *
+ * Asserts compare against `>=`, `<=` or `==`, and `assert own_entity` checks the server has given
+ * the bot an entity. Entities other than `own` are named by an id prefix, as in the console.
+ *
+ * The server only lets admins create entities or set their models, so bots running scripts that
+ * do must present its admin secret when they connect.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+  pub actions: Vec<Action>,
+}
+
+impl Script {
+  pub fn parse(contents: &str) -> Result<Script, String> {
+    // Each open repeat block, with the actions collected so far
+    let mut blocks: Vec<(Option<u32>, Vec<Action>)> = Vec::new();
+    let mut actions = Vec::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+      let words = line.split('#').next().unwrap().split_whitespace().collect::<Vec<&str>>();
+      let line_num = idx + 1;
+
+      match words.as_slice() {
+        &[] => {},
+        &["repeat", count] => {
+          let count = try!(parse_word::<u32>(count, line_num));
+          blocks.push((Some(count), actions));
+          actions = Vec::new();
+        },
+        &["loop"] => {
+          blocks.push((None, actions));
+          actions = Vec::new();
+        },
+        &["end"] => {
+          match blocks.pop() {
+            Some((count, mut outer)) => {
+              outer.push(Action::Repeat(count, actions));
+              actions = outer;
+            },
+            None => return Err(format!("line {}: end without repeat or loop", line_num)),
+          }
+        },
+        other => actions.push(try!(parse_action(other, line_num))),
+      }
+    }
+
+    if blocks.is_empty() {
+      Ok(Script { actions: actions })
+    } else {
+      Err("script ends inside a repeat or loop".to_owned())
+    }
+  }
+}
+
+fn parse_action(words: &[&str], line_num: usize) -> Result<Action, String> {
+  match words {
+    &["move", x, y, z] => {
+      Ok(Action::Move(try!(parse_word(x, line_num)),
+                      try!(parse_word(y, line_num)),
+                      try!(parse_word(z, line_num))))
+    },
+    &["create"] => Ok(Action::Create),
+    &["set_model", target, model] => {
+      let model = match model {
+        "cube" => ModelType::Cube,
+        "sphere" => ModelType::Icosphere3,
+        _ => return Err(format!("line {}: {} is not a model (cube or sphere)", line_num, model)),
+      };
+      Ok(Action::SetModel(parse_target(target), model))
+    },
+    &["wait", ticks] => Ok(Action::Wait(try!(parse_word(ticks, line_num)))),
+    &["assert", "own_entity"] => Ok(Action::Assert(Expectation::OwnEntity)),
+    &["assert", "entities", comparison, count] => {
+      let comparison = match comparison {
+        ">=" => Comparison::AtLeast,
+        "<=" => Comparison::AtMost,
+        "==" => Comparison::Exactly,
+        _ => return Err(format!("line {}: {} is not >=, <= or ==", line_num, comparison)),
+      };
+      Ok(Action::Assert(Expectation::EntityCount(comparison, try!(parse_word(count, line_num)))))
+    },
+    _ => Err(format!("line {}: can't understand \"{}\"", line_num, words.join(" "))),
+  }
+}
+
+fn parse_target(word: &str) -> Target {
+  match word {
+    "own" => Target::Own,
+    id => Target::Entity(id.to_owned()),
+  }
+}
+
+fn parse_word<T: FromStr>(word: &str, line_num: usize) -> Result<T, String> {
+  T::from_str(word).map_err(|_| format!("line {}: {} is not a valid number", line_num, word))
+}
+
+/**
+ * Steps through a script, yielding the actions due each tick
+ */
+pub struct Runner {
+  // Innermost block last, with the index of its next action and how many more passes it gets
+  frames: Vec<Frame>,
+  waiting: u32,
+}
+
+struct Frame {
+  actions: Vec<Action>,
+  next: usize,
+  remaining: Option<u32>,
+}
+
+/// Stops a loop with no waits from hanging the tick
+const MAX_STEPS_PER_TICK: usize = 64;
+
+impl Runner {
+  pub fn new(script: Script) -> Runner {
+    Runner {
+      frames: vec![Frame {
+                     actions: script.actions,
+                     next: 0,
+                     remaining: Some(0),
+                   }],
+      waiting: 0,
+    }
+  }
+
+  pub fn finished(&self) -> bool {
+    self.frames.is_empty()
+  }
+
+  /**
+   * Yields everything up to the next wait (or the end of the script)
+   */
+  pub fn tick(&mut self) -> Vec<Action> {
+    let mut due = Vec::new();
+
+    if self.waiting > 0 {
+      self.waiting -= 1;
+      return due;
+    }
+
+    for _ in 0..MAX_STEPS_PER_TICK {
+      let action = match self.frames.last_mut() {
+        None => break,
+        Some(frame) => {
+          if frame.next < frame.actions.len() {
+            frame.next += 1;
+            Some(frame.actions[frame.next - 1].clone())
+          } else {
+            match frame.remaining {
+              Some(0) => None,
+              Some(ref mut count) => {
+                *count -= 1;
+                frame.next = 0;
+                continue;
+              },
+              None => {
+                frame.next = 0;
+                continue;
+              },
+            }
+          }
+        },
+      };
+
+      match action {
+        None => {
+          self.frames.pop();
+        },
+        Some(Action::Wait(ticks)) => {
+          // This tick counts as the first one waited
+          self.waiting = ticks.saturating_sub(1);
+          break;
+        },
+        Some(Action::Repeat(count, actions)) => {
+          match count {
+            Some(0) => {},
+            _ => {
+              self.frames.push(Frame {
+                actions: actions,
+                next: 0,
+                remaining: count.map(|c| c - 1),
+              })
+            },
+          }
+        },
+        Some(other) => due.push(other),
+      }
+    }
+
+    due
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use common::model::ModelType;
+
+  #[test]
+  fn scripts_parse_into_nested_actions() {
+    let script = Script::parse("create # make a friend\n\nrepeat 2\n  move 1 0 0\n  wait \
+                                3\nend\nset_model own sphere\nassert entities >= 2")
+      .unwrap();
+
+    assert_eq!(script.actions,
+               vec![Action::Create,
+                    Action::Repeat(Some(2),
+                                   vec![Action::Move(1.0, 0.0, 0.0), Action::Wait(3)]),
+                    Action::SetModel(Target::Own, ModelType::Icosphere3),
+                    Action::Assert(Expectation::EntityCount(Comparison::AtLeast, 2))]);
+  }
+
+  #[test]
+  fn bad_scripts_are_rejected() {
+    assert!(Script::parse("repeat 2\ncreate").is_err());
+    assert!(Script::parse("end").is_err());
+    assert!(Script::parse("move 1 0").is_err());
+    assert!(Script::parse("wait soon").is_err());
+  }
+
+  #[test]
+  fn runner_waits_between_repeats() {
+    let mut runner = Runner::new(Script::parse("repeat 2\ncreate\nwait 2\nend").unwrap());
+
+    assert_eq!(runner.tick(), vec![Action::Create]);
+    assert_eq!(runner.tick(), vec![]);
+    assert_eq!(runner.tick(), vec![Action::Create]);
+    assert_eq!(runner.tick(), vec![]);
+    assert!(!runner.finished());
+    assert_eq!(runner.tick(), vec![]);
+    assert!(runner.finished());
+  }
+
+  #[test]
+  fn loops_without_waits_dont_hang() {
+    let mut runner = Runner::new(Script::parse("loop\ncreate\nend").unwrap());
+
+    assert!(runner.tick().len() > 1);
+    assert!(!runner.finished());
+  }
+}
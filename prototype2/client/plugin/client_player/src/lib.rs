@@ -4,39 +4,101 @@ extern crate itertools;
 extern crate common;
 extern crate camera;
 extern crate pubsub;
-extern crate glutin;
 extern crate pause;
+extern crate input_map;
 extern crate client_state as state;
 #[macro_use(declare_dependencies, standalone_installer_from_new)]
 extern crate automatic_system_installer;
 
-use state::Delta;
-use common::protocol::ClientNetworkEvent::{self, DomainEvent};
-use common::protocol::ClientEvent::SelfMove;
+use std::collections::HashSet;
+
+use state::{Delta, InputLog};
+use common::network::now_ms;
+use common::protocol::{BUTTON_JUMP, BUTTON_PRIMARY, BUTTON_SECONDARY, ClientEvent,
+                       ClientNetworkEvent, PlayerInput};
 use cgmath::{InnerSpace, Vector2};
+use input_map::{Action, HeldActions};
 use pause::PauseState;
-use pubsub::{PubSubStore, Publisher, SubscriberToken};
-
-#[derive(Clone)]
-enum MoveEvent {
-  Forward,
-  Backward,
-  Left,
-  Right,
-}
+use pubsub::PubSubStore;
 
 /**
- * Convert client player actions to network events
+ * The player's actions that are being held down
+ *
+ * Sampled every tick for the player's input.
  */
-pub struct MoveSystem {
-  move_event_sub_token: SubscriberToken<MoveEvent>,
+#[derive(Debug, Clone)]
+pub struct InputState {
+  held: HashSet<Action>,
+}
+
+impl InputState {
+  pub fn new() -> InputState {
+    InputState { held: HashSet::new() }
+  }
+
+  pub fn is_held(&self, action: Action) -> bool {
+    self.held.contains(&action)
+  }
+
+  /**
+   * How far the player is pushing forward and right, each from -1 to 1
+   */
+  pub fn movement_axes(&self) -> (f32, f32) {
+    let axis = |positive, negative| {
+      match (self.is_held(positive), self.is_held(negative)) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => 0.0,
+      }
+    };
+
+    (axis(Action::MoveForward, Action::MoveBackward), axis(Action::MoveRight, Action::MoveLeft))
+  }
+
+  /**
+   * The held action buttons, as PlayerInput bits
+   */
+  pub fn buttons(&self) -> u8 {
+    let mut buttons = 0;
+    if self.is_held(Action::Primary) {
+      buttons = buttons | BUTTON_PRIMARY;
+    }
+    if self.is_held(Action::Secondary) {
+      buttons = buttons | BUTTON_SECONDARY;
+    }
+    if self.is_held(Action::Jump) {
+      buttons = buttons | BUTTON_JUMP;
+    }
+    buttons
+  }
+
+  /**
+   * Lets go of everything, as when the game is paused
+   */
+  pub fn release_all(&mut self) {
+    self.held.clear();
+  }
 }
+
+/**
+ * Samples the player's controls every tick, and sends them to the server as a PlayerInput
+ *
+ * Movement is turned from forward/right into world coordinates using the camera, which looks
+ * toward the player's entity. Each input is numbered and noted in the InputLog, so it can be
+ * replayed until the server says it has been applied.
+ *
+ * Input: InputState, CameraPos
+ * Output: ClientNetworkEvent, InputLog
+ */
+pub struct MoveSystem;
 declare_dependencies!(MoveSystem, [PreprocessorSystem, camera::MovementSystem]);
 standalone_installer_from_new!(MoveSystem, Delta);
 
 impl MoveSystem {
   pub fn new(world: &mut specs::World) -> MoveSystem {
-    MoveSystem { move_event_sub_token: world.register_subscriber::<MoveEvent>() }
+    world.add_resource::<InputLog>(InputLog::new());
+
+    MoveSystem
   }
 
   pub fn name() -> &'static str {
@@ -44,75 +106,56 @@ impl MoveSystem {
   }
 }
 
-#[allow(unused_imports, unused_variables)]
 impl specs::System<Delta> for MoveSystem {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    use specs::Join;
-    use itertools::Itertools;
-
-    let (mut move_events, outbound_events, camera_pos) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.move_event_sub_token).collected(),
-       w.fetch_publisher::<ClientNetworkEvent>(),
-       w.read_resource::<camera::CameraPos>())
+    let (input_state, camera_pos, mut input_log, mut outbound_events) = arg.fetch(|w| {
+      (w.read_resource::<InputState>(),
+       w.read_resource::<camera::CameraPos>(),
+       w.write_resource::<InputLog>(),
+       w.fetch_publisher::<ClientNetworkEvent>())
     });
 
-    let mut player_manipulator = PlayerManipulator::new(camera_pos.clone(), outbound_events);
-    move_events.drain(..).foreach(|e| player_manipulator.move_player(e));
-  }
-}
-
-// TODO(acmcarther): Document
-struct PlayerManipulator<'a> {
-  forward_vec: Vector2<f32>,
-  left_vec: Vector2<f32>,
-  outbound_events: Publisher<'a, ClientNetworkEvent>,
-}
+    let camera::CameraPos(x, y, z) = camera_pos.clone();
+    // Negative because we're looking toward the origin
+    let forward_vec = -Vector2::new(x, y).normalize();
+    let right_vec = Vector2::new(forward_vec.y, -forward_vec.x);
 
-impl<'a> PlayerManipulator<'a> {
-  pub fn new(cam_pos: camera::CameraPos,
-             outbound_events: Publisher<'a, ClientNetworkEvent>)
-             -> PlayerManipulator<'a> {
-    let camera::CameraPos(x, y, _) = cam_pos;
-
-    PlayerManipulator {
-      // Negative because we're looking toward the origin
-      forward_vec: -Vector2::new(x, y).normalize_to(0.1),
-      left_vec: -Vector2::new(-y, x).normalize_to(0.1),
-      outbound_events: outbound_events,
+    let (forward, right) = input_state.movement_axes();
+    let mut move_dir = forward_vec * forward + right_vec * right;
+    if move_dir.magnitude() > 1.0 {
+      move_dir = move_dir.normalize();
     }
-  }
+    let movement = (move_dir.x, move_dir.y, 0.0);
 
-  pub fn move_player(&mut self, event: MoveEvent) {
-    let move_dir = match event {
-      MoveEvent::Forward => self.forward_vec.clone(),
-      MoveEvent::Backward => -self.forward_vec.clone(),
-      MoveEvent::Left => self.left_vec.clone(),
-      MoveEvent::Right => -self.left_vec.clone(),
-    };
+    let look = ((-y).atan2(-x), (-z).atan2((x * x + y * y).sqrt()));
+    let seq = input_log.record(movement, now_ms());
 
-    self.moove(move_dir);
-  }
-
-  fn moove(&mut self, dir: Vector2<f32>) {
-    let (x, y) = (dir.x, dir.y);
-    self.outbound_events.push(DomainEvent(SelfMove {
-      x_d: x,
-      y_d: y,
-      z_d: 0.0,
-    }))
+    outbound_events.push(ClientNetworkEvent::DomainEvent(ClientEvent::PlayerInput(PlayerInput {
+      seq: seq,
+      movement: movement,
+      look: look,
+      buttons: input_state.buttons(),
+    })));
   }
 }
 
-pub struct PreprocessorSystem {
-  window_event_sub_token: SubscriberToken<glutin::Event>,
-}
-// NOTE: This depends on a window emitter that lives in the main thread
-declare_dependencies!(PreprocessorSystem, [pause::System]);
+/**
+ * Keeps the InputState up to date with the actions being held
+ *
+ * Nothing is held while the game is paused.
+ *
+ * Input: HeldActions, PauseState
+ * Output: InputState
+ */
+pub struct PreprocessorSystem;
+declare_dependencies!(PreprocessorSystem, [pause::System, input_map::System]);
 standalone_installer_from_new!(PreprocessorSystem, Delta);
 
 impl PreprocessorSystem {
   pub fn new(world: &mut specs::World) -> PreprocessorSystem {
-    PreprocessorSystem { window_event_sub_token: world.register_subscriber::<glutin::Event>() }
+    world.add_resource::<InputState>(InputState::new());
+
+    PreprocessorSystem
   }
 
   pub fn name() -> &'static str {
@@ -122,35 +165,16 @@ impl PreprocessorSystem {
 
 impl specs::System<Delta> for PreprocessorSystem {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    use itertools::Itertools;
-    use glutin::VirtualKeyCode::{A, D, S, W};
-    use glutin::ElementState;
-    use glutin::Event::KeyboardInput;
-
-    let (mut glutin_events, pause_state, mut move_events) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.window_event_sub_token).collected(),
+    let (held_actions, pause_state, mut input_state) = arg.fetch(|w| {
+      (w.read_resource::<HeldActions>(),
        w.read_resource::<PauseState>(),
-       w.fetch_publisher::<MoveEvent>())
+       w.write_resource::<InputState>())
     });
 
-    if *pause_state != PauseState::Paused {
-      glutin_events.drain(..).foreach(|e| {
-        match e {
-          KeyboardInput(ElementState::Pressed, _, Some(W)) => {
-            move_events.push(MoveEvent::Forward);
-          },
-          KeyboardInput(ElementState::Pressed, _, Some(A)) => {
-            move_events.push(MoveEvent::Left);
-          },
-          KeyboardInput(ElementState::Pressed, _, Some(S)) => {
-            move_events.push(MoveEvent::Backward);
-          },
-          KeyboardInput(ElementState::Pressed, _, Some(D)) => {
-            move_events.push(MoveEvent::Right);
-          },
-          _ => {}, // I throw this on the ground
-        }
-      });
+    if *pause_state == PauseState::Paused {
+      input_state.release_all();
+    } else {
+      input_state.held = held_actions.0.clone();
     }
   }
 }
@@ -0,0 +1,40 @@
+use specs;
+
+use state::Delta;
+use network::{ConnectionSystem, ConnectionTransition};
+use pubsub::{PubSubStore, SubscriberToken};
+use invoke::ConsoleLog;
+
+/**
+ * Notes changes in the connection to the server in the console log
+ *
+ * Input: ConnectionTransition
+ */
+pub struct System {
+  transition_sub_token: SubscriberToken<ConnectionTransition>,
+}
+declare_dependencies!(System, [::invoke::System, ConnectionSystem]);
+standalone_installer_from_new!(System, Delta);
+
+impl System {
+  pub fn new(world: &mut specs::World) -> System {
+    System { transition_sub_token: world.register_subscriber::<ConnectionTransition>() }
+  }
+
+  pub fn name() -> &'static str {
+    "console::connection"
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+    use itertools::Itertools;
+
+    let (mut transitions, mut console_log) = arg.fetch(|w| {
+      (w.fetch_subscriber(&self.transition_sub_token).collected(),
+       w.write_resource::<ConsoleLog>())
+    });
+
+    transitions.drain(..).foreach(|transition| console_log.push(transition.to.to_string()));
+  }
+}
@@ -1,15 +1,19 @@
 use specs;
 
-use glutin;
-use glutin::Event::KeyboardInput;
-use std::convert::From;
 use charsets::{CharAction, CharEvent, CharMotion};
+use input_map::Action;
 use std::mem;
 use state::Delta;
 use pubsub::{PubSubStore, Publisher, SubscriberToken};
 
+/**
+ * Something typed into the open console
+ */
 #[derive(Clone)]
-pub struct ConsoleEvent(glutin::Event);
+pub enum ConsoleEvent {
+  Character(char),
+  Action(Action),
+}
 
 #[derive(Clone)]
 pub struct CommandBuffer(pub String);
@@ -20,24 +24,8 @@ pub struct CommandCursor(pub usize);
 #[derive(Clone)]
 pub struct ExecutedCommand(pub String);
 
-impl ConsoleEvent {
-  fn get_input(&self) -> &glutin::Event {
-    let &ConsoleEvent(ref input) = self;
-    input
-  }
-}
-
-impl From<glutin::Event> for ConsoleEvent {
-  fn from(event: glutin::Event) -> ConsoleEvent {
-    ConsoleEvent(event)
-  }
-}
-
 pub struct System {
-  lshifted: bool,
-  rshifted: bool,
   cursor: usize,
-  capsed: bool,
   command_buffer: String,
   console_event_sub_token: SubscriberToken<ConsoleEvent>,
 }
@@ -50,9 +38,6 @@ impl System {
     world.add_resource::<CommandCursor>(CommandCursor(0));
 
     System {
-      lshifted: false,
-      rshifted: false,
-      capsed: false,
       cursor: 0,
       command_buffer: String::new(),
       console_event_sub_token: world.register_subscriber::<ConsoleEvent>(),
@@ -63,9 +48,14 @@ impl System {
     "console::input"
   }
 
-  pub fn should_cap(&self) -> bool {
-    let shift = self.lshifted || self.rshifted;
-    (shift && !self.capsed) || (!shift && self.capsed)
+  // The cursor is a byte offset, so it steps over whole characters
+  fn prev_boundary(&self) -> usize {
+    self.command_buffer[..self.cursor].char_indices().next_back().map(|(idx, _)| idx).unwrap_or(0)
+  }
+
+  fn next_boundary(&self) -> usize {
+    let next_len = self.command_buffer[self.cursor..].chars().next().map(|c| c.len_utf8());
+    self.cursor + next_len.unwrap_or(0)
   }
 
   pub fn handle_char_event(&mut self,
@@ -74,18 +64,16 @@ impl System {
     match event {
       CharEvent::Character(c) => {
         self.command_buffer.insert(self.cursor, c);
-        self.cursor = self.cursor.saturating_add(1);
+        self.cursor = self.cursor + c.len_utf8();
       },
-      CharEvent::Motion(CharMotion::Left) => self.cursor = self.cursor.saturating_sub(1),
-      CharEvent::Motion(CharMotion::Right) => self.cursor = self.cursor.saturating_add(1),
-      CharEvent::Motion(CharMotion::Up) => {},
-      CharEvent::Motion(CharMotion::Down) => {},
+      CharEvent::Motion(CharMotion::Left) => self.cursor = self.prev_boundary(),
+      CharEvent::Motion(CharMotion::Right) => self.cursor = self.next_boundary(),
       CharEvent::Motion(CharMotion::Home) => self.cursor = 0,
       CharEvent::Motion(CharMotion::End) => self.cursor = self.command_buffer.len(),
       CharEvent::Action(CharAction::Backspace) => {
-        if self.command_buffer.len() > 0 {
-          self.command_buffer.remove(self.cursor.saturating_sub(1));
-          self.cursor = self.cursor.saturating_sub(1);
+        if self.cursor > 0 {
+          self.cursor = self.prev_boundary();
+          self.command_buffer.remove(self.cursor);
         }
       },
       CharEvent::Action(CharAction::Delete) => {
@@ -106,9 +94,6 @@ impl System {
 
 impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
-    use charsets::ASCII_CHAR_DICT;
-    use glutin::VirtualKeyCode::{Capital, LShift, RShift};
-    use glutin::ElementState;
     use itertools::Itertools;
 
     let (mut console_events,
@@ -122,29 +107,11 @@ impl specs::System<Delta> for System {
     });
 
     console_events.drain(..).foreach(|event| {
-      match event.get_input() {
-        &KeyboardInput(ElementState::Pressed, _, Some(LShift)) => {
-          self.lshifted = true;
-        },
-        &KeyboardInput(ElementState::Released, _, Some(LShift)) => {
-          self.lshifted = false;
-        },
-        &KeyboardInput(ElementState::Pressed, _, Some(RShift)) => {
-          self.rshifted = true;
-        },
-        &KeyboardInput(ElementState::Released, _, Some(RShift)) => {
-          self.rshifted = false;
-        },
-        &KeyboardInput(ElementState::Pressed, _, Some(Capital)) => {
-          self.capsed = !self.capsed;
-        },
-        &KeyboardInput(ElementState::Pressed, _, Some(ref key)) => {
-          let event = ASCII_CHAR_DICT.get_mapping(self.should_cap(), key.clone());
-          self.handle_char_event(event, &mut executed_commands);
-        },
-        &KeyboardInput(ElementState::Released, _, _) => {},
-        _ => {}, // Something thats not a keyboard input
-      }
+      let char_event = match event {
+        ConsoleEvent::Character(c) => CharEvent::from_char(c),
+        ConsoleEvent::Action(action) => CharEvent::from_action(action),
+      };
+      self.handle_char_event(char_event, &mut executed_commands);
     });
 
     // We're authoritative for the console command and the cursor -- any mutation
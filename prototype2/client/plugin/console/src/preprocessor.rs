@@ -1,22 +1,39 @@
 use specs;
 use glutin;
 
+use input_map::ActionEvent;
 use pause::PauseState;
 use state::Delta;
 use pubsub::{PubSubStore, SubscriberToken};
 
 use input::ConsoleEvent;
 
+/**
+ * Passes typed characters and pressed actions to the console while it's open
+ *
+ * Characters come from the window rather than from keys, so they follow the player's keyboard
+ * layout. Nothing is passed on the tick the console opens, so that the key that opened it isn't
+ * typed.
+ *
+ * Input: glutin::Event, ActionEvent, PauseState
+ * Output: ConsoleEvent
+ */
 pub struct System {
   window_event_sub_token: SubscriberToken<glutin::Event>,
+  action_event_sub_token: SubscriberToken<ActionEvent>,
+  was_open: bool,
 }
 // NOTE: This depends on a window emitter that lives in the main thread
-declare_dependencies!(System, [::pause::System]);
+declare_dependencies!(System, [::pause::System, ::input_map::System]);
 standalone_installer_from_new!(System, Delta);
 
 impl System {
   pub fn new(world: &mut specs::World) -> System {
-    System { window_event_sub_token: world.register_subscriber::<glutin::Event>() }
+    System {
+      window_event_sub_token: world.register_subscriber::<glutin::Event>(),
+      action_event_sub_token: world.register_subscriber::<ActionEvent>(),
+      was_open: false,
+    }
   }
 
   pub fn name() -> &'static str {
@@ -28,16 +45,26 @@ impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
     use itertools::Itertools;
 
-    let (mut glutin_events, pause_state, mut console_events) = arg.fetch(|w| {
+    let (mut glutin_events, mut action_events, pause_state, mut console_events) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.window_event_sub_token).collected(),
+       w.fetch_subscriber(&self.action_event_sub_token).collected(),
        w.read_resource::<PauseState>(),
        w.fetch_publisher::<ConsoleEvent>())
     });
 
-    if *pause_state == PauseState::Paused {
+    let is_open = *pause_state == PauseState::Paused;
+    if is_open && self.was_open {
       glutin_events.drain(..).foreach(|e| {
-        console_events.push(ConsoleEvent::from(e));
+        if let glutin::Event::ReceivedCharacter(c) = e {
+          console_events.push(ConsoleEvent::Character(c));
+        }
+      });
+      action_events.drain(..).foreach(|e| {
+        if let ActionEvent::Pressed(action) = e {
+          console_events.push(ConsoleEvent::Action(action));
+        }
       });
     }
+    self.was_open = is_open;
   }
 }
@@ -1,4 +1,5 @@
 use common::model::ModelType;
+use input_map::{Action, Binding};
 use std::str::FromStr;
 
 #[derive(Debug, Clone)]
@@ -11,12 +12,16 @@ pub enum Command {
   DeleteEntity(String),
   SetEntityPos(String, (f32, f32, f32)),
   SetEntityModel(String, ModelType),
+  ListBindings,
+  Bind(Action, Binding),
+  /// Unbinds the one binding given, or every binding if none is
+  Unbind(Action, Option<Binding>),
 }
 
 impl Command {
   pub fn print_all() -> String {
     "help, exit, list_entities, create, show $id, delete $id, set_pos $id $x $y $z, set_model \
-     (cube|sphere)"
+     (cube|sphere), bindings, bind $action $key, unbind $action [$key]"
       .to_owned()
   }
 }
@@ -59,6 +64,27 @@ impl Interpreter {
       &["set_model", item, "sphere"] => {
         InterpreterResult::Valid(Command::SetEntityModel(item.to_owned(), ModelType::Icosphere3))
       },
+      &["bindings"] => InterpreterResult::Valid(Command::ListBindings),
+      &["bind", action, binding] => {
+        match (Action::from_str(action), Binding::from_str(binding)) {
+          (Ok(action), Ok(binding)) => InterpreterResult::Valid(Command::Bind(action, binding)),
+          _ => InterpreterResult::Invalid,
+        }
+      },
+      &["unbind", action] => {
+        match Action::from_str(action) {
+          Ok(action) => InterpreterResult::Valid(Command::Unbind(action, None)),
+          _ => InterpreterResult::Invalid,
+        }
+      },
+      &["unbind", action, binding] => {
+        match (Action::from_str(action), Binding::from_str(binding)) {
+          (Ok(action), Ok(binding)) => {
+            InterpreterResult::Valid(Command::Unbind(action, Some(binding)))
+          },
+          _ => InterpreterResult::Invalid,
+        }
+      },
       _ => InterpreterResult::Invalid,
     }
   }
@@ -1,6 +1,7 @@
 extern crate specs;
 extern crate itertools;
 extern crate console;
+extern crate input_map;
 extern crate pubsub;
 extern crate client_state as state;
 extern crate common;
@@ -9,8 +10,10 @@ extern crate automatic_system_installer;
 
 use state::Delta;
 use console::{Command, ConsoleLog};
+use input_map::{ALL_ACTIONS, Action, Binding, Bindings, BindingsPath};
 use pubsub::{PubSubStore, Publisher, SubscriberToken};
 use state::ExitFlag;
+use std::path::Path;
 use std::sync::{RwLockReadGuard, RwLockWriteGuard};
 use common::aspects::{PhysicalAspect, RenderAspect, SynchronizedAspect};
 use common::protocol::{ClientEvent, ClientNetworkEvent};
@@ -56,14 +59,18 @@ impl specs::System<Delta> for System {
          renders,
          mut client_events,
          mut exit_flag,
-         mut console_log) = arg.fetch(|w| {
+         mut console_log,
+         mut bindings,
+         bindings_path) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.commands_sub_token).collected(),
        w.read::<SynchronizedAspect>(),
        w.read::<PhysicalAspect>(),
        w.read::<RenderAspect>(),
        w.fetch_publisher::<ClientNetworkEvent>(),
        w.write_resource::<ExitFlag>(),
-       w.write_resource::<ConsoleLog>())
+       w.write_resource::<ConsoleLog>(),
+       w.write_resource::<Bindings>(),
+       w.read_resource::<BindingsPath>())
     });
 
     commands.drain(..).foreach(|e| {
@@ -108,6 +115,13 @@ impl specs::System<Delta> for System {
           }
         }
         Command::Help => self.help(&mut console_log),
+        Command::ListBindings => self.list_bindings(&bindings, &mut console_log),
+        Command::Bind(action, binding) => {
+          self.bind(action, binding, &mut bindings, &bindings_path, &mut console_log)
+        },
+        Command::Unbind(action, binding) => {
+          self.unbind(action, binding, &mut bindings, &bindings_path, &mut console_log)
+        },
       }
     });
   }
@@ -123,6 +137,49 @@ impl System {
     console_log.push(format!("Valid commands: {}", Command::print_all()));
   }
 
+  fn list_bindings(&mut self, bindings: &Bindings, console_log: &mut ConsoleLog) {
+    ALL_ACTIONS.iter().foreach(|action| {
+      let bound = bindings.bindings_for(*action)
+        .iter()
+        .map(|binding| binding.to_string())
+        .collect::<Vec<String>>();
+      console_log.push(format!("{}: {}", action, bound.join(", ")));
+    });
+  }
+
+  fn bind(&mut self,
+          action: Action,
+          binding: Binding,
+          bindings: &mut Bindings,
+          bindings_path: &BindingsPath,
+          console_log: &mut ConsoleLog) {
+    bindings.bind(action, binding);
+    console_log.push(format!("Bound {} to {}", binding, action));
+    save_bindings(bindings, bindings_path, console_log);
+  }
+
+  fn unbind(&mut self,
+            action: Action,
+            binding: Option<Binding>,
+            bindings: &mut Bindings,
+            bindings_path: &BindingsPath,
+            console_log: &mut ConsoleLog) {
+    match binding {
+      Some(binding) => {
+        if !bindings.unbind(action, &binding) {
+          console_log.push(format!("{} isn't bound to {}", binding, action));
+          return;
+        }
+        console_log.push(format!("Unbound {} from {}", binding, action));
+      },
+      None => {
+        bindings.unbind_all(action);
+        console_log.push(format!("Unbound everything from {}", action));
+      },
+    }
+    save_bindings(bindings, bindings_path, console_log);
+  }
+
   fn list_entities<'a>(&mut self,
                        synchros: &AspectStorageRead<'a, SynchronizedAspect>,
                        console_log: &mut ConsoleLog) {
@@ -193,6 +250,17 @@ impl System {
   }
 }
 
+/**
+ * Writes the bindings back to the file they came from, if they came from one
+ */
+fn save_bindings(bindings: &Bindings, bindings_path: &BindingsPath, console_log: &mut ConsoleLog) {
+  if let &BindingsPath(Some(ref path)) = bindings_path {
+    if let Err(err) = bindings.save(Path::new(path)) {
+      console_log.push(format!("Could not save bindings to {}: {}", path, err));
+    }
+  }
+}
+
 fn synchro_from_id<'a>(id: &str,
                        synchros: &AspectStorageRead<'a, SynchronizedAspect>)
                        -> Option<SynchronizedAspect> {
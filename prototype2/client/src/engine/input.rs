@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use glutin;
+use glutin::ElementState::{Pressed, Released};
+use glutin::Event::{KeyboardInput, MouseInput};
+use input_map::Trigger;
+use specs;
+
+/**
+ * Where the engine gets the window events it feeds to its systems each tick
+ */
+pub trait InputSource {
+  fn poll(&mut self, world: &mut specs::World) -> Vec<glutin::Event>;
+}
+
+/**
+ * Polls the window stored in the world
+ *
+ * Polling has to happen on the main thread (see issue #55), so this is done by the engine rather
+ * than the window system.
+ */
+pub struct WindowInput;
+
+impl WindowInput {
+  pub fn new() -> WindowInput {
+    WindowInput
+  }
+}
+
+impl InputSource for WindowInput {
+  fn poll(&mut self, world: &mut specs::World) -> Vec<glutin::Event> {
+    let window = world.write_resource::<glutin::Window>();
+    window.poll_events().into_iter().collect()
+  }
+}
+
+/**
+ * Plays back a list of events, one batch per tick
+ *
+ * Once the script runs out, no more events are produced.
+ */
+pub struct ScriptedInput {
+  frames: VecDeque<Vec<glutin::Event>>,
+}
+
+impl ScriptedInput {
+  pub fn new(frames: Vec<Vec<glutin::Event>>) -> ScriptedInput {
+    ScriptedInput { frames: frames.into_iter().collect() }
+  }
+
+  /**
+   * Reads a script of input, one tick per line, like:
+   *
+   * ```text
+   * # Walk forward for a second, then jump without stopping
+   * press W
+   * wait 66
+   * press Space
+   * release Space, release W
+   * ```
+   *
+   * Each line presses or releases keys and mouse buttons (named as in the bindings), separated by
+   * commas, or waits that many ticks with no input.
+   */
+  pub fn parse(contents: &str) -> Result<ScriptedInput, String> {
+    let mut frames = Vec::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+      let line = line.split('#').next().unwrap().trim();
+      let line_num = idx + 1;
+      if line.is_empty() {
+        continue;
+      }
+
+      if let &["wait", ticks] = line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        let ticks = try!(u32::from_str(ticks)
+          .map_err(|_| format!("line {}: {} is not a valid number", line_num, ticks)));
+        for _ in 0..ticks {
+          frames.push(Vec::new());
+        }
+        continue;
+      }
+
+      let mut frame = Vec::new();
+      for step in line.split(',') {
+        frame.push(try!(parse_step(step, line_num)));
+      }
+      frames.push(frame);
+    }
+
+    Ok(ScriptedInput::new(frames))
+  }
+
+  /**
+   * Produces no input at all
+   */
+  pub fn idle() -> ScriptedInput {
+    ScriptedInput::new(Vec::new())
+  }
+}
+
+fn parse_step(step: &str, line_num: usize) -> Result<glutin::Event, String> {
+  let words = step.split_whitespace().collect::<Vec<&str>>();
+  let (state, trigger) = match words.as_slice() {
+    &["press", trigger] => (Pressed, trigger),
+    &["release", trigger] => (Released, trigger),
+    _ => return Err(format!("line {}: can't understand \"{}\"", line_num, step.trim())),
+  };
+
+  match Trigger::from_str(trigger) {
+    Ok(Trigger::Key(key)) => Ok(KeyboardInput(state, 0, Some(key))),
+    Ok(Trigger::Mouse(button)) => Ok(MouseInput(state, button)),
+    Err(err) => Err(format!("line {}: {}", line_num, err)),
+  }
+}
+
+impl InputSource for ScriptedInput {
+  fn poll(&mut self, _: &mut specs::World) -> Vec<glutin::Event> {
+    self.frames.pop_front().unwrap_or(Vec::new())
+  }
+}
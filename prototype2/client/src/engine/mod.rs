@@ -1,7 +1,9 @@
+pub mod input;
 pub mod systems;
 
-use std::net::SocketAddr;
-use std::sync::mpsc::{self, Sender};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration as StdDuration;
 
 use gfx_device_gl;
 use glutin;
@@ -10,8 +12,13 @@ use time;
 use gfx_window_glutin;
 use gfx;
 
+use bot;
+use engine::input::{InputSource, ScriptedInput, WindowInput};
+use input_map;
 use network;
+use network::ConnectionStatus;
 use renderer;
+use synchronization;
 
 use renderer::opengl::OpenGlRenderer;
 use renderer::opengl::primitive3d::{ColorFormat, DepthFormat};
@@ -23,13 +30,25 @@ use automatic_system_installer::{AutoInstaller, Dag};
 use std::any::TypeId;
 use itertools::Itertools;
 
+/// Most ticks to spend waiting for the server to acknowledge us leaving
+const FINALIZE_TICKS: u32 = 60;
+const FINALIZE_TICK_MS: u64 = 16;
+
 pub struct Engine {
   pub planner: specs::Planner<Delta>,
+  input: Box<InputSource>,
+  display: Option<Display>,
+  network_kill_signal: Sender<()>,
+  running: bool,
+}
+
+/**
+ * Everything needed to draw a frame, which headless engines go without
+ */
+struct Display {
   device: gfx_device_gl::Device,
   encoder: gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>,
   renderer: renderer::RenderingSystem,
-  network_kill_signal: Sender<()>,
-  running: bool,
 }
 
 impl Engine {
@@ -39,7 +58,10 @@ impl Engine {
     auto_installer.take_dag()
   }
 
-  pub fn new(port: u16, server_addr: SocketAddr) -> Engine {
+  /**
+   * Builds an engine around a network, which it connects to the server
+   */
+  pub fn new(network: network::Network) -> Engine {
     // One time init gfx stuff
     let builder = glutin::WindowBuilder::new()
       .with_title("Space Coop".to_owned())
@@ -50,36 +72,85 @@ impl Engine {
     let encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
     let (network_kill_sender, network_kill_receiver) = mpsc::channel();
+    let world = World::new(window).world;
+    let mut installer = Engine::installer_with_network(network, network_kill_receiver, world);
+    systems::install_auto_systems(&mut installer);
+    let planner = installer.apply(5 /* Threads, arbitrary */);
 
-    // ECS stuff
-    let mut world = World::new(window).world;
+    Engine {
+      planner: planner,
+      input: Box::new(WindowInput::new()),
+      display: Some(Display {
+        device: device,
+        renderer: renderer::RenderingSystem::new(OpenGlRenderer::new(factory,
+                                                                     main_color,
+                                                                     main_depth)),
+        encoder: encoder,
+      }),
+      network_kill_signal: network_kill_sender,
+      running: true,
+    }
+  }
 
-    // Specially initialize the network adapter
-    let network_adapter_system =
-      network::AdapterSystem::new(port, server_addr, network_kill_receiver, &mut world);
+  /**
+   * Builds an engine with no window or graphics device, driven by the given input
+   */
+  pub fn new_headless(network: network::Network, input: Box<InputSource>) -> Engine {
+    Engine::headless_with(network, input, systems::install_headless_systems)
+  }
 
-    // Automatic system installation
-    // TODO: chain these off each other when non-lexical borrows land
-    // https://github.com/rust-lang/rust/issues/21906/
-    let mut installer = AutoInstaller::with_world(world);
-    installer.auto_install_instance(network_adapter_system);
-    systems::install_auto_systems(&mut installer);
+  /**
+   * Builds a headless engine that plays the given bot script
+   *
+   * The bot steers itself, so the player's controls are left out.
+   */
+  pub fn new_bot(network: network::Network, script: bot::Script) -> Engine {
+    Engine::headless_with(network, Box::new(ScriptedInput::idle()), |installer| {
+      systems::install_bot_systems(installer);
+      let bot_system = bot::System::new(script, installer.mut_world());
+      installer.auto_install_instance(bot_system);
+    })
+  }
+
+  fn headless_with<F>(network: network::Network,
+                      input: Box<InputSource>,
+                      install_systems: F)
+                      -> Engine
+    where F: FnOnce(&mut AutoInstaller<Delta>)
+  {
+    let (network_kill_sender, network_kill_receiver) = mpsc::channel();
+    let world = World::new_headless().world;
+    let mut installer = Engine::installer_with_network(network, network_kill_receiver, world);
+    install_systems(&mut installer);
     let planner = installer.apply(5 /* Threads, arbitrary */);
 
     Engine {
       planner: planner,
-      device: device,
-      renderer: renderer::RenderingSystem::new(OpenGlRenderer::new(factory,
-                                                                   main_color,
-                                                                   main_depth)),
-      encoder: encoder,
+      input: input,
+      display: None,
       network_kill_signal: network_kill_sender,
       running: true,
     }
   }
 
+  fn installer_with_network(network: network::Network,
+                            network_kill_receiver: Receiver<()>,
+                            mut world: specs::World)
+                            -> AutoInstaller<Delta> {
+    // Specially initialize the network adapter
+    let network_adapter_system =
+      network::AdapterSystem::new(network, network_kill_receiver, &mut world);
+
+    // Automatic system installation
+    // TODO: chain these off each other when non-lexical borrows land
+    // https://github.com/rust-lang/rust/issues/21906/
+    let mut installer = AutoInstaller::with_world(world);
+    installer.auto_install_instance(network_adapter_system);
+    installer
+  }
+
   pub fn tick(&mut self, dt: &time::Duration) {
-    self.poll_window();
+    self.poll_input();
     self.dispatch_once(dt.clone());
     self.render();
 
@@ -87,13 +158,20 @@ impl Engine {
     if let ExitFlag(true) = *self.planner.mut_world().read_resource::<ExitFlag>() {
       self.running = false;
     }
+
+    // There's nothing left to do if the server won't have us, or we're done with it
+    match *self.planner.mut_world().read_resource::<ConnectionStatus>() {
+      ConnectionStatus::Failed(_) |
+      ConnectionStatus::Disconnected(_) => self.running = false,
+      _ => {},
+    }
   }
 
-  pub fn poll_window(&mut self) {
+  pub fn poll_input(&mut self) {
     let w = self.planner.mut_world();
-    let (window, mut glutin_events) = (w.write_resource::<glutin::Window>(),
-                                       w.fetch_publisher::<glutin::Event>());
-    window.poll_events().into_iter().foreach(|e| glutin_events.push(e));
+    let events = self.input.poll(w);
+    let mut glutin_events = w.fetch_publisher::<glutin::Event>();
+    events.into_iter().foreach(|e| glutin_events.push(e));
   }
 
   pub fn dispatch_once(&mut self, dt: time::Duration) {
@@ -106,8 +184,42 @@ impl Engine {
   }
 
   pub fn render(&mut self) {
-    let mut world = self.planner.mut_world();
-    self.renderer.run(&mut world, &mut self.encoder, &mut self.device);
+    if let Some(ref mut display) = self.display {
+      let mut world = self.planner.mut_world();
+      display.renderer.run(&mut world, &mut display.encoder, &mut display.device);
+    }
+  }
+
+  /**
+   * Decides what the engine does when the server says it's shutting down
+   */
+  pub fn set_shutdown_policy(&mut self, policy: network::ShutdownPolicy) {
+    *self.planner.mut_world().write_resource::<network::ShutdownPolicy>() = policy;
+  }
+
+  /**
+   * Sets the secret presented to be made an admin when connecting, if any
+   */
+  pub fn set_admin_secret(&mut self, secret: Option<String>) {
+    *self.planner.mut_world().write_resource::<network::AdminSecret>() =
+      network::AdminSecret(secret);
+  }
+
+  /**
+   * Sets how far behind the server's clock other entities are drawn, in milliseconds
+   */
+  pub fn set_interpolation_delay(&mut self, delay_ms: u64) {
+    *self.planner.mut_world().write_resource::<synchronization::InterpolationDelay>() =
+      synchronization::InterpolationDelay(delay_ms);
+  }
+
+  /**
+   * Sets which keys and buttons set off which actions, and where to save them when they change
+   */
+  pub fn set_bindings(&mut self, bindings: input_map::Bindings, path: Option<String>) {
+    let world = self.planner.mut_world();
+    *world.write_resource::<input_map::Bindings>() = bindings;
+    *world.write_resource::<input_map::BindingsPath>() = input_map::BindingsPath(path);
   }
 
   pub fn running(&self) -> bool {
@@ -118,7 +230,16 @@ impl Engine {
     // Tell the server we're leaving (to be polite)
     self.network_kill_signal.send(()).unwrap();
 
-    // Spin all services
-    self.tick(dt);
+    // Spin all services until the server has heard us, resending as needed
+    // A connection that failed has no one to tell
+    for _ in 0..FINALIZE_TICKS {
+      self.tick(dt);
+      match *self.planner.mut_world().read_resource::<ConnectionStatus>() {
+        ConnectionStatus::Disconnected(_) |
+        ConnectionStatus::Failed(_) => break,
+        _ => (),
+      }
+      thread::sleep(StdDuration::from_millis(FINALIZE_TICK_MS));
+    }
   }
 }
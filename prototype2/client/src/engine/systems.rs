@@ -4,6 +4,7 @@ use state::Delta;
 use network;
 use console;
 use pause;
+use input_map;
 use debug;
 use camera;
 use synchronization;
@@ -12,19 +13,38 @@ use mutator;
 use mouse_lock;
 
 pub fn install_auto_systems(installer: &mut AutoInstaller<Delta>) {
+  install_headless_systems(installer);
+  installer.auto_install::<mouse_lock::System>();
+}
+
+/**
+ * Installs every system that doesn't need a window
+ */
+pub fn install_headless_systems(installer: &mut AutoInstaller<Delta>) {
+  install_bot_systems(installer);
+  installer.auto_install::<player::PreprocessorSystem>();
+  installer.auto_install::<player::MoveSystem>();
+}
+
+/**
+ * Installs every system that needs neither a window nor the player's controls, for bots that
+ * steer themselves
+ */
+pub fn install_bot_systems(installer: &mut AutoInstaller<Delta>) {
   installer.auto_install::<network::EventDistributionSystem>();
   installer.auto_install::<network::ConnectionSystem>();
+  installer.auto_install::<input_map::System>();
   installer.auto_install::<pause::System>();
-  installer.auto_install::<player::PreprocessorSystem>();
-  installer.auto_install::<mouse_lock::System>();
   installer.auto_install::<camera::PreprocessorSystem>();
   installer.auto_install::<console::PreprocessorSystem>();
-  installer.auto_install::<player::MoveSystem>();
   installer.auto_install::<camera::MovementSystem>();
   installer.auto_install::<console::InputSystem>();
   installer.auto_install::<console::InvokeSystem>();
+  installer.auto_install::<console::ConnectionLogSystem>();
   installer.auto_install::<mutator::System>();
   installer.auto_install::<synchronization::System>();
+  installer.auto_install::<synchronization::InterpolationSystem>();
+  installer.auto_install::<synchronization::PredictionSystem>();
   installer.auto_install::<network::KeepAliveSystem>();
   installer.auto_install::<debug::System>();
 }
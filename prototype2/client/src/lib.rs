@@ -1,4 +1,5 @@
 #![feature(try_from)]
+#![feature(slice_patterns)]
 extern crate time;
 extern crate specs;
 extern crate itertools;
@@ -20,7 +21,9 @@ pub extern crate mouse_lock;
 pub extern crate window;
 pub extern crate client_player as player;
 pub extern crate pause;
+pub extern crate input_map;
 pub extern crate mutator;
+pub extern crate bot;
 pub extern crate client_state as state;
 pub extern crate automatic_system_installer;
 
@@ -28,16 +31,20 @@ pub mod engine;
 pub mod world;
 
 use std::thread;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::time::Duration as StdDuration;
 use std::convert::{From, TryFrom};
 use std::fs::File;
 use std::io::Write;
 use automatic_system_installer::PriorityMap;
+use itertools::Itertools;
 
 use time::Duration;
 
+use common::codec::CodecKind;
+use common::network::{ConditionedTransport, LinkConditions, Transport, UdpTransport};
 use engine::Engine;
+use engine::input::ScriptedInput;
 
 pub enum DependencyMode {
   Dag,
@@ -77,10 +84,123 @@ impl TryFrom<String> for DependencyMode {
 
 /**
  * A function to begin running the client
+ *
+ * Headless clients (those given input to play) open no window, and play their input from the
+ * first tick.
  */
-pub fn start(port: u16, server_addr: SocketAddr) {
+pub fn start(port: u16,
+             server_addr: SocketAddr,
+             codec_kind: CodecKind,
+             conditions: LinkConditions,
+             shutdown_policy: network::ShutdownPolicy,
+             admin_secret: Option<String>,
+             interpolation_delay_ms: u64,
+             bindings: input_map::Bindings,
+             bindings_path: Option<String>,
+             headless_input: Option<ScriptedInput>) {
   println!("Starting client on {}", port);
-  let mut engine = Engine::new(port, server_addr.clone());
+  let network = bind_network(port, server_addr, codec_kind, conditions);
+  let mut engine = match headless_input {
+    Some(input) => Engine::new_headless(network, Box::new(input)),
+    None => Engine::new(network),
+  };
+  engine.set_shutdown_policy(shutdown_policy);
+  engine.set_admin_secret(admin_secret);
+  engine.set_interpolation_delay(interpolation_delay_ms);
+  engine.set_bindings(bindings, bindings_path);
+
+  println!("Client Started!");
+  let mut engines = vec![engine];
+  run(&mut engines);
+}
+
+/**
+ * Runs as many headless bots as asked, each playing the script on its own port
+ *
+ * Bots join with the server's default role (crew, unless it's configured otherwise), which can't
+ * create or change entities. Scripts that do need the admin secret.
+ *
+ * Yields false if any bot failed to connect, or had its asserts fail, or if there aren't enough
+ * ports above the first for every bot.
+ */
+pub fn start_bots(port: u16,
+                  server_addr: SocketAddr,
+                  codec_kind: CodecKind,
+                  conditions: LinkConditions,
+                  shutdown_policy: network::ShutdownPolicy,
+                  admin_secret: Option<String>,
+                  script: bot::Script,
+                  count: u16)
+                  -> bool {
+  if count > 0 && port.checked_add(count - 1).is_none() {
+    println!("Can't start {} bots from port {}: there aren't enough ports above it",
+             count,
+             port);
+    return false;
+  }
+
+  println!("Starting {} bots on ports from {}", count, port);
+  let mut engines = Vec::new();
+  for idx in 0..count {
+    // Each bot's link misbehaves differently, but reproducibly
+    let mut bot_conditions = conditions.clone();
+    bot_conditions.seed = conditions.seed.wrapping_add(idx as u32);
+    let network = bind_network(port + idx, server_addr, codec_kind, bot_conditions);
+    let mut engine = Engine::new_bot(network, script.clone());
+    engine.set_shutdown_policy(shutdown_policy.clone());
+    engine.set_admin_secret(admin_secret.clone());
+    engines.push(engine);
+  }
+  let mut passed = true;
+
+  run(&mut engines);
+
+  for (idx, engine) in engines.iter_mut().enumerate() {
+    match *engine.planner.mut_world().read_resource::<network::ConnectionStatus>() {
+      network::ConnectionStatus::Failed(ref reason) => {
+        println!("Bot {} could not connect to {}: {}", idx, server_addr, reason);
+        passed = false;
+      },
+      network::ConnectionStatus::Disconnected(Some(ref reason)) => {
+        println!("Bot {} was disconnected: {}", idx, reason);
+      },
+      _ => {},
+    }
+    let report = engine.planner.mut_world().read_resource::<bot::BotReport>().clone();
+    for failure in report.failures.iter() {
+      println!("Bot {} failed: {}", idx, failure);
+    }
+    passed = passed && report.failures.is_empty();
+  }
+
+  passed
+}
+
+/**
+ * Binds a network for reaching the server over UDP, through a conditioner if the conditions
+ * aren't perfect
+ *
+ * The engine's ConnectionSystem does the connecting.
+ */
+fn bind_network(port: u16,
+                server_addr: SocketAddr,
+                codec_kind: CodecKind,
+                conditions: LinkConditions)
+                -> network::Network {
+  let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+  let udp = UdpTransport::bind(address).unwrap();
+  let transport: Box<Transport> = if conditions.is_perfect() {
+    Box::new(udp)
+  } else {
+    Box::new(ConditionedTransport::new(Box::new(udp), conditions))
+  };
+  network::Network::with_transport(transport, server_addr, codec_kind)
+}
+
+/**
+ * Ticks every engine until they've all stopped
+ */
+fn run(engines: &mut Vec<Engine>) {
   let frame_limit = 60;
   let time_step = 1.0 / (frame_limit as f32); //s
 
@@ -88,13 +208,12 @@ pub fn start(port: u16, server_addr: SocketAddr) {
   let mut next_time = time::now();
   let mut now;
 
-  println!("Client Started!");
-  while engine.running() {
+  while engines.iter().any(|engine| engine.running()) {
     now = time::now();
     if now > next_time {
       let dt = now - last_time;
 
-      engine.tick(&dt);
+      engines.iter_mut().filter(|engine| engine.running()).foreach(|engine| engine.tick(&dt));
 
       last_time = now;
       next_time = next_time + Duration::milliseconds((time_step * 1000.0) as i64);
@@ -105,7 +224,7 @@ pub fn start(port: u16, server_addr: SocketAddr) {
 
   // Finish up
   let dt = time::now() - last_time;
-  engine.finalize(&dt);
+  engines.iter_mut().foreach(|engine| engine.finalize(&dt));
 
   // Dump the report to disk
   // flame::dump_html(&mut File::create("flame-graph.html").unwrap()).unwrap();
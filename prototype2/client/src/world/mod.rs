@@ -3,6 +3,7 @@ use glutin;
 
 use state::{ExitFlag, OwnEntity};
 use common::aspects::{DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
+use synchronization::InterpolatedAspect;
 
 
 // TODO(acmcarther): This is awkward... "world.world"
@@ -12,16 +13,26 @@ pub struct World {
 
 impl World {
   pub fn new(window: glutin::Window) -> World {
+    let mut world = World::new_headless();
+    world.world.add_resource::<glutin::Window>(window);
+
+    world
+  }
+
+  /**
+   * Builds a world with no window, for clients that don't render
+   */
+  pub fn new_headless() -> World {
     let mut w = specs::World::new();
 
     w.register::<RenderAspect>();
     w.register::<PhysicalAspect>();
     w.register::<DisabledAspect>();
     w.register::<SynchronizedAspect>();
+    w.register::<InterpolatedAspect>();
 
     // "Common" resources
     w.add_resource::<ExitFlag>(ExitFlag(false));
-    w.add_resource::<glutin::Window>(window);
     w.add_resource::<Option<OwnEntity>>(None);
 
     World { world: w }
@@ -2,17 +2,62 @@ extern crate itertools;
 extern crate serde_codegen;
 
 use std::env;
+use std::fs::File;
+use std::io::Write;
 use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use itertools::Itertools;
 
+const CODEGEN_SOURCES: [&'static str; 4] = ["model.rs", "aspects.rs", "protocol.rs", "snapshot.rs"];
+
 pub fn main() {
   let out_dir = env::var_os("OUT_DIR").unwrap();
 
-  (vec!["model.rs", "aspects.rs", "protocol.rs"]).into_iter().foreach(|path| {
-    let full_src = "src/".to_owned() + path + ".in";
+  CODEGEN_SOURCES.iter().foreach(|path| {
+    let full_src = "src/".to_owned() + *path + ".in";
     let src = Path::new(&full_src);
-    let dst = Path::new(&out_dir).join(path);
+    let dst = Path::new(&out_dir).join(*path);
     serde_codegen::expand(&src, &dst).unwrap();
-  })
+    println!("cargo:rerun-if-changed={}", full_src);
+  });
+
+  let mut build_id_file = File::create(Path::new(&out_dir).join("build_id")).unwrap();
+  build_id_file.write_all(build_id().as_bytes()).unwrap();
+}
+
+/**
+ * Names this build: the package version, and the commit it was built from
+ *
+ * Builds from outside a git checkout are told apart by when they were built instead.
+ */
+fn build_id() -> String {
+  let version = env::var("CARGO_PKG_VERSION").unwrap_or("unknown".to_owned());
+  let git_dir = git(&["rev-parse", "--git-dir"]);
+  match (git(&["describe", "--always", "--dirty"]), git_dir) {
+    (Some(commit), Some(git_dir)) => {
+      // Rebuilt when the checked out commit or the working tree changes
+      println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+      println!("cargo:rerun-if-changed={}/index", git_dir);
+      format!("{}+{}", version, commit)
+    },
+    _ => {
+      let built_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+      format!("{}+built.{}", version, built_at)
+    },
+  }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+  Command::new("git")
+    .args(args)
+    .output()
+    .ok()
+    .and_then(|output| if output.status.success() {
+      String::from_utf8(output.stdout).ok()
+    } else {
+      None
+    })
+    .map(|out| out.trim().to_owned())
 }
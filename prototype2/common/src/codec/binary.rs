@@ -0,0 +1,707 @@
+use std::cmp;
+use std::str;
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use serde::{Deserialize, Serialize, de, ser};
+use uuid::Uuid;
+
+use codec::{CodecError, UUID_NEWTYPE};
+
+/// Bumped whenever the format below changes (not when the types written in it do)
+const BINARY_CODEC_VERSION: u8 = 10;
+
+/**
+ * Encodes a value in a compact, versioned binary format
+ *
+ * Every payload starts with the format version. Numbers are fixed width and big endian, strings
+ * and collections are prefixed with their length, options and enum variants with a one byte tag,
+ * and structs and tuples are just their fields in order. Uuids are their 16 raw bytes.
+ *
+ * Nothing says what type comes next, so both ends must agree on the types being sent, which the
+ * protocol version sees to.
+ */
+pub fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+  let mut serializer = BinarySerializer {
+    buf: vec![BINARY_CODEC_VERSION],
+    uuid_next: false,
+  };
+  // Only fails for types that can't be written at all, which the protocol doesn't use
+  value.serialize(&mut serializer).unwrap();
+  serializer.buf
+}
+
+// NOTE: Trailing bytes are ignored, as packets may arrive padded
+pub fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, CodecError> {
+  let mut deserializer = BinaryDeserializer { bytes: bytes };
+  let version = try!(deserializer.take(1))[0];
+  if version != BINARY_CODEC_VERSION {
+    return Err(CodecError::UnsupportedVersion(version));
+  }
+  T::deserialize(&mut deserializer)
+}
+
+struct BinarySerializer {
+  buf: Vec<u8>,
+  /// Whether the next string is a uuid's, to be written as its raw bytes
+  uuid_next: bool,
+}
+
+impl BinarySerializer {
+  fn write_len(&mut self, len: usize) -> Result<(), CodecError> {
+    if len > u32::max_value() as usize {
+      return Err(CodecError::Malformed(format!("{} items is too many to write", len)));
+    }
+    self.buf.write_u32::<BigEndian>(len as u32).unwrap();
+    Ok(())
+  }
+
+  fn write_tag(&mut self, variant_index: usize) -> Result<(), CodecError> {
+    if variant_index > u8::max_value() as usize {
+      return Err(CodecError::Malformed(format!("variant {} is past the last tag", variant_index)));
+    }
+    self.buf.push(variant_index as u8);
+    Ok(())
+  }
+
+  fn write_elements<V: ser::SeqVisitor>(&mut self, mut visitor: V) -> Result<(), CodecError> {
+    while let Some(()) = try!(visitor.visit(self)) {}
+    Ok(())
+  }
+
+  fn write_entries<V: ser::MapVisitor>(&mut self, mut visitor: V) -> Result<(), CodecError> {
+    while let Some(()) = try!(visitor.visit(self)) {}
+    Ok(())
+  }
+}
+
+// NOTE: Writing to a Vec can't fail, hence the unwraps
+impl ser::Serializer for BinarySerializer {
+  type Error = CodecError;
+
+  fn serialize_bool(&mut self, value: bool) -> Result<(), CodecError> {
+    self.buf.push(if value { 1 } else { 0 });
+    Ok(())
+  }
+
+  fn serialize_i64(&mut self, value: i64) -> Result<(), CodecError> {
+    self.buf.write_i64::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_u8(&mut self, value: u8) -> Result<(), CodecError> {
+    self.buf.push(value);
+    Ok(())
+  }
+
+  fn serialize_u16(&mut self, value: u16) -> Result<(), CodecError> {
+    self.buf.write_u16::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_u32(&mut self, value: u32) -> Result<(), CodecError> {
+    self.buf.write_u32::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_u64(&mut self, value: u64) -> Result<(), CodecError> {
+    self.buf.write_u64::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_f32(&mut self, value: f32) -> Result<(), CodecError> {
+    self.buf.write_f32::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_f64(&mut self, value: f64) -> Result<(), CodecError> {
+    self.buf.write_f64::<BigEndian>(value).unwrap();
+    Ok(())
+  }
+
+  fn serialize_str(&mut self, value: &str) -> Result<(), CodecError> {
+    if self.uuid_next {
+      self.uuid_next = false;
+      let uuid = try!(Uuid::parse_str(value)
+        .map_err(|err| CodecError::Malformed(format!("{:?}", err))));
+      self.buf.extend_from_slice(uuid.as_bytes());
+      return Ok(());
+    }
+
+    try!(self.write_len(value.len()));
+    self.buf.extend_from_slice(value.as_bytes());
+    Ok(())
+  }
+
+  fn serialize_unit(&mut self) -> Result<(), CodecError> {
+    Ok(())
+  }
+
+  fn serialize_none(&mut self) -> Result<(), CodecError> {
+    self.buf.push(0);
+    Ok(())
+  }
+
+  fn serialize_some<V: Serialize>(&mut self, value: V) -> Result<(), CodecError> {
+    self.buf.push(1);
+    value.serialize(self)
+  }
+
+  fn serialize_seq<V: ser::SeqVisitor>(&mut self, visitor: V) -> Result<(), CodecError> {
+    match visitor.len() {
+      Some(len) => try!(self.write_len(len)),
+      None => return Err(CodecError::Malformed("sequence of unknown length".to_owned())),
+    }
+    self.write_elements(visitor)
+  }
+
+  fn serialize_seq_elt<T: Serialize>(&mut self, value: T) -> Result<(), CodecError> {
+    value.serialize(self)
+  }
+
+  fn serialize_tuple<V: ser::SeqVisitor>(&mut self, visitor: V) -> Result<(), CodecError> {
+    self.write_elements(visitor)
+  }
+
+  fn serialize_map<V: ser::MapVisitor>(&mut self, visitor: V) -> Result<(), CodecError> {
+    match visitor.len() {
+      Some(len) => try!(self.write_len(len)),
+      None => return Err(CodecError::Malformed("map of unknown length".to_owned())),
+    }
+    self.write_entries(visitor)
+  }
+
+  fn serialize_map_elt<K: Serialize, V: Serialize>(&mut self,
+                                                   key: K,
+                                                   value: V)
+                                                   -> Result<(), CodecError> {
+    try!(key.serialize(self));
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_struct<T: Serialize>(&mut self,
+                                            name: &'static str,
+                                            value: T)
+                                            -> Result<(), CodecError> {
+    self.uuid_next = name == UUID_NEWTYPE;
+    let result = value.serialize(self);
+    self.uuid_next = false;
+    result
+  }
+
+  fn serialize_struct<V: ser::MapVisitor>(&mut self,
+                                          _name: &'static str,
+                                          visitor: V)
+                                          -> Result<(), CodecError> {
+    self.write_entries(visitor)
+  }
+
+  fn serialize_struct_elt<V: Serialize>(&mut self,
+                                        _key: &'static str,
+                                        value: V)
+                                        -> Result<(), CodecError> {
+    value.serialize(self)
+  }
+
+  fn serialize_unit_variant(&mut self,
+                            _name: &'static str,
+                            variant_index: usize,
+                            _variant: &'static str)
+                            -> Result<(), CodecError> {
+    self.write_tag(variant_index)
+  }
+
+  fn serialize_newtype_variant<T: Serialize>(&mut self,
+                                             _name: &'static str,
+                                             variant_index: usize,
+                                             _variant: &'static str,
+                                             value: T)
+                                             -> Result<(), CodecError> {
+    try!(self.write_tag(variant_index));
+    value.serialize(self)
+  }
+
+  fn serialize_tuple_variant<V: ser::SeqVisitor>(&mut self,
+                                                 _name: &'static str,
+                                                 variant_index: usize,
+                                                 _variant: &'static str,
+                                                 visitor: V)
+                                                 -> Result<(), CodecError> {
+    try!(self.write_tag(variant_index));
+    self.write_elements(visitor)
+  }
+
+  fn serialize_struct_variant<V: ser::MapVisitor>(&mut self,
+                                                  _name: &'static str,
+                                                  variant_index: usize,
+                                                  _variant: &'static str,
+                                                  visitor: V)
+                                                  -> Result<(), CodecError> {
+    try!(self.write_tag(variant_index));
+    self.write_entries(visitor)
+  }
+}
+
+/**
+ * A cursor over a received payload
+ */
+struct BinaryDeserializer<'a> {
+  bytes: &'a [u8],
+}
+
+impl<'a> BinaryDeserializer<'a> {
+  fn take(&mut self, count: usize) -> Result<&'a [u8], CodecError> {
+    if self.bytes.len() < count {
+      return Err(CodecError::Truncated);
+    }
+
+    let (taken, rest) = self.bytes.split_at(count);
+    self.bytes = rest;
+    Ok(taken)
+  }
+
+  fn read_len(&mut self) -> Result<usize, CodecError> {
+    self.take(4).map(|bytes| BigEndian::read_u32(bytes) as usize)
+  }
+
+  fn read_flag(&mut self, name: &'static str) -> Result<bool, CodecError> {
+    match try!(self.take(1))[0] {
+      0 => Ok(false),
+      1 => Ok(true),
+      tag => Err(CodecError::UnknownTag(name, tag)),
+    }
+  }
+}
+
+impl<'a> de::Deserializer for BinaryDeserializer<'a> {
+  type Error = CodecError;
+
+  fn deserialize<V: de::Visitor>(&mut self, _visitor: V) -> Result<V::Value, CodecError> {
+    Err(CodecError::Malformed("the binary format can't say what type comes next".to_owned()))
+  }
+
+  fn deserialize_bool<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = try!(self.read_flag("bool"));
+    visitor.visit_bool(value)
+  }
+
+  fn deserialize_i64<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_i64(try!(self.take(8)));
+    visitor.visit_i64(value)
+  }
+
+  fn deserialize_u8<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = try!(self.take(1))[0];
+    visitor.visit_u8(value)
+  }
+
+  fn deserialize_u16<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_u16(try!(self.take(2)));
+    visitor.visit_u16(value)
+  }
+
+  fn deserialize_u32<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_u32(try!(self.take(4)));
+    visitor.visit_u32(value)
+  }
+
+  fn deserialize_u64<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_u64(try!(self.take(8)));
+    visitor.visit_u64(value)
+  }
+
+  fn deserialize_f32<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_f32(try!(self.take(4)));
+    visitor.visit_f32(value)
+  }
+
+  fn deserialize_f64<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let value = BigEndian::read_f64(try!(self.take(8)));
+    visitor.visit_f64(value)
+  }
+
+  fn deserialize_str<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let len = try!(self.read_len());
+    let bytes = try!(self.take(len));
+    let value = try!(str::from_utf8(bytes).map_err(|_| CodecError::InvalidUtf8));
+    visitor.visit_str(value)
+  }
+
+  fn deserialize_unit<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_option<V: de::Visitor>(&mut self,
+                                        mut visitor: V)
+                                        -> Result<V::Value, CodecError> {
+    if try!(self.read_flag("Option")) {
+      visitor.visit_some(self)
+    } else {
+      visitor.visit_none()
+    }
+  }
+
+  fn deserialize_seq<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let len = try!(self.read_len());
+    visitor.visit_seq(Elements {
+      de: self,
+      remaining: len,
+    })
+  }
+
+  fn deserialize_map<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    let len = try!(self.read_len());
+    visitor.visit_map(Elements {
+      de: self,
+      remaining: len,
+    })
+  }
+
+  fn deserialize_unit_struct<V: de::Visitor>(&mut self,
+                                             _name: &'static str,
+                                             mut visitor: V)
+                                             -> Result<V::Value, CodecError> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V: de::Visitor>(&mut self,
+                                                name: &'static str,
+                                                mut visitor: V)
+                                                -> Result<V::Value, CodecError> {
+    if name == UUID_NEWTYPE {
+      let bytes = try!(self.take(16));
+      return visitor.visit_bytes(bytes);
+    }
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_tuple_struct<V: de::Visitor>(&mut self,
+                                              _name: &'static str,
+                                              len: usize,
+                                              visitor: V)
+                                              -> Result<V::Value, CodecError> {
+    self.deserialize_tuple(len, visitor)
+  }
+
+  fn deserialize_struct<V: de::Visitor>(&mut self,
+                                        _name: &'static str,
+                                        fields: &'static [&'static str],
+                                        visitor: V)
+                                        -> Result<V::Value, CodecError> {
+    self.deserialize_tuple(fields.len(), visitor)
+  }
+
+  fn deserialize_tuple<V: de::Visitor>(&mut self,
+                                       len: usize,
+                                       mut visitor: V)
+                                       -> Result<V::Value, CodecError> {
+    visitor.visit_seq(Elements {
+      de: self,
+      remaining: len,
+    })
+  }
+
+  fn deserialize_enum<V: de::EnumVisitor>(&mut self,
+                                          _name: &'static str,
+                                          _variants: &'static [&'static str],
+                                          mut visitor: V)
+                                          -> Result<V::Value, CodecError> {
+    visitor.visit(Variant { de: self })
+  }
+}
+
+/**
+ * The elements of a sequence, tuple or struct, or the entries of a map, as they're read
+ */
+struct Elements<'a, 'b: 'a> {
+  de: &'a mut BinaryDeserializer<'b>,
+  remaining: usize,
+}
+
+impl<'a, 'b> Elements<'a, 'b> {
+  fn next<T: Deserialize>(&mut self) -> Result<Option<T>, CodecError> {
+    if self.remaining == 0 {
+      return Ok(None);
+    }
+
+    self.remaining = self.remaining - 1;
+    T::deserialize(&mut *self.de).map(Some)
+  }
+
+  fn finish(&mut self) -> Result<(), CodecError> {
+    if self.remaining == 0 {
+      Ok(())
+    } else {
+      Err(CodecError::Malformed(format!("{} elements left unread", self.remaining)))
+    }
+  }
+
+  // The length came off the wire, so it's only trusted as far as there are bytes to back it
+  fn hint(&self) -> (usize, Option<usize>) {
+    (cmp::min(self.remaining, self.de.bytes.len()), Some(self.remaining))
+  }
+}
+
+impl<'a, 'b> de::SeqVisitor for Elements<'a, 'b> {
+  type Error = CodecError;
+
+  fn visit<T: Deserialize>(&mut self) -> Result<Option<T>, CodecError> {
+    self.next()
+  }
+
+  fn end(&mut self) -> Result<(), CodecError> {
+    self.finish()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.hint()
+  }
+}
+
+impl<'a, 'b> de::MapVisitor for Elements<'a, 'b> {
+  type Error = CodecError;
+
+  fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, CodecError> {
+    self.next()
+  }
+
+  fn visit_value<V: Deserialize>(&mut self) -> Result<V, CodecError> {
+    V::deserialize(&mut *self.de)
+  }
+
+  fn end(&mut self) -> Result<(), CodecError> {
+    self.finish()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.hint()
+  }
+}
+
+/**
+ * An enum variant being read: its tag, then whatever fields it has
+ */
+struct Variant<'a, 'b: 'a> {
+  de: &'a mut BinaryDeserializer<'b>,
+}
+
+impl<'a, 'b> de::VariantVisitor for Variant<'a, 'b> {
+  type Error = CodecError;
+
+  fn visit_variant<V: Deserialize>(&mut self) -> Result<V, CodecError> {
+    let tag = try!(self.de.take(1))[0];
+    V::deserialize(&mut Tag(tag))
+  }
+
+  fn visit_unit(&mut self) -> Result<(), CodecError> {
+    Ok(())
+  }
+
+  fn visit_newtype<T: Deserialize>(&mut self) -> Result<T, CodecError> {
+    T::deserialize(&mut *self.de)
+  }
+
+  fn visit_tuple<V: de::Visitor>(&mut self,
+                                 len: usize,
+                                 visitor: V)
+                                 -> Result<V::Value, CodecError> {
+    de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)
+  }
+
+  fn visit_struct<V: de::Visitor>(&mut self,
+                                  fields: &'static [&'static str],
+                                  visitor: V)
+                                  -> Result<V::Value, CodecError> {
+    de::Deserializer::deserialize_tuple(&mut *self.de, fields.len(), visitor)
+  }
+}
+
+/**
+ * A variant tag, handed to whatever tells the variants apart as their index
+ */
+struct Tag(u8);
+
+impl de::Deserializer for Tag {
+  type Error = CodecError;
+
+  fn deserialize<V: de::Visitor>(&mut self, mut visitor: V) -> Result<V::Value, CodecError> {
+    visitor.visit_usize(self.0 as usize)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use aspects::{CommonWorld, DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
+  use codec::{CodecError, CodecKind};
+  use model::ModelType;
+  use protocol::{BUTTON_JUMP, BUTTON_PRIMARY, Capability, ClientEvent, ClientMessage,
+                 ClientNetworkEvent, ConnectAccepted, ConnectRejection, ConnectRequest,
+                 PlayerInput, ServerEcho, ServerNetworkEvent, SessionToken, SnapshotEvent,
+                 StateFragment};
+  use snapshot::WorldDelta;
+
+  const CODECS: [CodecKind; 2] = [CodecKind::Binary, CodecKind::Json];
+
+  fn client_messages() -> Vec<ClientMessage> {
+    let mut physical = PhysicalAspect::new((1.0, -2.5, 3.0), (0.5, 0.0, -0.25));
+    physical.ang = (0.5, 0.5, -0.5, 0.5);
+    let render = RenderAspect::new_with(ModelType::Icosphere2);
+    let mut admin_request = ConnectRequest::current();
+    admin_request.admin_secret = Some("hunter2".to_owned());
+    let events = vec![ClientNetworkEvent::Connect(ConnectRequest::current()),
+                      ClientNetworkEvent::Connect(admin_request),
+                      ClientNetworkEvent::Disconnect,
+                      ClientNetworkEvent::KeepAlive {
+                        client_ms: 1234,
+                        echo: None,
+                      },
+                      ClientNetworkEvent::KeepAlive {
+                        client_ms: 1254,
+                        echo: Some(ServerEcho {
+                          server_ms: 98765,
+                          held_ms: 12,
+                        }),
+                      },
+                      ClientNetworkEvent::SnapshotAck(65535),
+                      ClientNetworkEvent::DomainEvent(ClientEvent::PlayerInput(PlayerInput {
+                        seq: 4000000000,
+                        movement: (0.6, -0.8, 0.0),
+                        look: (3.1, -0.5),
+                        buttons: BUTTON_PRIMARY | BUTTON_JUMP,
+                      })),
+                      ClientNetworkEvent::DomainEvent(ClientEvent::MutatePhysicalAspect(
+                        SynchronizedAspect::new(), physical)),
+                      ClientNetworkEvent::DomainEvent(ClientEvent::MutateRenderAspect(
+                        SynchronizedAspect::new(), render)),
+                      ClientNetworkEvent::DomainEvent(ClientEvent::DeleteEntity(
+                        SynchronizedAspect::new())),
+                      ClientNetworkEvent::DomainEvent(ClientEvent::CreateEntity)];
+
+    events.into_iter()
+      .enumerate()
+      .map(|(idx, event)| {
+        ClientMessage {
+          session: if idx % 2 == 0 { Some(SessionToken::new()) } else { None },
+          event: event,
+        }
+      })
+      .collect()
+  }
+
+  fn server_events() -> Vec<ServerNetworkEvent> {
+    vec![ServerNetworkEvent::Connected(ConnectAccepted {
+           session: SessionToken::new(),
+           capabilities: Capability::all(),
+         }),
+         ServerNetworkEvent::Rejected(ConnectRejection::ProtocolMismatch {
+           server_version: 7,
+           server_build_id: "0.2.0+4915994".to_owned(),
+           client_version: 6,
+         }),
+         ServerNetworkEvent::Rejected(ConnectRejection::ShuttingDown("Maintenance".to_owned())),
+         ServerNetworkEvent::Disconnected,
+         ServerNetworkEvent::KeepAlive {
+           client_ms: 1234,
+           server_tick: 400,
+           server_ms: 98765,
+           held_ms: 15,
+         },
+         ServerNetworkEvent::Error("Tried to disconnect, but not connected".to_owned()),
+         ServerNetworkEvent::Snapshot(SnapshotEvent::PartialSnapshot(StateFragment {
+           seq_num: 12,
+           idx: 3,
+           count: 4,
+           payload: vec![0, 1, 2, 254, 255],
+         })),
+         ServerNetworkEvent::ShuttingDown {
+           reason: "Maintenance".to_owned(),
+           eta_ms: 2000,
+         }]
+  }
+
+  #[test]
+  fn events_round_trip() {
+    for codec in CODECS.iter() {
+      for message in client_messages() {
+        let bytes = codec.encode(&message);
+        assert_eq!(codec.decode::<ClientMessage>(&bytes), Ok(message));
+      }
+      for event in server_events() {
+        let bytes = codec.encode(&event);
+        assert_eq!(codec.decode::<ServerNetworkEvent>(&bytes), Ok(event));
+      }
+    }
+  }
+
+  #[test]
+  fn snapshots_round_trip() {
+    let (moved, still) = (SynchronizedAspect::new(), SynchronizedAspect::new());
+    let mut baseline = CommonWorld::new();
+    baseline.entities.insert(still.clone());
+    let mut target = CommonWorld::new();
+    target.entities.insert(moved.clone());
+    target.own_entity = Some(moved.clone());
+    target.physical.insert(moved.to_string(),
+                           PhysicalAspect::new((1.0, 2.0, 3.0), (0.0, 0.0, 0.5)));
+    target.rendered.insert(moved.to_string(), RenderAspect::new());
+    target.disabled.insert(moved.to_string(), DisabledAspect::new());
+    let delta = WorldDelta::between(Some((3, &baseline)), &target).stamped(77, 1500);
+
+    for codec in CODECS.iter() {
+      let bytes = codec.encode(&delta);
+      assert_eq!(codec.decode::<WorldDelta>(&bytes), Ok(delta.clone()));
+    }
+  }
+
+  #[test]
+  fn fields_are_written_after_their_tags() {
+    let message = ClientMessage {
+      session: Some(SessionToken::new()),
+      event: ClientNetworkEvent::DomainEvent(ClientEvent::DeleteEntity(SynchronizedAspect::new())),
+    };
+
+    // version, session tag, session, ClientNetworkEvent tag, ClientEvent tag, synchro
+    // Ids are written as their raw uuid bytes
+    assert_eq!(CodecKind::Binary.encode(&message).len(), 1 + 1 + 16 + 1 + 1 + 16);
+  }
+
+  #[test]
+  fn uuids_are_raw_bytes() {
+    let synchro = SynchronizedAspect::new();
+
+    let bytes = CodecKind::Binary.encode(&synchro);
+    assert_eq!(&bytes[1..], synchro.uuid().as_bytes());
+    // Json keeps them readable
+    let json = String::from_utf8(CodecKind::Json.encode(&synchro)).unwrap();
+    assert_eq!(json, format!("\"{}\"", synchro.uuid()));
+  }
+
+  #[test]
+  fn bad_payloads_are_rejected() {
+    let message = ClientMessage {
+      session: None,
+      event: ClientNetworkEvent::SnapshotAck(4),
+    };
+    let mut bytes = CodecKind::Binary.encode(&message);
+
+    let truncated = &bytes[0..bytes.len() - 1];
+    assert_eq!(CodecKind::Binary.decode::<ClientMessage>(truncated),
+               Err(CodecError::Truncated));
+
+    bytes[0] = 200;
+    assert_eq!(CodecKind::Binary.decode::<ClientMessage>(&bytes),
+               Err(CodecError::UnsupportedVersion(200)));
+  }
+
+  #[test]
+  fn unknown_variants_are_rejected() {
+    let message = ClientMessage {
+      session: None,
+      event: ClientNetworkEvent::Disconnect,
+    };
+    let mut bytes = CodecKind::Binary.encode(&message);
+
+    // version, session tag, ClientNetworkEvent tag
+    bytes[2] = 200;
+    assert!(CodecKind::Binary.decode::<ClientMessage>(&bytes).is_err());
+  }
+}
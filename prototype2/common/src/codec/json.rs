@@ -0,0 +1,28 @@
+use std::str;
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use codec::CodecError;
+
+/**
+ * Encodes a value as UTF-8 Json
+ *
+ * Large and slow, but readable in a packet capture.
+ */
+pub fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+  serde_json::to_string(value).unwrap().into_bytes()
+}
+
+pub fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, CodecError> {
+  trimmed_str(bytes).and_then(|s| {
+    serde_json::from_str(s).map_err(|err| CodecError::Malformed(format!("{:?}", err)))
+  })
+}
+
+// Packets may arrive padded with trailing NULs
+fn trimmed_str(bytes: &[u8]) -> Result<&str, CodecError> {
+  str::from_utf8(bytes)
+    .map(|s| s.trim_right_matches('\0'))
+    .map_err(|_| CodecError::InvalidUtf8)
+}
@@ -0,0 +1,153 @@
+mod binary;
+mod json;
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+use uuid::Uuid;
+
+/**
+ * The formats payloads can be converted to and from for the wire
+ *
+ * Both peers must agree on the codec in use. Binary is the compact default, while Json is kept
+ * around as a human-readable format for debugging.
+ *
+ * Either works on anything serde can serialize, so protocol types only need their derives.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodecKind {
+  Json,
+  Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecError {
+  /// The payload was encoded with a version of the format we don't understand
+  UnsupportedVersion(u8),
+  /// A tag didn't name any known value of the named type
+  UnknownTag(&'static str, u8),
+  /// The payload ended before the value it describes did
+  Truncated,
+  InvalidUtf8,
+  Malformed(String),
+}
+
+pub struct InvalidCodec;
+
+impl CodecKind {
+  pub fn encode<T: Serialize>(&self, value: &T) -> Vec<u8> {
+    match *self {
+      CodecKind::Json => json::encode(value),
+      CodecKind::Binary => binary::encode(value),
+    }
+  }
+
+  pub fn decode<T: Deserialize>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+    match *self {
+      CodecKind::Json => json::decode(bytes),
+      CodecKind::Binary => binary::decode(bytes),
+    }
+  }
+}
+
+impl FromStr for CodecKind {
+  type Err = InvalidCodec;
+  fn from_str(s: &str) -> Result<CodecKind, InvalidCodec> {
+    match s {
+      "json" => Ok(CodecKind::Json),
+      "binary" => Ok(CodecKind::Binary),
+      _ => Err(InvalidCodec),
+    }
+  }
+}
+
+impl fmt::Display for CodecKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      CodecKind::Json => write!(f, "json"),
+      CodecKind::Binary => write!(f, "binary"),
+    }
+  }
+}
+
+impl fmt::Display for CodecError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      &CodecError::UnsupportedVersion(version) => write!(f, "unsupported format v{}", version),
+      &CodecError::UnknownTag(name, tag) => write!(f, "unknown {} tag {}", name, tag),
+      &CodecError::Truncated => write!(f, "payload ended early"),
+      &CodecError::InvalidUtf8 => write!(f, "invalid utf-8"),
+      &CodecError::Malformed(ref msg) => write!(f, "malformed payload ({})", msg),
+    }
+  }
+}
+
+impl error::Error for CodecError {
+  fn description(&self) -> &str {
+    match self {
+      &CodecError::UnsupportedVersion(_) => "unsupported format version",
+      &CodecError::UnknownTag(_, _) => "unknown tag",
+      &CodecError::Truncated => "payload ended early",
+      &CodecError::InvalidUtf8 => "invalid utf-8",
+      &CodecError::Malformed(_) => "malformed payload",
+    }
+  }
+}
+
+impl ser::Error for CodecError {
+  fn custom<T: Into<String>>(msg: T) -> CodecError {
+    CodecError::Malformed(msg.into())
+  }
+}
+
+impl de::Error for CodecError {
+  fn custom<T: Into<String>>(msg: T) -> CodecError {
+    CodecError::Malformed(msg.into())
+  }
+
+  fn end_of_stream() -> CodecError {
+    CodecError::Truncated
+  }
+}
+
+/// The newtype name uuids are written under, which lets a codec pick its own form for them
+pub const UUID_NEWTYPE: &'static str = "Uuid";
+
+/**
+ * Writes a uuid as its hyphenated string, inside a newtype named UUID_NEWTYPE
+ *
+ * For the ids wrapping uuids. Codecs that don't know the name see just the string, so they read
+ * the same in every codec, while the binary codec writes the 16 raw bytes instead.
+ */
+pub fn serialize_uuid<S: Serializer>(uuid: &Uuid, serializer: &mut S) -> Result<(), S::Error> {
+  serializer.serialize_newtype_struct(UUID_NEWTYPE, uuid.to_string())
+}
+
+/**
+ * Reads a uuid written by serialize_uuid
+ */
+pub fn deserialize_uuid<D: Deserializer>(deserializer: &mut D) -> Result<Uuid, D::Error> {
+  deserializer.deserialize_newtype_struct(UUID_NEWTYPE, UuidVisitor)
+}
+
+struct UuidVisitor;
+
+impl de::Visitor for UuidVisitor {
+  type Value = Uuid;
+
+  fn visit_newtype_struct<D: Deserializer>(&mut self,
+                                           deserializer: &mut D)
+                                           -> Result<Uuid, D::Error> {
+    deserializer.deserialize_str(UuidVisitor)
+  }
+
+  fn visit_str<E: de::Error>(&mut self, value: &str) -> Result<Uuid, E> {
+    Uuid::parse_str(value).map_err(|err| E::custom(format!("{:?}", err)))
+  }
+
+  fn visit_bytes<E: de::Error>(&mut self, value: &[u8]) -> Result<Uuid, E> {
+    Uuid::from_bytes(value).map_err(|err| E::custom(format!("{:?}", err)))
+  }
+}
@@ -1,23 +1,41 @@
+extern crate byteorder;
 extern crate serde;
 extern crate serde_json;
 extern crate gaffer_udp;
 extern crate specs;
 extern crate itertools;
 extern crate uuid;
+extern crate rand;
 extern crate time;
 
 /// Describes outbound and inbound payloads
 ///
 pub mod protocol;
 
+/// Converts payloads to and from their wire format
+///
+pub mod codec;
+
 /// Describes shared client/server aspects
 ///
 pub mod aspects;
 
+/// Describes world snapshots and the deltas between them
+///
+pub mod snapshot;
+
 /// Manages Network IO
 ///
 pub mod network;
 
+/// Steers player entities, the same way on both ends
+///
+pub mod movement;
+
+/// Converts between game and physics space, and turns orientations
+///
+pub mod space;
+
 /// Convenience wrappers for builtin types
 ///
 pub mod util;
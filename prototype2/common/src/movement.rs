@@ -0,0 +1,81 @@
+/// Fastest a player can steer their entity, in units per second
+pub const MAX_WALK_SPEED: f32 = 5.0;
+
+/// Quickest a player can change their entity's speed, in units per second per second
+pub const MAX_WALK_ACCELERATION: f32 = 20.0;
+
+/**
+ * Shortens movement longer than 1 down to 1, and treats movement that isn't a number as none
+ */
+pub fn clamped(movement: (f32, f32, f32)) -> (f32, f32, f32) {
+  let (x, y, z) = movement;
+  if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+    return (0.0, 0.0, 0.0);
+  }
+
+  let len = (x * x + y * y + z * z).sqrt();
+  if len > 1.0 {
+    (x / len, y / len, z / len)
+  } else {
+    movement
+  }
+}
+
+/**
+ * Turns a velocity toward the one a player's movement asks for, no faster than the acceleration
+ * limit allows over dt_s
+ *
+ * Only the horizontal (x, y) part is steered, so falling and bouncing are left to physics.
+ * Movement is clamped first, so diagonals are no faster than straight lines.
+ */
+pub fn steer(vel: (f32, f32, f32), movement: (f32, f32, f32), dt_s: f32) -> (f32, f32, f32) {
+  let movement = clamped(movement);
+  let desired = (movement.0 * MAX_WALK_SPEED, movement.1 * MAX_WALK_SPEED);
+
+  let change = (desired.0 - vel.0, desired.1 - vel.1);
+  let change_len = (change.0 * change.0 + change.1 * change.1).sqrt();
+  let max_change = MAX_WALK_ACCELERATION * dt_s;
+
+  if change_len <= max_change {
+    (desired.0, desired.1, vel.2)
+  } else {
+    let fraction = max_change / change_len;
+    (vel.0 + change.0 * fraction, vel.1 + change.1 * fraction, vel.2)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn speeds_up_no_faster_than_the_limit() {
+    let vel = steer((0.0, 0.0, -1.0), (1.0, 0.0, 0.0), 0.1);
+    assert_eq!(vel, (MAX_WALK_ACCELERATION * 0.1, 0.0, -1.0));
+
+    let vel = steer(vel, (1.0, 0.0, 0.0), 1.0);
+    assert_eq!(vel, (MAX_WALK_SPEED, 0.0, -1.0));
+  }
+
+  #[test]
+  fn diagonals_are_no_faster() {
+    let (x, y, _) = steer((0.0, 0.0, 0.0), (1.0, 1.0, 0.0), 10.0);
+    assert!(((x * x + y * y).sqrt() - MAX_WALK_SPEED).abs() < 0.0001);
+  }
+
+  #[test]
+  fn movement_is_clamped_to_a_number_no_longer_than_1() {
+    assert_eq!(clamped((3.0, 0.0, 4.0)), (0.6, 0.0, 0.8));
+    assert_eq!(clamped((0.5, 0.0, 0.0)), (0.5, 0.0, 0.0));
+    assert_eq!(clamped((::std::f32::NAN, 0.0, 0.0)), (0.0, 0.0, 0.0));
+    assert_eq!(clamped((::std::f32::INFINITY, 1.0, 0.0)), (0.0, 0.0, 0.0));
+
+    let (x, y, _) = steer((0.0, 0.0, 0.0), (::std::f32::MAX, 0.0, 0.0), 10.0);
+    assert!(x.is_finite() && y == 0.0 && x <= MAX_WALK_SPEED);
+  }
+
+  #[test]
+  fn stops_without_movement() {
+    assert_eq!(steer((1.0, -1.0, 0.0), (0.0, 0.0, 0.0), 1.0), (0.0, 0.0, 0.0));
+  }
+}
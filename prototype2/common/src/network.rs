@@ -1,3 +0,0 @@
-use std::net::SocketAddr;
-
-pub type Address = SocketAddr;
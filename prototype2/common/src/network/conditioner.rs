@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+use time;
+
+use network::{Datagram, Transport};
+
+/**
+ * How badly a ConditionedTransport should treat the datagrams passing through it
+ *
+ * Applies to each direction separately, so a round trip sees the latency twice.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkConditions {
+  /// Delay added to every datagram, in milliseconds
+  pub latency_ms: u32,
+  /// Most extra delay, in milliseconds, that may be added to or taken from the latency
+  pub jitter_ms: u32,
+  /// Chance (out of 100) that a datagram is dropped
+  pub loss_percent: f32,
+  /// How many datagrams ahead of it a datagram may jump
+  pub reorder_window: u32,
+  /// Chance (out of 100) that a datagram arrives twice
+  pub duplicate_percent: f32,
+  /// Seeds the randomness, so a given run can be reproduced
+  pub seed: u32,
+}
+
+impl LinkConditions {
+  /**
+   * Leaves datagrams alone
+   */
+  pub fn perfect() -> LinkConditions {
+    LinkConditions {
+      latency_ms: 0,
+      jitter_ms: 0,
+      loss_percent: 0.0,
+      reorder_window: 0,
+      duplicate_percent: 0.0,
+      seed: 0,
+    }
+  }
+
+  pub fn is_perfect(&self) -> bool {
+    LinkConditions { seed: self.seed, ..LinkConditions::perfect() } == *self
+  }
+}
+
+/**
+ * Wraps a transport, delaying, dropping, reordering and duplicating what passes through it
+ *
+ * Held datagrams are only let through when the transport is used, so it should be sent to or
+ * received from regularly (as the network adapters do every tick).
+ */
+pub struct ConditionedTransport {
+  inner: Box<Transport>,
+  conditions: LinkConditions,
+  rng: XorShiftRng,
+  clock: Box<Fn() -> u64 + Send>,
+  outbound: VecDeque<(u64, Datagram)>,
+  inbound: VecDeque<(u64, Datagram)>,
+}
+
+impl ConditionedTransport {
+  pub fn new(inner: Box<Transport>, conditions: LinkConditions) -> ConditionedTransport {
+    ConditionedTransport::with_clock(inner,
+                                     conditions,
+                                     Box::new(|| time::precise_time_ns() / 1_000_000))
+  }
+
+  /**
+   * Uses the given clock (in milliseconds) to decide when held datagrams are due
+   */
+  pub fn with_clock(inner: Box<Transport>,
+                    conditions: LinkConditions,
+                    clock: Box<Fn() -> u64 + Send>)
+                    -> ConditionedTransport {
+    // XorShift can't be seeded with all zeroes
+    let rng = XorShiftRng::from_seed([0x193a6754, 0xa8a7d469, 0x97830e05, conditions.seed]);
+
+    ConditionedTransport {
+      inner: inner,
+      conditions: conditions,
+      rng: rng,
+      clock: clock,
+      outbound: VecDeque::new(),
+      inbound: VecDeque::new(),
+    }
+  }
+
+  fn flush_outbound(&mut self) {
+    let now = (self.clock)();
+    while let Some(datagram) = take_due(&mut self.outbound, now) {
+      self.inner.send(datagram);
+    }
+  }
+}
+
+impl Transport for ConditionedTransport {
+  fn send(&mut self, datagram: Datagram) {
+    let now = (self.clock)();
+    hold(&mut self.outbound, datagram, now, &self.conditions, &mut self.rng);
+    self.flush_outbound();
+  }
+
+  fn recv(&mut self) -> Option<Datagram> {
+    let now = (self.clock)();
+    while let Some(datagram) = self.inner.recv() {
+      hold(&mut self.inbound, datagram, now, &self.conditions, &mut self.rng);
+    }
+    self.flush_outbound();
+
+    take_due(&mut self.inbound, now)
+  }
+}
+
+/**
+ * Queues the datagram (maybe twice, maybe not at all) to be let through once it's due
+ */
+fn hold(queue: &mut VecDeque<(u64, Datagram)>,
+        datagram: Datagram,
+        now: u64,
+        conditions: &LinkConditions,
+        rng: &mut XorShiftRng) {
+  if roll(rng, conditions.loss_percent) {
+    return;
+  }
+
+  let copies = if roll(rng, conditions.duplicate_percent) { 2 } else { 1 };
+  for _ in 0..copies {
+    let jitter = if conditions.jitter_ms > 0 {
+      rng.gen_range(-(conditions.jitter_ms as i64), conditions.jitter_ms as i64 + 1)
+    } else {
+      0
+    };
+    let due = (now as i64 + conditions.latency_ms as i64 + jitter).max(now as i64) as u64;
+
+    let jump = if conditions.reorder_window > 0 {
+      rng.gen_range(0, conditions.reorder_window as usize + 1).min(queue.len())
+    } else {
+      0
+    };
+    let position = queue.len() - jump;
+    queue.insert(position, (due, datagram.clone()));
+  }
+}
+
+/**
+ * Yields the datagram at the front of the queue if it's due
+ *
+ * A datagram that isn't due yet holds back everything behind it, so only the reorder window
+ * changes the order datagrams are let through in.
+ */
+fn take_due(queue: &mut VecDeque<(u64, Datagram)>, now: u64) -> Option<Datagram> {
+  let due = queue.front().map(|&(due, _)| due <= now).unwrap_or(false);
+  if due {
+    queue.pop_front().map(|(_, datagram)| datagram)
+  } else {
+    None
+  }
+}
+
+fn roll(rng: &mut XorShiftRng, percent: f32) -> bool {
+  percent > 0.0 && rng.gen::<f32>() * 100.0 < percent
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::net::SocketAddr;
+  use std::str::FromStr;
+  use std::sync::Arc;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use network::{Datagram, Loopback, Transport};
+
+  fn conditioned_pair(conditions: LinkConditions)
+                      -> (ConditionedTransport, Box<Transport>, Arc<AtomicUsize>, SocketAddr) {
+    let loopback = Loopback::new();
+    let sender_addr = SocketAddr::from_str("127.0.0.1:7190").unwrap();
+    let receiver_addr = SocketAddr::from_str("127.0.0.1:7090").unwrap();
+    let clock = Arc::new(AtomicUsize::new(0));
+    let clock_handle = clock.clone();
+
+    let sender = ConditionedTransport::with_clock(Box::new(loopback.bind(sender_addr)),
+                                                  conditions,
+                                                  Box::new(move || {
+                                                    clock_handle.load(Ordering::SeqCst) as u64
+                                                  }));
+    (sender, Box::new(loopback.bind(receiver_addr)), clock, receiver_addr)
+  }
+
+  fn drain(transport: &mut Box<Transport>) -> Vec<u8> {
+    let mut received = Vec::new();
+    while let Some(datagram) = transport.recv() {
+      received.push(datagram.payload[0]);
+    }
+    received
+  }
+
+  #[test]
+  fn datagrams_are_held_for_the_latency() {
+    let mut conditions = LinkConditions::perfect();
+    conditions.latency_ms = 50;
+    let (mut sender, mut receiver, clock, receiver_addr) = conditioned_pair(conditions);
+
+    sender.send(Datagram::new(receiver_addr, vec![1]));
+    sender.send(Datagram::new(receiver_addr, vec![2]));
+    assert_eq!(drain(&mut receiver), Vec::<u8>::new());
+
+    clock.store(50, Ordering::SeqCst);
+    sender.recv();
+    assert_eq!(drain(&mut receiver), vec![1, 2]);
+  }
+
+  #[test]
+  fn conditions_are_reproducible_from_the_seed() {
+    let mut conditions = LinkConditions::perfect();
+    conditions.loss_percent = 30.0;
+    conditions.duplicate_percent = 30.0;
+    conditions.reorder_window = 3;
+    conditions.seed = 7;
+
+    let run = |conditions: LinkConditions| {
+      let (mut sender, mut receiver, _, receiver_addr) = conditioned_pair(conditions);
+      for idx in 0..50 {
+        sender.send(Datagram::new(receiver_addr, vec![idx]));
+      }
+      drain(&mut receiver)
+    };
+
+    let received = run(conditions.clone());
+    assert_eq!(received, run(conditions.clone()));
+    assert!(received != (0..50).collect::<Vec<u8>>());
+  }
+
+  #[test]
+  fn perfect_conditions_ignore_the_seed() {
+    let mut conditions = LinkConditions::perfect();
+    conditions.seed = 12;
+    assert!(conditions.is_perfect());
+
+    conditions.latency_ms = 1;
+    assert!(!conditions.is_perfect());
+  }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use gaffer_udp::GafferPacket;
+use gaffer_udp::non_blocking::GafferSocket;
+
+mod conditioner;
+mod reliability;
+mod timing;
+
+pub use self::conditioner::{ConditionedTransport, LinkConditions};
+pub use self::reliability::{Delivery, Endpoint};
+pub use self::timing::{LinkStats, now_ms};
+
+pub type Address = SocketAddr;
+
+/**
+ * Some bytes, and the address they're from (when received) or to (when sent)
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Datagram {
+  pub address: Address,
+  pub payload: Vec<u8>,
+}
+
+impl Datagram {
+  pub fn new(address: Address, payload: Vec<u8>) -> Datagram {
+    Datagram {
+      address: address,
+      payload: payload,
+    }
+  }
+}
+
+/**
+ * Moves datagrams between peers without blocking
+ *
+ * Delivery is best effort: a datagram may be dropped on the way, and a send that fails is
+ * dropped too.
+ */
+pub trait Transport: Send {
+  fn send(&mut self, datagram: Datagram);
+
+  /**
+   * Yields the next datagram to have arrived, if there is one
+   */
+  fn recv(&mut self) -> Option<Datagram>;
+}
+
+/**
+ * Sends over UDP, using a GafferSocket (a wrapper around UDP for some reliability)
+ */
+pub struct UdpTransport {
+  socket: GafferSocket,
+}
+
+impl UdpTransport {
+  pub fn bind(address: Address) -> io::Result<UdpTransport> {
+    GafferSocket::bind(address).map(|socket| UdpTransport { socket: socket })
+  }
+}
+
+impl Transport for UdpTransport {
+  fn send(&mut self, datagram: Datagram) {
+    let _ = self.socket.send(GafferPacket::new(datagram.address, datagram.payload));
+  }
+
+  fn recv(&mut self) -> Option<Datagram> {
+    self.socket
+      .recv()
+      .ok()
+      .and_then(|v| v)
+      .map(|packet| Datagram::new(packet.addr, packet.payload.as_ref().to_vec()))
+  }
+}
+
+/**
+ * A pretend network that lives in memory, so peers in one process can talk without sockets
+ *
+ * Nothing is ever dropped or reordered, and a datagram sent is available to its recipient
+ * immediately, which makes it handy for stepping a server and clients deterministically in tests.
+ */
+#[derive(Clone)]
+pub struct Loopback {
+  inboxes: Arc<Mutex<HashMap<Address, Sender<Datagram>>>>,
+}
+
+impl Loopback {
+  pub fn new() -> Loopback {
+    Loopback { inboxes: Arc::new(Mutex::new(HashMap::new())) }
+  }
+
+  /**
+   * Attaches a peer at the address, replacing any peer already there
+   */
+  pub fn bind(&self, address: Address) -> LoopbackTransport {
+    let (sender, receiver) = mpsc::channel();
+    self.inboxes.lock().unwrap().insert(address, sender);
+
+    LoopbackTransport {
+      address: address,
+      loopback: self.clone(),
+      inbox: receiver,
+    }
+  }
+}
+
+pub struct LoopbackTransport {
+  address: Address,
+  loopback: Loopback,
+  inbox: Receiver<Datagram>,
+}
+
+impl Transport for LoopbackTransport {
+  fn send(&mut self, datagram: Datagram) {
+    // Datagrams to nobody are dropped, as they would be over UDP
+    if let Some(inbox) = self.loopback.inboxes.lock().unwrap().get(&datagram.address) {
+      let _ = inbox.send(Datagram::new(self.address, datagram.payload));
+    }
+  }
+
+  fn recv(&mut self) -> Option<Datagram> {
+    self.inbox.try_recv().ok()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::net::SocketAddr;
+  use std::str::FromStr;
+
+  #[test]
+  fn loopback_delivers_to_the_bound_address() {
+    let loopback = Loopback::new();
+    let server_addr = SocketAddr::from_str("127.0.0.1:7090").unwrap();
+    let client_addr = SocketAddr::from_str("127.0.0.1:7190").unwrap();
+    let mut server = loopback.bind(server_addr);
+    let mut client = loopback.bind(client_addr);
+
+    client.send(Datagram::new(server_addr, vec![1, 2, 3]));
+    client.send(Datagram::new(SocketAddr::from_str("127.0.0.1:1").unwrap(), vec![4]));
+
+    assert_eq!(server.recv(), Some(Datagram::new(client_addr, vec![1, 2, 3])));
+    assert_eq!(server.recv(), None);
+    assert_eq!(client.recv(), None);
+  }
+}
@@ -0,0 +1,444 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use time;
+
+use util::Newness;
+
+/**
+ * How hard the network should try to deliver a message
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Delivery {
+  /// Resent until acked, and handed over in the order it was sent
+  ReliableOrdered,
+  /// Resent until acked, and handed over as soon as it arrives
+  ReliableUnordered,
+  /// Sent once, and dropped if anything sent after it on the same channel has already arrived
+  UnreliableSequenced(u8),
+  /// Sent once, and handed over whenever (and however often) it arrives
+  Unreliable,
+}
+
+impl Delivery {
+  fn to_bytes(&self) -> (u8, u8) {
+    match self {
+      &Delivery::ReliableOrdered => (0, 0),
+      &Delivery::ReliableUnordered => (1, 0),
+      &Delivery::UnreliableSequenced(channel) => (2, channel),
+      &Delivery::Unreliable => (3, 0),
+    }
+  }
+
+  fn from_bytes(tag: u8, channel: u8) -> Option<Delivery> {
+    match (tag, channel) {
+      (0, 0) => Some(Delivery::ReliableOrdered),
+      (1, 0) => Some(Delivery::ReliableUnordered),
+      (2, channel) => Some(Delivery::UnreliableSequenced(channel)),
+      (3, 0) => Some(Delivery::Unreliable),
+      _ => None,
+    }
+  }
+
+  fn is_reliable(&self) -> bool {
+    match self {
+      &Delivery::ReliableOrdered |
+      &Delivery::ReliableUnordered => true,
+      _ => false,
+    }
+  }
+}
+
+const MESSAGE_FRAME: u8 = 0;
+const ACK_FRAME: u8 = 1;
+
+/// How long a reliable message goes unacked before it's sent again, in milliseconds
+pub const RESEND_AFTER_MS: u64 = 100;
+
+/// A reliable message sent this many times without an ack means the link is dead
+const MAX_SENDS: u32 = 50;
+
+/// How many unordered messages we remember receiving, to spot resends of them
+const REMEMBERED_UNORDERED: usize = 512;
+
+/// Stands in for the peer's id until we've heard from it
+const UNKNOWN_PEER: u32 = 0;
+
+/// The latest id given to an endpoint in this process
+static LAST_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+struct Unacked {
+  delivery: Delivery,
+  seq: u16,
+  frame: Vec<u8>,
+  last_sent: u64,
+  sends: u32,
+}
+
+/**
+ * One end of a conversation with a single peer, adding delivery guarantees on top of datagrams
+ *
+ * Each payload is framed with its delivery class and a sequence number (one sequence per class,
+ * and per channel for sequenced messages). Reliable messages are acked by the receiver and resent
+ * by the sender until they are, or until the sender gives up and calls the link dead. Each end
+ * has an id newer than any its previous incarnations had, so when a peer restarts (and starts its
+ * sequences over) the other end notices and starts over too, rather than mistaking new messages
+ * for old ones. Stragglers from an earlier incarnation are ignored.
+ */
+pub struct Endpoint {
+  id: u32,
+  peer_id: u32,
+  next_seqs: HashMap<Delivery, u16>,
+  unacked: Vec<Unacked>,
+  dead: bool,
+  next_expected_ordered: u16,
+  held_ordered: HashMap<u16, Vec<u8>>,
+  seen_unordered: VecDeque<u16>,
+  latest_sequenced: HashMap<u8, u16>,
+  acks: Vec<Vec<u8>>,
+}
+
+impl Endpoint {
+  pub fn new() -> Endpoint {
+    Endpoint {
+      id: next_id(),
+      peer_id: UNKNOWN_PEER,
+      next_seqs: HashMap::new(),
+      unacked: Vec::new(),
+      dead: false,
+      next_expected_ordered: 0,
+      held_ordered: HashMap::new(),
+      seen_unordered: VecDeque::new(),
+      latest_sequenced: HashMap::new(),
+      acks: Vec::new(),
+    }
+  }
+
+  /**
+   * Frames the payload for sending, holding on to it for resending if it's reliable
+   */
+  pub fn send(&mut self, delivery: Delivery, payload: &[u8], now: u64) -> Vec<u8> {
+    let seq = {
+      let next_seq = self.next_seqs.entry(delivery).or_insert(0);
+      let seq = *next_seq;
+      *next_seq = next_seq.wrapping_add(1);
+      seq
+    };
+
+    let mut frame = self.header(MESSAGE_FRAME, delivery, seq);
+    frame.extend_from_slice(payload);
+
+    if delivery.is_reliable() {
+      self.unacked.push(Unacked {
+        delivery: delivery,
+        seq: seq,
+        frame: frame.clone(),
+        last_sent: now,
+        sends: 1,
+      });
+    }
+
+    frame
+  }
+
+  /**
+   * Unframes a received datagram, yielding every payload that's now ready to be handed over
+   *
+   * Malformed frames, duplicates and stale sequenced messages yield nothing.
+   */
+  pub fn receive(&mut self, frame: &[u8]) -> Vec<Vec<u8>> {
+    let mut cursor = Cursor::new(frame);
+    let header = (cursor.read_u8(),
+                  cursor.read_u32::<BigEndian>(),
+                  cursor.read_u32::<BigEndian>(),
+                  (cursor.read_u8(), cursor.read_u8()),
+                  cursor.read_u16::<BigEndian>());
+    let (kind, sender, receiver, delivery, seq) = match header {
+      (Ok(kind), Ok(sender), Ok(receiver), (Ok(tag), Ok(channel)), Ok(seq)) => {
+        match Delivery::from_bytes(tag, channel) {
+          Some(delivery) => (kind, sender, receiver, delivery, seq),
+          None => return Vec::new(),
+        }
+      },
+      _ => return Vec::new(),
+    };
+
+    // Meant for an earlier incarnation of this endpoint
+    if receiver != UNKNOWN_PEER && receiver != self.id {
+      return Vec::new();
+    }
+
+    if self.peer_id == UNKNOWN_PEER {
+      self.peer_id = sender;
+    } else if sender.is_newer_than(&self.peer_id) {
+      self.restart_with(sender);
+    } else if sender != self.peer_id {
+      // From an earlier incarnation of the peer, delayed on the way
+      return Vec::new();
+    }
+
+    match kind {
+      ACK_FRAME => {
+        self.unacked.retain(|unacked| unacked.delivery != delivery || unacked.seq != seq);
+        Vec::new()
+      },
+      MESSAGE_FRAME => {
+        let payload = frame[cursor.position() as usize..].to_vec();
+        self.accept(delivery, seq, payload)
+      },
+      _ => Vec::new(),
+    }
+  }
+
+  /**
+   * Yields the frames that should go out now: acks for what we've received, and resends of what
+   * hasn't been acked
+   *
+   * Once a message has been sent as many times as it's allowed without an ack, the link is dead
+   * and nothing more is resent. Nothing is dropped, though, since a missing ordered message would
+   * hold up everything after it.
+   */
+  pub fn outgoing(&mut self, now: u64) -> Vec<Vec<u8>> {
+    let mut frames = self.acks.drain(..).collect::<Vec<_>>();
+
+    if self.unacked.iter().any(|unacked| unacked.sends >= MAX_SENDS) {
+      self.dead = true;
+    }
+    if self.dead {
+      return frames;
+    }
+
+    for unacked in self.unacked.iter_mut() {
+      if now >= unacked.last_sent + RESEND_AFTER_MS {
+        unacked.last_sent = now;
+        unacked.sends = unacked.sends + 1;
+        frames.push(unacked.frame.clone());
+      }
+    }
+
+    frames
+  }
+
+  /**
+   * How many reliable messages we're still waiting on acks for
+   */
+  pub fn unacked(&self) -> usize {
+    self.unacked.len()
+  }
+
+  /**
+   * Whether the peer has stopped acking what we send, so it will never get some of it
+   *
+   * A dead endpoint stays dead until the peer starts over.
+   */
+  pub fn is_dead(&self) -> bool {
+    self.dead
+  }
+
+  fn accept(&mut self, delivery: Delivery, seq: u16, payload: Vec<u8>) -> Vec<Vec<u8>> {
+    match delivery {
+      Delivery::ReliableOrdered => {
+        let ack = self.header(ACK_FRAME, delivery, seq);
+        self.acks.push(ack);
+
+        // Already handed over, so it's a resend
+        if !seq.is_newer_than(&self.next_expected_ordered.wrapping_sub(1)) {
+          return Vec::new();
+        }
+
+        self.held_ordered.insert(seq, payload);
+        let mut ready = Vec::new();
+        while let Some(payload) = self.held_ordered.remove(&self.next_expected_ordered) {
+          ready.push(payload);
+          self.next_expected_ordered = self.next_expected_ordered.wrapping_add(1);
+        }
+        ready
+      },
+      Delivery::ReliableUnordered => {
+        let ack = self.header(ACK_FRAME, delivery, seq);
+        self.acks.push(ack);
+
+        if self.seen_unordered.iter().any(|seen| *seen == seq) {
+          return Vec::new();
+        }
+        self.seen_unordered.push_back(seq);
+        if self.seen_unordered.len() > REMEMBERED_UNORDERED {
+          self.seen_unordered.pop_front();
+        }
+        vec![payload]
+      },
+      Delivery::UnreliableSequenced(channel) => {
+        let is_latest = self.latest_sequenced
+          .get(&channel)
+          .map(|latest| seq.is_newer_than(latest))
+          .unwrap_or(true);
+        if is_latest {
+          self.latest_sequenced.insert(channel, seq);
+          vec![payload]
+        } else {
+          Vec::new()
+        }
+      },
+      Delivery::Unreliable => vec![payload],
+    }
+  }
+
+  /**
+   * Forgets everything about the conversation so far, because the peer has started over
+   */
+  fn restart_with(&mut self, peer_id: u32) {
+    let id = self.id;
+    *self = Endpoint::new();
+    self.id = id;
+    self.peer_id = peer_id;
+  }
+
+  fn header(&self, kind: u8, delivery: Delivery, seq: u16) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.write_u8(kind).unwrap();
+    header.write_u32::<BigEndian>(self.id).unwrap();
+    header.write_u32::<BigEndian>(self.peer_id).unwrap();
+    let (tag, channel) = delivery.to_bytes();
+    header.write_u8(tag).unwrap();
+    header.write_u8(channel).unwrap();
+    header.write_u16::<BigEndian>(seq).unwrap();
+    header
+  }
+}
+
+/**
+ * Picks an id for a new endpoint, newer than any picked before it
+ *
+ * Ids follow the clock (in wrapping milliseconds), so an endpoint in a restarted process is newer
+ * than those of the process before it too.
+ */
+fn next_id() -> u32 {
+  let clock = time::get_time();
+  let clock_ms = (clock.sec as u64 * 1000 + clock.nsec as u64 / 1_000_000) as u32;
+
+  loop {
+    let last = LAST_ID.load(Ordering::SeqCst);
+    let mut id = if last == UNKNOWN_PEER as usize || clock_ms.is_newer_than(&(last as u32)) {
+      clock_ms
+    } else {
+      (last as u32).wrapping_add(1)
+    };
+    if id == UNKNOWN_PEER {
+      id = id.wrapping_add(1);
+    }
+    if LAST_ID.compare_and_swap(last, id as usize, Ordering::SeqCst) == last {
+      return id;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /**
+   * Passes frames between two endpoints, yielding what each handed over
+   */
+  fn exchange(from: &mut Endpoint, to: &mut Endpoint, frames: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    let received = frames.iter().flat_map(|frame| to.receive(frame)).collect();
+    for ack in to.outgoing(0) {
+      from.receive(&ack);
+    }
+    received
+  }
+
+  #[test]
+  fn ordered_messages_wait_for_earlier_ones() {
+    let (mut client, mut server) = (Endpoint::new(), Endpoint::new());
+    let first = client.send(Delivery::ReliableOrdered, &[1], 0);
+    let second = client.send(Delivery::ReliableOrdered, &[2], 0);
+
+    assert_eq!(exchange(&mut client, &mut server, vec![second.clone()]),
+               Vec::<Vec<u8>>::new());
+    assert_eq!(exchange(&mut client, &mut server, vec![first.clone(), second]),
+               vec![vec![1], vec![2]]);
+    assert_eq!(exchange(&mut client, &mut server, vec![first]),
+               Vec::<Vec<u8>>::new());
+    assert_eq!(client.unacked(), 0);
+  }
+
+  #[test]
+  fn unacked_messages_are_resent() {
+    let (mut client, mut server) = (Endpoint::new(), Endpoint::new());
+    client.send(Delivery::ReliableUnordered, &[1], 0);
+
+    assert_eq!(client.outgoing(RESEND_AFTER_MS - 1), Vec::<Vec<u8>>::new());
+    let resent = client.outgoing(RESEND_AFTER_MS);
+    assert_eq!(exchange(&mut client, &mut server, resent), vec![vec![1]]);
+    assert_eq!(client.unacked(), 0);
+  }
+
+  #[test]
+  fn stale_sequenced_messages_are_dropped() {
+    let (mut client, mut server) = (Endpoint::new(), Endpoint::new());
+    let old = client.send(Delivery::UnreliableSequenced(0), &[1], 0);
+    let new = client.send(Delivery::UnreliableSequenced(0), &[2], 0);
+
+    assert_eq!(exchange(&mut client, &mut server, vec![new, old]), vec![vec![2]]);
+    assert_eq!(client.unacked(), 0);
+  }
+
+  #[test]
+  fn channels_and_unsequenced_messages_are_not_superseded() {
+    let (mut client, mut server) = (Endpoint::new(), Endpoint::new());
+    let keep_alive = client.send(Delivery::UnreliableSequenced(0), &[1], 0);
+    let input = client.send(Delivery::UnreliableSequenced(1), &[2], 0);
+    let first_piece = client.send(Delivery::Unreliable, &[3], 0);
+    let second_piece = client.send(Delivery::Unreliable, &[4], 0);
+
+    assert_eq!(exchange(&mut client,
+                        &mut server,
+                        vec![second_piece, input, first_piece, keep_alive]),
+               vec![vec![4], vec![2], vec![3], vec![1]]);
+  }
+
+  #[test]
+  fn links_that_stop_acking_die_without_dropping_anything() {
+    let mut client = Endpoint::new();
+    client.send(Delivery::ReliableOrdered, &[1], 0);
+
+    let mut now = 0;
+    while !client.is_dead() {
+      now = now + RESEND_AFTER_MS;
+      client.outgoing(now);
+      assert!(now < RESEND_AFTER_MS * 100, "Link never died");
+    }
+    assert_eq!(client.unacked(), 1);
+    assert_eq!(client.outgoing(now + RESEND_AFTER_MS), Vec::<Vec<u8>>::new());
+  }
+
+  #[test]
+  fn restarted_peers_start_over() {
+    let mut server = Endpoint::new();
+    let mut client = Endpoint::new();
+    let first = client.send(Delivery::ReliableOrdered, &[1], 0);
+    exchange(&mut client, &mut server, vec![first]);
+
+    let mut restarted = Endpoint::new();
+    let first_again = restarted.send(Delivery::ReliableOrdered, &[2], 0);
+    assert_eq!(exchange(&mut restarted, &mut server, vec![first_again]), vec![vec![2]]);
+  }
+
+  #[test]
+  fn stragglers_from_earlier_peers_are_ignored() {
+    let mut server = Endpoint::new();
+    let mut client = Endpoint::new();
+    let first = client.send(Delivery::ReliableOrdered, &[1], 0);
+    let straggler = client.send(Delivery::ReliableOrdered, &[2], 0);
+    exchange(&mut client, &mut server, vec![first]);
+
+    let mut restarted = Endpoint::new();
+    let first_again = restarted.send(Delivery::ReliableOrdered, &[3], 0);
+    let second_again = restarted.send(Delivery::ReliableOrdered, &[4], 0);
+    assert_eq!(exchange(&mut restarted, &mut server, vec![first_again, straggler]),
+               vec![vec![3]]);
+    assert_eq!(exchange(&mut restarted, &mut server, vec![second_again]), vec![vec![4]]);
+  }
+}
@@ -0,0 +1,110 @@
+use time;
+
+/// How much of each new round trip is folded into the smoothed round trip time (as in TCP)
+const RTT_GAIN: f64 = 0.125;
+
+/// How much of each new deviation is folded into the smoothed jitter (as in TCP)
+const JITTER_GAIN: f64 = 0.25;
+
+/// How much of each new clock offset is folded into the smoothed offset
+const OFFSET_GAIN: f64 = 0.125;
+
+/**
+ * Reads the clock both ends stamp their KeepAlives with, in milliseconds
+ *
+ * The clock only ever moves forward, but each machine's starts somewhere different, so readings
+ * from a peer have to be adjusted by the clock offset before they mean anything here.
+ */
+pub fn now_ms() -> u64 {
+  time::precise_time_ns() / 1_000_000
+}
+
+/**
+ * Smoothed estimates of how a link to a peer is behaving, built up from timed round trips
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkStats {
+  /// Round trip time, in milliseconds
+  pub rtt_ms: f64,
+  /// Average deviation of the round trip time from the smoothed one, in milliseconds
+  pub jitter_ms: f64,
+  /// What to add to a reading of our clock to get the peer's, in milliseconds
+  pub clock_offset_ms: f64,
+  /// How many round trips have been folded in
+  pub samples: u32,
+}
+
+impl LinkStats {
+  pub fn new() -> LinkStats {
+    LinkStats {
+      rtt_ms: 0.0,
+      jitter_ms: 0.0,
+      clock_offset_ms: 0.0,
+      samples: 0,
+    }
+  }
+
+  /**
+   * Folds in a round trip that left at sent_ms and returned at received_ms (by our clock)
+   *
+   * The peer may have held on to it for held_ms before answering, which doesn't count toward the
+   * round trip. If the peer stamped the answer with its own clock, the offset is updated too,
+   * assuming the way there took as long as the way back.
+   */
+  pub fn record(&mut self, sent_ms: u64, received_ms: u64, held_ms: u64, peer_ms: Option<u64>) {
+    let rtt = received_ms.saturating_sub(sent_ms).saturating_sub(held_ms) as f64;
+    // The peer stamped its answer as it sent it, after holding on to what we sent
+    let offset =
+      peer_ms.map(|peer_ms| peer_ms as f64 - (sent_ms as f64 + held_ms as f64 + rtt / 2.0));
+
+    if self.samples == 0 {
+      self.rtt_ms = rtt;
+      self.jitter_ms = rtt / 2.0;
+      offset.map(|offset| self.clock_offset_ms = offset);
+    } else {
+      self.jitter_ms = self.jitter_ms + JITTER_GAIN * ((rtt - self.rtt_ms).abs() - self.jitter_ms);
+      self.rtt_ms = self.rtt_ms + RTT_GAIN * (rtt - self.rtt_ms);
+      offset.map(|offset| {
+        self.clock_offset_ms = self.clock_offset_ms + OFFSET_GAIN * (offset - self.clock_offset_ms)
+      });
+    }
+    self.samples = self.samples + 1;
+  }
+
+  /**
+   * Converts a reading of the peer's clock into a reading of ours
+   */
+  pub fn to_local_ms(&self, peer_ms: u64) -> f64 {
+    peer_ms as f64 - self.clock_offset_ms
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn steady_round_trips_settle() {
+    let mut stats = LinkStats::new();
+    for idx in 0..100 {
+      let sent = idx * 20;
+      // The peer's clock runs 5000ms ahead, and each way takes 30ms
+      stats.record(sent, sent + 60, 0, Some(sent + 30 + 5000));
+    }
+
+    assert_eq!(stats.rtt_ms, 60.0);
+    assert!(stats.jitter_ms < 1.0);
+    assert_eq!(stats.clock_offset_ms, 5000.0);
+    assert_eq!(stats.to_local_ms(6000), 1000.0);
+  }
+
+  #[test]
+  fn time_held_by_the_peer_is_left_out() {
+    let mut stats = LinkStats::new();
+    // The peer's clock runs 5000ms ahead, and it answered 20ms after our reading arrived
+    stats.record(100, 180, 20, Some(100 + 30 + 20 + 5000));
+
+    assert_eq!(stats.rtt_ms, 60.0);
+    assert_eq!(stats.clock_offset_ms, 5000.0);
+  }
+}
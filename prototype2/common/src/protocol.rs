@@ -1,9 +1,5 @@
-use std::str;
-
-use gaffer_udp::GafferPacket;
-use serde_json;
-
-use network;
+use codec::{CodecError, CodecKind};
+use network::{self, Datagram};
 
 include!(concat!(env!("OUT_DIR"), "/protocol.rs"));
 
@@ -23,43 +19,33 @@ impl ServerPayload {
     }
   }
 
-  // TODO: Result for a more informative failure?
-  pub fn from_gaffer_packet(pkt: GafferPacket) -> Option<ServerPayload> {
-    let address = pkt.addr;
-    let payload = pkt.payload;
-    str::from_utf8(payload.as_ref())
-      .ok()
-      .map(|s| s.trim_right_matches('\0'))
-      .and_then(|s| serde_json::from_str(s).ok())
-      .map(|event| {
-        ServerPayload {
-          address: address,
-          event: event,
-        }
-      })
+  pub fn from_datagram(datagram: Datagram, codec: CodecKind) -> Result<ServerPayload, CodecError> {
+    let address = datagram.address;
+    codec.decode::<ServerNetworkEvent>(datagram.payload.as_ref()).map(|event| {
+      ServerPayload {
+        address: address,
+        event: event,
+      }
+    })
   }
 }
 
 #[derive(Clone, Debug)]
 pub struct ClientPayload {
   pub address: network::Address,
+  pub session: Option<SessionToken>,
   pub event: ClientNetworkEvent,
 }
 
 impl ClientPayload {
-  // TODO: Result for a more informative failure?
-  pub fn from_gaffer_packet(pkt: GafferPacket) -> Option<ClientPayload> {
-    let address = pkt.addr;
-    let payload = pkt.payload;
-    str::from_utf8(payload.as_ref())
-      .ok()
-      .map(|s| s.trim_right_matches('\0'))
-      .and_then(|s| serde_json::from_str(s).ok())
-      .map(|event| {
-        ClientPayload {
-          address: address,
-          event: event,
-        }
-      })
+  pub fn from_datagram(datagram: Datagram, codec: CodecKind) -> Result<ClientPayload, CodecError> {
+    let address = datagram.address;
+    codec.decode::<ClientMessage>(datagram.payload.as_ref()).map(|message| {
+      ClientPayload {
+        address: address,
+        session: message.session,
+        event: message.event,
+      }
+    })
   }
 }
@@ -0,0 +1,226 @@
+/// A vector in game space (right handed, z up) unless said otherwise
+pub type Vector = (f32, f32, f32);
+
+/// A unit quaternion, as (w, x, y, z)
+pub type Orientation = (f32, f32, f32, f32);
+
+/// Facing along the axes, unrotated
+pub const IDENTITY: Orientation = (1.0, 0.0, 0.0, 0.0);
+
+/// Below this, angles are small enough to skip the trigonometry
+const SMALL_ANGLE: f32 = 0.0001;
+
+/**
+ * Moves a vector from game space into physics space (right handed, y up)
+ *
+ * This is a quarter turn about x rather than a swap of y and z, so handedness is kept and the same
+ * mapping works for positions, velocities, angular velocities and rotation vectors.
+ */
+pub fn vector_to_physics(v: Vector) -> Vector {
+  (v.0, v.2, -v.1)
+}
+
+/**
+ * Moves a vector from physics space back into game space
+ */
+pub fn vector_from_physics(v: Vector) -> Vector {
+  (v.0, -v.2, v.1)
+}
+
+/**
+ * Moves an orientation from game space into physics space
+ */
+pub fn orientation_to_physics(q: Orientation) -> Orientation {
+  let (x, y, z) = vector_to_physics((q.1, q.2, q.3));
+  (q.0, x, y, z)
+}
+
+/**
+ * Moves an orientation from physics space back into game space
+ */
+pub fn orientation_from_physics(q: Orientation) -> Orientation {
+  let (x, y, z) = vector_from_physics((q.1, q.2, q.3));
+  (q.0, x, y, z)
+}
+
+/**
+ * The orientation reached by turning about the vector's direction by its length, in radians
+ */
+pub fn from_rotation_vector(v: Vector) -> Orientation {
+  let angle = (v.0 * v.0 + v.1 * v.1 + v.2 * v.2).sqrt();
+  if angle < SMALL_ANGLE {
+    return normalize((1.0, v.0 / 2.0, v.1 / 2.0, v.2 / 2.0));
+  }
+
+  let scale = (angle / 2.0).sin() / angle;
+  ((angle / 2.0).cos(), v.0 * scale, v.1 * scale, v.2 * scale)
+}
+
+/**
+ * The shortest turn that reaches the orientation, as its axis scaled by its angle in radians
+ */
+pub fn to_rotation_vector(q: Orientation) -> Vector {
+  let q = if q.0 < 0.0 { negate(q) } else { q };
+  let sin_half = (q.1 * q.1 + q.2 * q.2 + q.3 * q.3).sqrt();
+  if sin_half < SMALL_ANGLE {
+    return (q.1 * 2.0, q.2 * 2.0, q.3 * 2.0);
+  }
+
+  let scale = 2.0 * sin_half.atan2(q.0) / sin_half;
+  (q.1 * scale, q.2 * scale, q.3 * scale)
+}
+
+/**
+ * Turning by b, then by a
+ */
+pub fn multiply(a: Orientation, b: Orientation) -> Orientation {
+  (a.0 * b.0 - a.1 * b.1 - a.2 * b.2 - a.3 * b.3,
+   a.0 * b.1 + a.1 * b.0 + a.2 * b.3 - a.3 * b.2,
+   a.0 * b.2 - a.1 * b.3 + a.2 * b.0 + a.3 * b.1,
+   a.0 * b.3 + a.1 * b.2 - a.2 * b.1 + a.3 * b.0)
+}
+
+/**
+ * Turns a vector by the orientation
+ */
+pub fn rotate(q: Orientation, v: Vector) -> Vector {
+  let turned = multiply(multiply(q, (0.0, v.0, v.1, v.2)), (q.0, -q.1, -q.2, -q.3));
+  (turned.1, turned.2, turned.3)
+}
+
+pub fn normalize(q: Orientation) -> Orientation {
+  let len = (q.0 * q.0 + q.1 * q.1 + q.2 * q.2 + q.3 * q.3).sqrt();
+  if len < SMALL_ANGLE {
+    IDENTITY
+  } else {
+    (q.0 / len, q.1 / len, q.2 / len, q.3 / len)
+  }
+}
+
+/**
+ * Turns an orientation by an angular velocity (radians per second, about game axes) for dt_s
+ */
+pub fn integrate(q: Orientation, ang_vel: Vector, dt_s: f32) -> Orientation {
+  let turn = from_rotation_vector((ang_vel.0 * dt_s, ang_vel.1 * dt_s, ang_vel.2 * dt_s));
+  normalize(multiply(turn, q))
+}
+
+/**
+ * Blends two orientations the short way around
+ */
+pub fn slerp(from: Orientation, to: Orientation, fraction: f32) -> Orientation {
+  let mut dot = from.0 * to.0 + from.1 * to.1 + from.2 * to.2 + from.3 * to.3;
+  let to = if dot < 0.0 {
+    dot = -dot;
+    negate(to)
+  } else {
+    to
+  };
+
+  // Nearly the same orientation, where a straight blend is just as good and better behaved
+  let (from_weight, to_weight) = if dot > 1.0 - SMALL_ANGLE {
+    (1.0 - fraction, fraction)
+  } else {
+    let angle = dot.acos();
+    (((1.0 - fraction) * angle).sin() / angle.sin(), (fraction * angle).sin() / angle.sin())
+  };
+
+  normalize((from.0 * from_weight + to.0 * to_weight,
+             from.1 * from_weight + to.1 * to_weight,
+             from.2 * from_weight + to.2 * to_weight,
+             from.3 * from_weight + to.3 * to_weight))
+}
+
+fn negate(q: Orientation) -> Orientation {
+  (-q.0, -q.1, -q.2, -q.3)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rand::{Rng, SeedableRng, XorShiftRng};
+  use serde_json;
+  use std::f32::consts::PI;
+
+  use aspects::PhysicalAspect;
+
+  fn close(a: Vector, b: Vector) -> bool {
+    (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001 && (a.2 - b.2).abs() < 0.001
+  }
+
+  fn random_vector(rng: &mut XorShiftRng) -> Vector {
+    (rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0), rng.gen_range(-10.0, 10.0))
+  }
+
+  fn random_orientation(rng: &mut XorShiftRng) -> Orientation {
+    normalize((rng.gen_range(-1.0, 1.0),
+               rng.gen_range(-1.0, 1.0),
+               rng.gen_range(-1.0, 1.0),
+               rng.gen_range(-1.0, 1.0)))
+  }
+
+  fn cross(a: Vector, b: Vector) -> Vector {
+    (a.1 * b.2 - a.2 * b.1, a.2 * b.0 - a.0 * b.2, a.0 * b.1 - a.1 * b.0)
+  }
+
+  #[test]
+  fn up_is_up_in_both_spaces_and_handedness_is_kept() {
+    assert_eq!(vector_to_physics((0.0, 0.0, 1.0)), (0.0, 1.0, 0.0));
+
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    for _ in 0..100 {
+      let (a, b) = (random_vector(&mut rng), random_vector(&mut rng));
+      assert_eq!(vector_from_physics(vector_to_physics(a)), a);
+      assert!(close(vector_to_physics(cross(a, b)),
+                    cross(vector_to_physics(a), vector_to_physics(b))));
+    }
+  }
+
+  #[test]
+  fn turning_in_physics_space_is_turning_in_game_space() {
+    let mut rng = XorShiftRng::from_seed([5, 6, 7, 8]);
+    for _ in 0..100 {
+      let (q, v) = (random_orientation(&mut rng), random_vector(&mut rng));
+      let in_physics = rotate(orientation_to_physics(q), vector_to_physics(v));
+      assert!(close(vector_from_physics(in_physics), rotate(q, v)));
+    }
+  }
+
+  #[test]
+  fn orientation_survives_simulation_and_serialization() {
+    let mut rng = XorShiftRng::from_seed([9, 10, 11, 12]);
+    for _ in 0..100 {
+      let (q, v) = (random_orientation(&mut rng), random_vector(&mut rng));
+
+      // The simulation holds orientations as rotation vectors in its own space
+      let simulated = vector_to_physics(to_rotation_vector(q));
+      let mut physical = PhysicalAspect::new((0.0, 0.0, 0.0), (0.0, 0.0, 0.0));
+      physical.ang = from_rotation_vector(vector_from_physics(simulated));
+
+      let json = serde_json::to_string(&physical).unwrap();
+      let received = serde_json::from_str::<PhysicalAspect>(&json).unwrap();
+
+      // The renderer turns the model by the received orientation
+      assert!(close(rotate(received.ang, v), rotate(q, v)));
+    }
+  }
+
+  #[test]
+  fn spinning_turns_by_the_angular_velocity() {
+    let mut q = IDENTITY;
+    for _ in 0..10 {
+      q = integrate(q, (0.0, 0.0, PI / 2.0), 0.1);
+    }
+
+    assert!(close(rotate(q, (1.0, 0.0, 0.0)), (0.0, 1.0, 0.0)));
+  }
+
+  #[test]
+  fn orientations_blend_the_short_way_around() {
+    let from = from_rotation_vector((0.0, 0.0, PI - 0.1));
+    let to = from_rotation_vector((0.0, 0.0, -PI + 0.1));
+
+    let halfway = slerp(from, to, 0.5);
+    assert!(close(rotate(halfway, (1.0, 0.0, 0.0)), (-1.0, 0.0, 0.0)));
+  }
+}
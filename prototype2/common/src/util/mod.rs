@@ -11,6 +11,14 @@ impl Newness for u16 {
   }
 }
 
+impl Newness for u32 {
+  // As for u16, within half of u32::MAX
+  fn is_newer_than(&self, other: &u32) -> bool {
+    let pos_diff = self.wrapping_sub(*other);
+    pos_diff != 0 && pos_diff < 1 << 31
+  }
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -22,4 +30,11 @@ mod test {
     assert!(30000.is_newer_than(&0));
     assert!(!33000.is_newer_than(&0));
   }
+
+  #[test]
+  fn u32() {
+    assert!(!0u32.is_newer_than(&0));
+    assert!(1u32.is_newer_than(&u32::max_value()));
+    assert!(!u32::max_value().is_newer_than(&1));
+  }
 }
@@ -25,6 +25,7 @@ pub trait PubSubStore {
                                                        token: &SubscriberToken<T>)
                                                        -> Subscriber<'a, T>;
   fn register_subscriber<T: Clone + Any + Send + Sync>(&mut self) -> SubscriberToken<T>;
+  fn register_publisher<T: Clone + Any + Send + Sync>(&mut self);
 }
 
 /// An indicator pointing to a specific subscriber reader that the holder has
@@ -82,6 +83,11 @@ impl PubSubStore for specs::World {
       reader_idx: idx,
     }
   }
+
+  /// Make sure this resource can be published, even before anyone subscribes to it.
+  fn register_publisher<T: Clone + Any + Send + Sync>(&mut self) {
+    acquire_or_install_raw_containers::<T>(self);
+  }
 }
 
 // Wrap builtins so they cannot be accessed outside this module
@@ -176,4 +182,15 @@ mod test {
     assert_eq!(world.fetch_subscriber(&stoken_2).pull(), &vec![1, 2]);
   }
 
+  #[test]
+  fn publishing_before_anyone_subscribes_is_dropped() {
+    let mut world = specs::World::new();
+    world.register_publisher::<u32>();
+    world.fetch_publisher::<u32>().push(1);
+
+    let stoken = world.register_subscriber::<u32>();
+    world.fetch_publisher::<u32>().push(2);
+    assert_eq!(world.fetch_subscriber(&stoken).pull(), &vec![2]);
+  }
+
 }
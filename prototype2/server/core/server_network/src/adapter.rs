@@ -1,4 +1,6 @@
 use specs;
+use std::net::SocketAddr;
+
 use itertools::Itertools;
 
 use Network;
@@ -9,11 +11,26 @@ use aspects::PlayerAspect;
 use protocol::OutboundEvent;
 use pubsub::{PubSubStore, SubscriberToken};
 
+/**
+ * How many reliable events connected players have yet to ack, as of the end of the last tick
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnackedEvents(pub usize);
+
+/**
+ * A client that stopped acking what it was sent, by its address
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkLostEvent(pub SocketAddr);
+
 /**
  * Manages the network adapter, broadcasting pending outgoing events and accepting incoming events
  *
+ * Outgoing events are held until the first tick of the next frame, so a frame that runs several
+ * ticks to catch up sends once.
+ *
  * Input: OutboundEvent, Players
- * Output: InboundEvent
+ * Output: InboundEvent, UnackedEvents, LinkLostEvent
  */
 pub struct System {
   network: Network,
@@ -21,32 +38,48 @@ pub struct System {
 }
 
 impl System {
-  pub fn new(port: u16, world: &mut specs::World) -> System {
+  pub fn new(network: Network, world: &mut specs::World) -> System {
+    world.add_resource::<UnackedEvents>(UnackedEvents(0));
+    world.register_publisher::<LinkLostEvent>();
+
     System {
-      network: Network::new(port),
+      network: network,
       outbound_event_sub_token: world.register_subscriber::<OutboundEvent>(),
     }
   }
 }
 
 impl specs::System<Delta> for System {
-  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+  fn run(&mut self, arg: specs::RunArg, delta: Delta) {
     use specs::Join;
 
-    let (mut outbound_events, mut inbound_events, player) = arg.fetch(|w| {
-      (w.fetch_subscriber(&self.outbound_event_sub_token).collected(),
-       w.fetch_publisher::<ClientPayload>(),
-       w.read::<PlayerAspect>())
-    });
+    let (outbound_events, mut inbound_events, mut lost_links, player, mut unacked_events) =
+      arg.fetch(|w| {
+        (w.fetch_subscriber(&self.outbound_event_sub_token),
+         w.fetch_publisher::<ClientPayload>(),
+         w.fetch_publisher::<LinkLostEvent>(),
+         w.read::<PlayerAspect>(),
+         w.write_resource::<UnackedEvents>())
+      });
 
     let all_addresses = player.iter().map(|player| player.address.clone()).collect();
 
     // Emit all pending events
-    outbound_events.drain(..)
-      .flat_map(|outbound| outbound.to_server_payloads(&all_addresses))
-      .foreach(|event| self.network.send(event));
+    if delta.first_of_frame {
+      outbound_events.collected()
+        .drain(..)
+        .flat_map(|outbound| outbound.to_server_payloads(&all_addresses))
+        .foreach(|event| self.network.send(event));
+    }
 
     // Process all incoming events
     self.network.recv_pending().into_iter().foreach(|e| inbound_events.push(e));
+    self.network.take_lost().into_iter().foreach(|address| lost_links.push(LinkLostEvent(address)));
+
+    let connected_addresses = player.iter()
+      .filter(|player| player.connected)
+      .map(|player| player.address.clone())
+      .collect();
+    *unacked_events = UnackedEvents(self.network.unacked_by(&connected_addresses));
   }
 }
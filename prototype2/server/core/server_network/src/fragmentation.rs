@@ -1,11 +1,11 @@
 use std::io::Write;
 
-use serde_json;
 use flate2::write::GzEncoder;
 use flate2::Compression;
 
+use common::codec::CodecKind;
 use common::protocol::{ServerNetworkEvent, SnapshotEvent, StateFragment};
-use common::aspects::CommonWorld;
+use common::snapshot::WorldDelta;
 
 /**
  * Indicates that the implementor can be broken into events to be transmitted over the wire
@@ -14,18 +14,26 @@ use common::aspects::CommonWorld;
  * Convert this to emit fragment buffers, which can then be turned into server events
  */
 pub trait Fragmentable {
-  fn fragment_to_events(&self, seq_num: u16) -> Vec<ServerNetworkEvent>;
+  fn fragment_to_events(&self,
+                        seq_num: u16,
+                        fragment_size: usize,
+                        codec: CodecKind)
+                        -> Vec<ServerNetworkEvent>;
 }
 
-impl Fragmentable for CommonWorld {
-  fn fragment_to_events(&self, seq_num: u16) -> Vec<ServerNetworkEvent> {
-    let client_snapshot = serde_json::to_string(&self).unwrap();
+impl Fragmentable for WorldDelta {
+  fn fragment_to_events(&self,
+                        seq_num: u16,
+                        fragment_size: usize,
+                        codec: CodecKind)
+                        -> Vec<ServerNetworkEvent> {
+    let client_snapshot = codec.encode(self);
     let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
-    encoder.write(client_snapshot.as_bytes()).unwrap();
+    encoder.write(&client_snapshot).unwrap();
 
     // Assumed to be safe because I control the format
     let snapshot_bytes = encoder.finish().unwrap();
-    let snapshot_byte_sets = snapshot_bytes.chunks(128 /* bytes */).enumerate();
+    let snapshot_byte_sets = snapshot_bytes.chunks(fragment_size).enumerate();
     let set_count = snapshot_byte_sets.len();
 
     snapshot_byte_sets.map(|(idx, bytes)| {
@@ -2,7 +2,6 @@ extern crate specs;
 extern crate itertools;
 extern crate flate2;
 extern crate serde_json;
-extern crate gaffer_udp;
 extern crate time;
 
 extern crate common;
@@ -15,39 +14,149 @@ mod adapter;
 mod fragmentation;
 
 pub use protocol::OutboundEvent;
-pub use adapter::System as AdapterSystem;
+pub use adapter::{LinkLostEvent, System as AdapterSystem, UnackedEvents};
 
 pub use self::fragmentation::Fragmentable;
 
-use gaffer_udp::GafferPacket;
-use gaffer_udp::non_blocking::GafferSocket;
-use itertools::Unfold;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 
-use common::protocol::{ClientPayload, ServerPayload};
+use common::codec::CodecKind;
+use common::network::{Datagram, Endpoint, Transport, UdpTransport, now_ms};
+use common::protocol::{ClientNetworkEvent, ClientPayload, ServerNetworkEvent, ServerPayload};
 
 /**
  * Manages the connection to the game clients
  *
- * Uses a GafferSocket (a wrapper around UDP for some reliability)
+ * Uses the provided transport to reach clients (UDP, outside of tests), an endpoint per client to
+ * ack and resend what needs to be reliable, and the provided codec to convert payloads to and
+ * from bytes
+ *
+ * A client only gets an endpoint by sending a Connect, and nothing is sent to addresses without
+ * one. Its endpoint is closed once it's told it's disconnected or rejected, and dropped as soon
+ * as that's been acked. Endpoints whose links die are dropped too, and reported as lost.
  */
 pub struct Network {
-  socket: GafferSocket,
+  transport: Box<Transport>,
+  codec: CodecKind,
+  endpoints: HashMap<SocketAddr, Endpoint>,
+  closing: HashSet<SocketAddr>,
+  lost: Vec<SocketAddr>,
 }
 
 impl Network {
-  pub fn new(port: u16) -> Network {
-    let sock = GafferSocket::bind(("0.0.0.0", port)).unwrap();
-    Network { socket: sock }
+  pub fn new(address: SocketAddr, codec: CodecKind) -> Network {
+    let transport = UdpTransport::bind(address).unwrap();
+    Network::with_transport(Box::new(transport), codec)
+  }
+
+  pub fn with_transport(transport: Box<Transport>, codec: CodecKind) -> Network {
+    Network {
+      transport: transport,
+      codec: codec,
+      endpoints: HashMap::new(),
+      closing: HashSet::new(),
+      lost: Vec::new(),
+    }
   }
 
+  /**
+   * Yields every payload that's ready, then sends any acks and resends that are due
+   */
   pub fn recv_pending(&mut self) -> Vec<ClientPayload> {
-    Unfold::new((), |_| self.socket.recv().ok().and_then(|v| v))
-      .filter_map(|gaffer_packet| ClientPayload::from_gaffer_packet(gaffer_packet))
-      .collect()
+    let mut payloads = Vec::new();
+    let codec = self.codec;
+    while let Some(datagram) = self.transport.recv() {
+      let address = datagram.address;
+      let is_known = self.endpoints.contains_key(&address);
+      let mut received = {
+        let endpoint = self.endpoints.entry(address).or_insert_with(Endpoint::new);
+        endpoint.receive(&datagram.payload)
+          .into_iter()
+          .filter_map(|bytes| {
+            ClientPayload::from_datagram(Datagram::new(address, bytes), codec).ok()
+          })
+          .collect::<Vec<_>>()
+      };
+
+      let connecting = received.iter().any(|payload| match payload.event {
+        ClientNetworkEvent::Connect(_) => true,
+        _ => false,
+      });
+      if connecting {
+        self.closing.remove(&address);
+      } else if !is_known {
+        // Strangers have to introduce themselves first
+        self.endpoints.remove(&address);
+        received.clear();
+      }
+      payloads.extend(received);
+    }
+
+    let now = now_ms();
+    for (address, endpoint) in self.endpoints.iter_mut() {
+      for frame in endpoint.outgoing(now) {
+        self.transport.send(Datagram::new(address.clone(), frame));
+      }
+    }
+
+    let dead = self.endpoints
+      .iter()
+      .filter(|&(_, endpoint)| endpoint.is_dead())
+      .map(|(address, _)| address.clone())
+      .collect::<Vec<_>>();
+    let closed = self.closing
+      .iter()
+      .filter(|address| self.endpoints.get(address).map(|e| e.unacked() == 0).unwrap_or(true))
+      .cloned()
+      .collect::<Vec<_>>();
+    for address in dead.iter() {
+      self.endpoints.remove(address);
+      if !self.closing.remove(address) {
+        self.lost.push(address.clone());
+      }
+    }
+    for address in closed.iter() {
+      self.endpoints.remove(address);
+      self.closing.remove(address);
+    }
+
+    payloads
   }
 
   pub fn send(&mut self, payload: ServerPayload) {
-    let json = serde_json::to_string(&payload.event).unwrap();
-    let _ = self.socket.send(GafferPacket::new(payload.address, json.as_bytes().to_vec()));
+    let now = now_ms();
+    let event = payload.event.restamped(now);
+    let bytes = self.codec.encode(&event);
+    let frame = match self.endpoints.get_mut(&payload.address) {
+      Some(endpoint) => endpoint.send(event.delivery(), &bytes, now),
+      None => return,
+    };
+    self.transport.send(Datagram::new(payload.address, frame));
+
+    match event {
+      ServerNetworkEvent::Disconnected |
+      ServerNetworkEvent::Rejected(_) => {
+        self.closing.insert(payload.address);
+      },
+      _ => {},
+    }
+  }
+
+  /**
+   * How many reliable events the clients at these addresses have yet to ack
+   */
+  pub fn unacked_by(&self, addresses: &Vec<SocketAddr>) -> usize {
+    addresses.iter()
+      .filter_map(|address| self.endpoints.get(address))
+      .map(|endpoint| endpoint.unacked())
+      .sum()
+  }
+
+  /**
+   * Yields the addresses of clients whose links have died since this was last asked
+   */
+  pub fn take_lost(&mut self) -> Vec<SocketAddr> {
+    self.lost.drain(..).collect()
   }
 }
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::cmp::Ordering;
+
+use common::codec::CodecKind;
+use common::aspects::{CommonWorld, PhysicalAspect, SynchronizedAspect};
+use common::protocol::SessionToken;
+
+/// How much more a moving entity matters than a still one
+const MOVING_PRIORITY: f32 = 2.0;
+
+/// How much a still entity matters, right next to the player
+const STATIC_PRIORITY: f32 = 1.0;
+
+/// The distance at which an entity matters half as much as one right next to the player
+const PRIORITY_FALLOFF: f32 = 10.0;
+
+/**
+ * What a client's latest snapshot cost, and what was held back from it
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotUsage {
+  /// Entity updates included, before compression, as counted against the budget
+  pub estimated_bytes: usize,
+  /// Sent over the wire, across every fragment
+  pub sent_bytes: usize,
+  /// Entities with updates held back for a later snapshot
+  pub deferred: usize,
+}
+
+/**
+ * The latest snapshot usage of each connected player, by their session
+ */
+pub struct SnapshotStats(pub HashMap<SessionToken, SnapshotUsage>);
+
+/**
+ * Fits entity updates into each client's per-tick byte budget
+ *
+ * Updates are sent in order of priority: the player's own entity always goes first (even over
+ * budget), then nearby moving entities, then still ones. An entity that's held back gains its
+ * priority again every tick until it's sent, so far-off entities are never starved. The most
+ * pressing of those held back goes through every tick even if it's over budget, so an entity too
+ * big for the budget on its own is still sent.
+ */
+pub struct BandwidthBudget {
+  bytes_per_tick: usize,
+  /// What snapshots are encoded with, to tell what updates cost
+  codec: CodecKind,
+}
+
+impl BandwidthBudget {
+  pub fn new(bytes_per_tick: usize, codec: CodecKind) -> BandwidthBudget {
+    BandwidthBudget {
+      bytes_per_tick: bytes_per_tick,
+      codec: codec,
+    }
+  }
+
+  /**
+   * Roughly how many bytes everything in a world takes up in a snapshot
+   */
+  pub fn cost_of_world(&self, world: &CommonWorld) -> usize {
+    world.entities
+      .iter()
+      .fold(0, |total, synchro| total + cost_of(self.codec, world, &synchro.to_string()))
+  }
+
+  /**
+   * Builds the world to send a client holding the baseline, moving it as far toward the target as
+   * the budget allows
+   *
+   * Held back entities keep their baseline state, or are left out if they're new. Removals cost
+   * nothing, so they always go through. The priorities held back entities have built up are kept
+   * in `deferred`.
+   */
+  pub fn fit(&self,
+             baseline: Option<&CommonWorld>,
+             target: &CommonWorld,
+             viewpoint: Option<&PhysicalAspect>,
+             deferred: &mut HashMap<SynchronizedAspect, f32>)
+             -> (CommonWorld, SnapshotUsage) {
+    let empty_world = CommonWorld::new();
+    let baseline = baseline.unwrap_or(&empty_world);
+    let mut world = CommonWorld::new();
+    world.own_entity = target.own_entity.clone();
+
+    let mut updates = Vec::new();
+    for synchro in target.entities.iter() {
+      let key = synchro.to_string();
+      if is_unchanged(baseline, target, synchro, &key) {
+        copy_entity(target, &mut world, synchro, &key);
+        continue;
+      }
+
+      let priority = if target.own_entity.as_ref() == Some(synchro) {
+        ::std::f32::INFINITY
+      } else {
+        deferred.get(synchro).cloned().unwrap_or(0.0) +
+        base_priority(viewpoint, target.physical.get(&key))
+      };
+      updates.push((priority, cost_of(self.codec, target, &key), synchro));
+    }
+    updates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let mut usage = SnapshotUsage {
+      estimated_bytes: 0,
+      sent_bytes: 0,
+      deferred: 0,
+    };
+    let mut overdue_sent = false;
+    for (priority, cost, synchro) in updates.into_iter() {
+      let key = synchro.to_string();
+      let is_overdue = !overdue_sent && deferred.contains_key(synchro);
+      if priority.is_infinite() || is_overdue ||
+         usage.estimated_bytes + cost <= self.bytes_per_tick {
+        copy_entity(target, &mut world, synchro, &key);
+        usage.estimated_bytes = usage.estimated_bytes + cost;
+        if deferred.remove(synchro).is_some() {
+          overdue_sent = true;
+        }
+      } else {
+        if baseline.entities.contains(synchro) {
+          copy_entity(baseline, &mut world, synchro, &key);
+        }
+        usage.deferred = usage.deferred + 1;
+        deferred.insert(synchro.clone(), priority);
+      }
+    }
+
+    let departed = deferred.keys()
+      .filter(|synchro| !target.entities.contains(*synchro))
+      .cloned()
+      .collect::<Vec<SynchronizedAspect>>();
+    for synchro in departed.iter() {
+      deferred.remove(synchro);
+    }
+
+    (world, usage)
+  }
+}
+
+fn base_priority(viewpoint: Option<&PhysicalAspect>, subject: Option<&PhysicalAspect>) -> f32 {
+  let subject = match subject {
+    Some(subject) => subject,
+    None => return STATIC_PRIORITY,
+  };
+
+  let distance = viewpoint.map(|viewer| {
+      let (dx, dy, dz) = (subject.pos.0 - viewer.pos.0,
+                          subject.pos.1 - viewer.pos.1,
+                          subject.pos.2 - viewer.pos.2);
+      (dx * dx + dy * dy + dz * dz).sqrt()
+    })
+    .unwrap_or(0.0);
+  let is_moving = subject.vel != (0.0, 0.0, 0.0) || subject.ang_vel != (0.0, 0.0, 0.0);
+  let priority = if is_moving {
+    MOVING_PRIORITY
+  } else {
+    STATIC_PRIORITY
+  };
+
+  priority / (1.0 + distance / PRIORITY_FALLOFF)
+}
+
+fn is_unchanged(baseline: &CommonWorld,
+                target: &CommonWorld,
+                synchro: &SynchronizedAspect,
+                key: &String)
+                -> bool {
+  baseline.entities.contains(synchro) && baseline.physical.get(key) == target.physical.get(key) &&
+  baseline.rendered.get(key) == target.rendered.get(key) &&
+  baseline.disabled.get(key) == target.disabled.get(key)
+}
+
+/**
+ * Roughly how many bytes an entity's aspects take up in a snapshot
+ */
+fn cost_of(codec: CodecKind, world: &CommonWorld, key: &String) -> usize {
+  key.len() + world.physical.get(key).map(|a| codec.encode(a).len()).unwrap_or(0) +
+  world.rendered.get(key).map(|a| codec.encode(a).len()).unwrap_or(0) +
+  world.disabled.get(key).map(|a| codec.encode(a).len()).unwrap_or(0)
+}
+
+fn copy_entity(from: &CommonWorld,
+               to: &mut CommonWorld,
+               synchro: &SynchronizedAspect,
+               key: &String) {
+  to.entities.insert(synchro.clone());
+  from.physical.get(key).map(|aspect| to.physical.insert(key.clone(), aspect.clone()));
+  from.rendered.get(key).map(|aspect| to.rendered.insert(key.clone(), aspect.clone()));
+  from.disabled.get(key).map(|aspect| to.disabled.insert(key.clone(), aspect.clone()));
+}
+
+#[cfg(test)]
+mod test {
+  use super::BandwidthBudget;
+  use std::collections::HashMap;
+  use common::codec::CodecKind;
+  use common::aspects::{CommonWorld, PhysicalAspect, SynchronizedAspect};
+
+  fn budget(bytes_per_tick: usize) -> BandwidthBudget {
+    BandwidthBudget::new(bytes_per_tick, CodecKind::Binary)
+  }
+
+  fn world_with(entities: &Vec<(SynchronizedAspect, f32, f32)>) -> CommonWorld {
+    let mut world = CommonWorld::new();
+    for &(ref synchro, x, vel) in entities.iter() {
+      world.entities.insert(synchro.clone());
+      world.physical
+        .insert(synchro.to_string(), PhysicalAspect::new((x, 0.0, 0.0), (vel, 0.0, 0.0)));
+    }
+    world
+  }
+
+  #[test]
+  fn own_entity_then_nearby_movers_go_first() {
+    let (own, near_mover, near_still, far_mover) =
+      (SynchronizedAspect::new(), SynchronizedAspect::new(), SynchronizedAspect::new(),
+       SynchronizedAspect::new());
+    let mut target = world_with(&vec![(own.clone(), 0.0, 0.0),
+                                      (near_mover.clone(), 1.0, 1.0),
+                                      (near_still.clone(), 1.0, 0.0),
+                                      (far_mover.clone(), 50.0, 1.0)]);
+    target.own_entity = Some(own.clone());
+    let mut deferred = HashMap::new();
+
+    // No room for anything besides our own entity
+    let (sent, usage) = budget(1).fit(None, &target, None, &mut deferred);
+    assert!(sent.entities.contains(&own));
+    assert_eq!(usage.deferred, 3);
+
+    // Room for one more, which the nearby mover has built up the most priority for
+    let viewpoint = target.physical.get(&own.to_string());
+    let room = usage.estimated_bytes * 5 / 2;
+    let (sent, _) = budget(room).fit(None, &target, viewpoint, &mut deferred);
+    assert!(sent.entities.contains(&own) && sent.entities.contains(&near_mover));
+    assert!(!sent.entities.contains(&near_still) && !sent.entities.contains(&far_mover));
+  }
+
+  #[test]
+  fn held_back_entities_keep_their_baseline_and_catch_up() {
+    let synchro = SynchronizedAspect::new();
+    let baseline = world_with(&vec![(synchro.clone(), 0.0, 1.0)]);
+    let target = world_with(&vec![(synchro.clone(), 1.0, 1.0)]);
+    let mut deferred = HashMap::new();
+
+    let (sent, usage) = budget(0).fit(Some(&baseline), &target, None, &mut deferred);
+    assert_eq!(sent, baseline);
+    assert_eq!(usage.deferred, 1);
+    assert!(deferred.contains_key(&synchro));
+
+    let (sent, usage) = budget(10000).fit(Some(&baseline), &target, None, &mut deferred);
+    assert_eq!(sent, target);
+    assert_eq!(usage.deferred, 0);
+    assert!(deferred.is_empty());
+  }
+
+  #[test]
+  fn entities_bigger_than_the_budget_still_go_through() {
+    let (big, other) = (SynchronizedAspect::new(), SynchronizedAspect::new());
+    let target = world_with(&vec![(big.clone(), 0.0, 1.0), (other.clone(), 20.0, 0.0)]);
+    let mut deferred = HashMap::new();
+
+    let (sent, usage) = budget(1).fit(None, &target, None, &mut deferred);
+    assert!(sent.entities.is_empty());
+    assert_eq!(usage.deferred, 2);
+
+    // Only the most pressing of those held back goes over budget, and the other waits its turn
+    let (sent, usage) = budget(1).fit(None, &target, None, &mut deferred);
+    assert!(sent.entities.contains(&big) && !sent.entities.contains(&other));
+    assert_eq!(usage.deferred, 1);
+
+    let (sent, _) = budget(1).fit(Some(&sent), &target, None, &mut deferred);
+    assert_eq!(sent, target);
+    assert!(deferred.is_empty());
+  }
+
+  #[test]
+  fn a_whole_world_costs_what_its_entities_do() {
+    let (near, far) = (SynchronizedAspect::new(), SynchronizedAspect::new());
+    let target = world_with(&vec![(near.clone(), 0.0, 0.0), (far.clone(), 50.0, 1.0)]);
+
+    let (_, usage) = budget(10000).fit(None, &target, None, &mut HashMap::new());
+    assert_eq!(budget(0).cost_of_world(&target), usage.estimated_bytes);
+  }
+
+  #[test]
+  fn unchanged_and_removed_entities_cost_nothing() {
+    let (kept, removed) = (SynchronizedAspect::new(), SynchronizedAspect::new());
+    let baseline = world_with(&vec![(kept.clone(), 0.0, 0.0), (removed.clone(), 0.0, 0.0)]);
+    let target = world_with(&vec![(kept.clone(), 0.0, 0.0)]);
+
+    let (sent, usage) = budget(0).fit(Some(&baseline), &target, None, &mut HashMap::new());
+    assert_eq!(sent, target);
+    assert_eq!(usage.estimated_bytes, 0);
+  }
+}
@@ -1,22 +1,25 @@
 use std::net::SocketAddr;
 
 use aspects::{CollisionAspect, ControllerAspect, PlayerAspect};
+use permission::PermissionConfig;
 
 use common::aspects::{DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
 
-use common::protocol::ServerNetworkEvent;
+use common::protocol::{Capability, ConnectAccepted, ConnectRejection, ConnectRequest,
+                       ServerNetworkEvent, SessionToken};
 use network::OutboundEvent;
 
 use std::collections::HashMap;
 
 use specs;
-use state::Delta;
+use state::{Delta, RunState};
 use pubsub::{PubSubStore, SubscriberToken};
 
 #[derive(Debug, Clone)]
 pub enum ConnectEvent {
-  Connect(SocketAddr),
-  Disconnect(SocketAddr),
+  /// A connect request from an address, presenting the session it was previously issued (if any)
+  Connect(SocketAddr, Option<SessionToken>, ConnectRequest),
+  Disconnect(SessionToken),
 }
 
 /**
@@ -24,18 +27,30 @@ pub enum ConnectEvent {
  *
  * Players and their associated entities have a lot of creation-time dependencies.
  *
+ * Clients speaking a different protocol version are rejected before any of that happens, as is
+ * everyone once the server is shutting down. Each new session is given a role according to the
+ * PermissionConfig, which it keeps for as long as it lives.
+ *
+ * A client presenting a session it was issued reclaims that session's player, even from a new
+ * address. Without one, only a repeated connect from a connected player's own address is treated
+ * as that player -- anyone else gets a new player and a new session.
+ *
  * TODO(acmcarther): Refactor this whole implementation, its really messy
  *
- * Input: Players, Controllers, ConnectEvent
+ * Input: Players, Controllers, ConnectEvent, RunState
  * Output: Players, Controllers, Collisions, Disableds, Renders, Physicals
  */
 pub struct System {
+  permissions: PermissionConfig,
   connection_event_sub_token: SubscriberToken<ConnectEvent>,
 }
 
 impl System {
-  pub fn new(world: &mut specs::World) -> System {
-    System { connection_event_sub_token: world.register_subscriber::<ConnectEvent>() }
+  pub fn new(world: &mut specs::World, permissions: PermissionConfig) -> System {
+    System {
+      permissions: permissions,
+      connection_event_sub_token: world.register_subscriber::<ConnectEvent>(),
+    }
   }
 }
 
@@ -53,7 +68,8 @@ impl specs::System<Delta> for System {
          mut collision,
          mut disabled,
          mut render,
-         mut physical) = arg.fetch(|w| {
+         mut physical,
+         run_state) = arg.fetch(|w| {
       (w.write::<PlayerAspect>(),
        w.entities(),
        w.write::<SynchronizedAspect>(),
@@ -63,7 +79,8 @@ impl specs::System<Delta> for System {
        w.write::<CollisionAspect>(),
        w.write::<DisabledAspect>(),
        w.write::<RenderAspect>(),
-       w.write::<PhysicalAspect>())
+       w.write::<PhysicalAspect>(),
+       w.read_resource::<RunState>())
     });
 
     // Build synchro -> entity map and our set of synchros
@@ -72,22 +89,51 @@ impl specs::System<Delta> for System {
       synchro_to_entity.insert(synchro.clone(), ent.clone());
     });
 
+    let permissions = &self.permissions;
     events.drain(..).foreach(|e| {
       match &e {
-        &ConnectEvent::Connect(addr) => {
+        &ConnectEvent::Connect(addr, resumed_session, ref request) => {
+          if let RunState::ShuttingDown(ref reason) = *run_state {
+            outbound.push(OutboundEvent::Directed {
+              dest: addr,
+              event: ServerNetworkEvent::Rejected(ConnectRejection::ShuttingDown(reason.clone())),
+            });
+            return;
+          }
+
+          let capabilities = match request.negotiate(&Capability::all()) {
+            Ok(capabilities) => capabilities,
+            Err(rejection) => {
+              println!("Rejected client {} (build {}): {}", addr, request.build_id, rejection);
+              outbound.push(OutboundEvent::Directed {
+                dest: addr,
+                event: ServerNetworkEvent::Rejected(rejection),
+              });
+              return;
+            },
+          };
+
           // TODO: Fix ugly code
           // TODO: Make more efficient, this is currently a linear search
           if let Some((current_player, controller)) = (&mut player, &mut controller)
             .iter()
-            .filter(|&(ref player, _)| player.address == addr)
+            .filter(|&(ref player, _)| match resumed_session {
+              Some(session) => player.session == session,
+              None => player.connected && player.address == addr,
+            })
             .next() {
+            current_player.address = addr;
             current_player.connected = true;
             current_player.last_msg = delta.now;
+            current_player.capabilities = capabilities.clone();
             disabled.remove(synchro_to_entity.get(&controller.subject).unwrap().clone());
             // Dodging borrow checker, by returning instead of else-ing
             outbound.push(OutboundEvent::Directed {
               dest: addr,
-              event: ServerNetworkEvent::Connected,
+              event: ServerNetworkEvent::Connected(ConnectAccepted {
+                session: current_player.session,
+                capabilities: capabilities,
+              }),
             });
             return;
           } else {
@@ -101,9 +147,13 @@ impl specs::System<Delta> for System {
           synchronized.insert(player_ent.clone(), SynchronizedAspect::new());
           synchronized.insert(object_ent.clone(), object_synchro.clone());
 
+          let session = SessionToken::new();
           player.insert(player_ent.clone(),
                         PlayerAspect {
                           address: addr,
+                          session: session,
+                          capabilities: capabilities.clone(),
+                          role: permissions.role_for(request.admin_secret.as_ref()),
                           last_msg: delta.now,
                           connected: true,
                         });
@@ -113,37 +163,33 @@ impl specs::System<Delta> for System {
 
           render.insert(object_ent.clone(), RenderAspect::new());
           physical.insert(object_ent.clone(),
-                          PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0), false));
+                          PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0)));
           collision.insert(object_ent.clone(), CollisionAspect::new());
           outbound.push(OutboundEvent::Directed {
             dest: addr,
-            event: ServerNetworkEvent::Connected,
+            event: ServerNetworkEvent::Connected(ConnectAccepted {
+              session: session,
+              capabilities: capabilities,
+            }),
           })
         },
-        &ConnectEvent::Disconnect(addr) => {
+        &ConnectEvent::Disconnect(session) => {
           println!("disconnect event");
           // TODO: Fix ugly code
           // TODO: Make more efficient, this is currently a linear search
+          // NOTE: Sessions are checked on the way in, so the player should always exist
           if let Some((current_player, controller)) = (&mut player, &mut controller)
             .iter()
-            .filter(|&(ref player, _)| player.address == addr)
+            .filter(|&(ref player, _)| player.session == session)
             .next() {
             current_player.connected = false;
             disabled.insert(synchro_to_entity.get(&controller.subject).unwrap().clone(),
                             DisabledAspect::default());
-            // Dodging borrow checker, by returning instead of else-ing
             outbound.push(OutboundEvent::Directed {
-              dest: addr,
+              dest: current_player.address.clone(),
               event: ServerNetworkEvent::Disconnected,
             });
-            return;
           }
-          // Else: no existing entity
-          outbound.push(OutboundEvent::Directed {
-            dest: addr,
-            event: ServerNetworkEvent::Error("Tried to disconnect, but not connected to server"
-              .to_owned()),
-          });
         },
       }
     });
@@ -1,43 +1,53 @@
 use specs;
 use time::Duration;
 
-use std::net::SocketAddr;
 use std::collections::HashMap;
 
 use aspects::PlayerAspect;
+use common::protocol::SessionToken;
 use state::Delta;
 
 use connection::ConnectEvent;
 use itertools::Itertools;
+use network::LinkLostEvent;
 use pubsub::{PubSubStore, SubscriberToken};
 
 #[derive(Debug, Clone)]
-pub struct HealthyEvent(SocketAddr);
+pub struct HealthyEvent(SessionToken);
 
 impl HealthyEvent {
-  pub fn new(address: SocketAddr) -> HealthyEvent {
-    HealthyEvent(address)
+  pub fn new(session: SessionToken) -> HealthyEvent {
+    HealthyEvent(session)
   }
 
-  pub fn address(&self) -> &SocketAddr {
-    let &HealthyEvent(ref addr) = self;
-    addr
+  pub fn session(&self) -> &SessionToken {
+    let &HealthyEvent(ref session) = self;
+    session
   }
 }
 
 /**
- * Accepts address-specific health events to update player's connection status
+ * Accepts session-specific health events to update player's connection status
  *
- * Inputs: HealthyEvents
+ * Players that haven't been heard from within the timeout are disconnected, as are those whose
+ * links have died.
+ *
+ * Inputs: HealthyEvents, LinkLostEvents
  * Outputs: Players, ConnectEvents
  */
 pub struct System {
+  timeout: Duration,
   healthy_event_sub_token: SubscriberToken<HealthyEvent>,
+  link_lost_event_sub_token: SubscriberToken<LinkLostEvent>,
 }
 
 impl System {
-  pub fn new(world: &mut specs::World) -> System {
-    System { healthy_event_sub_token: world.register_subscriber() }
+  pub fn new(world: &mut specs::World, timeout: Duration) -> System {
+    System {
+      timeout: timeout,
+      healthy_event_sub_token: world.register_subscriber(),
+      link_lost_event_sub_token: world.register_subscriber(),
+    }
   }
 }
 
@@ -45,34 +55,41 @@ impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, delta: Delta) {
     use specs::Join;
 
-    let (entities, mut players, mut healthy_events, mut connect_events) = arg.fetch(|w| {
-      (w.entities(),
-       w.write::<PlayerAspect>(),
-       w.fetch_subscriber(&self.healthy_event_sub_token).collected(),
-       w.fetch_publisher::<ConnectEvent>())
-    });
+    let (entities, mut players, mut healthy_events, lost_links, mut connect_events) =
+      arg.fetch(|w| {
+        (w.entities(),
+         w.write::<PlayerAspect>(),
+         w.fetch_subscriber(&self.healthy_event_sub_token).collected(),
+         w.fetch_subscriber(&self.link_lost_event_sub_token).collected(),
+         w.fetch_publisher::<ConnectEvent>())
+      });
 
-    // Build address to entity mapping for convenience
-    let mut addr_to_entity = HashMap::new();
+    // Build session to entity mapping for convenience
+    let mut session_to_entity = HashMap::new();
     (&entities, &players)
       .iter()
       .filter(|&(_, ref player)| player.connected)
       .foreach(|(entity, player)| {
-        addr_to_entity.insert(player.address.clone(), entity.clone());
+        session_to_entity.insert(player.session, entity.clone());
       });
 
     // Set all affected players last_msg to now
     healthy_events.drain(..)
-      .filter_map(|event| addr_to_entity.get(event.address()).map(|v| v.clone()))
+      .filter_map(|event| session_to_entity.get(event.session()).map(|v| v.clone()))
       .foreach(|entity| {
         players.get_mut(entity).unwrap().last_msg = delta.now.clone();
       });
 
     // Disconnect any dead players
+    let timeout = self.timeout;
     players.iter()
-      .filter(|&player| player.connected && delta.now - player.last_msg > Duration::seconds(3))
+      .filter(|&player| player.connected)
+      .filter(|&player| {
+        delta.now - player.last_msg > timeout ||
+        lost_links.iter().any(|&LinkLostEvent(address)| address == player.address)
+      })
       .foreach(|player| {
-        connect_events.push(ConnectEvent::Disconnect(player.address));
+        connect_events.push(ConnectEvent::Disconnect(player.session));
       });
   }
 }
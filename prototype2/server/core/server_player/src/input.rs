@@ -1,33 +1,48 @@
 use specs;
-use std::net::SocketAddr;
 use std::collections::HashMap;
 
-use common::protocol::ClientEvent;
+use common::movement;
+use common::protocol::{ClientEvent, PlayerInput, ServerNetworkEvent, SessionToken};
 use common::aspects::{PhysicalAspect, RenderAspect, SynchronizedAspect};
 use aspects::{CollisionAspect, ControllerAspect, PlayerAspect};
+use network::OutboundEvent;
+use permission::Permission;
 use state::Delta;
 use pubsub::{PubSubStore, SubscriberToken};
 
 #[derive(Debug, Clone)]
 pub struct InputEvent {
-  pub address: SocketAddr,
+  pub session: SessionToken,
   pub event: ClientEvent,
 }
 
 impl InputEvent {
-  pub fn new(address: SocketAddr, event: ClientEvent) -> InputEvent {
+  pub fn new(session: SessionToken, event: ClientEvent) -> InputEvent {
     InputEvent {
-      address: address,
+      session: session,
       event: event,
     }
   }
 }
 
+/**
+ * The latest input each connected player has sent, by their session
+ *
+ * Inputs are sequenced on the way in, so the latest is also the newest. It stands until the next
+ * one arrives.
+ */
+pub struct AppliedInputs(pub HashMap<SessionToken, PlayerInput>);
+
 /**
  * Handles input events for players
  *
- * Inputs: ClientEvent, Player
+ * Events the player's role doesn't permit are dropped, and the player is sent an Error saying why
+ * (except for PlayerInputs, which are sent every tick and are dropped quietly). PlayerInputs that
+ * aren't all finite numbers are dropped quietly too, and movement is clamped to a length of 1.
+ * Every tick, each player's entity is steered toward the movement in their latest PlayerInput.
  *
+ * Inputs: ClientEvent, Player
+ * Outputs: OutboundEvent, AppliedInputs, PhysicalAspect
  */
 pub struct System {
   input_event_sub_token: SubscriberToken<InputEvent>,
@@ -35,12 +50,14 @@ pub struct System {
 
 impl System {
   pub fn new(world: &mut specs::World) -> System {
+    world.add_resource::<AppliedInputs>(AppliedInputs(HashMap::new()));
+
     System { input_event_sub_token: world.register_subscriber() }
   }
 }
 
 impl specs::System<Delta> for System {
-  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+  fn run(&mut self, arg: specs::RunArg, delta: Delta) {
     use specs::Join;
     use itertools::Itertools;
 
@@ -51,7 +68,9 @@ impl specs::System<Delta> for System {
          mut collision,
          mut physicals,
          players,
-         controllers) = arg.fetch(|w| {
+         controllers,
+         mut applied_inputs,
+         mut outbound_events) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.input_event_sub_token).collected(),
        w.entities(),
        w.write::<SynchronizedAspect>(),
@@ -59,7 +78,24 @@ impl specs::System<Delta> for System {
        w.write::<CollisionAspect>(),
        w.write::<PhysicalAspect>(),
        w.read::<PlayerAspect>(),
-       w.read::<ControllerAspect>())
+       w.read::<ControllerAspect>(),
+       w.write_resource::<AppliedInputs>(),
+       w.fetch_publisher::<OutboundEvent>())
+    });
+
+    let mut session_to_player = HashMap::new();
+    players.iter().foreach(|player| {
+      session_to_player.insert(player.session, player.clone());
+    });
+
+    // A player that comes back starts numbering their moves over
+    let departed_sessions = applied_inputs.0
+      .keys()
+      .filter(|session| !session_to_player.get(*session).map(|p| p.connected).unwrap_or(false))
+      .cloned()
+      .collect::<Vec<SessionToken>>();
+    departed_sessions.iter().foreach(|session| {
+      applied_inputs.0.remove(session);
     });
 
     // Build synchro -> entity map and our set of synchros
@@ -68,35 +104,41 @@ impl specs::System<Delta> for System {
       synchro_to_entity.insert(synchro.clone(), ent.clone());
     });
 
-    // Grab our controller
-    // TODO(acmcarther): More robust address -> player association. This is
-    // insecure and also inconvient
-    let mut address_to_controller = HashMap::new();
-    (&players, &controllers)
-      .iter()
-      .foreach(|(player, controller)| {
-        address_to_controller.insert(player.address.clone(), controller.clone());
-      });
-
 
     client_events.drain(..).foreach(|event| {
+      let player = match session_to_player.get(&event.session) {
+        Some(player) => player,
+        None => return,
+      };
+
+      let permission = Permission::required_for(&event.event);
+      if !permission.granted_to(&player.role) {
+        if let ClientEvent::PlayerInput(_) = event.event {
+          return;
+        }
+        outbound_events.push(OutboundEvent::Directed {
+          dest: player.address.clone(),
+          event: ServerNetworkEvent::Error(format!("Permission denied: {:?} players may not {:?}",
+                                                   player.role,
+                                                   permission)),
+        });
+        return;
+      }
+
       match event.event {
-        ClientEvent::SelfMove { x_d, y_d, z_d } => {
-          if let Some(controller) = address_to_controller.get(&event.address) {
-            let mut physical =
-              physicals.get_mut(synchro_to_entity.get(&controller.subject).unwrap().clone())
-                .unwrap();
-            physical.vel.0 = physical.vel.0 + x_d;
-            physical.vel.1 = physical.vel.1 + y_d;
-            physical.vel.2 = physical.vel.2 + z_d;
+        ClientEvent::PlayerInput(mut input) => {
+          if !input.is_finite() {
+            return;
           }
+          input.movement = movement::clamped(input.movement);
+          applied_inputs.0.insert(event.session, input);
         },
         ClientEvent::CreateEntity => {
           let ent = arg.create();
           synchronized.insert(ent.clone(), SynchronizedAspect::new());
           render.insert(ent.clone(), RenderAspect::new());
           physicals.insert(ent.clone(),
-                           PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0), false));
+                           PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0)));
           collision.insert(ent.clone(), CollisionAspect::new());
         },
         ClientEvent::DeleteEntity(synchro) => {
@@ -107,11 +149,30 @@ impl specs::System<Delta> for System {
         },
         ClientEvent::MutateRenderAspect(synchro, new_render) => {
           synchro_to_entity.get(&synchro).map(|e| {
-            collision.insert(e.clone(), CollisionAspect::from_render(&new_render));
+            let reshaped = collision.get(e.clone())
+              .cloned()
+              .unwrap_or(CollisionAspect::new())
+              .reshaped_for(&new_render);
+            collision.insert(e.clone(), reshaped);
             render.insert(e.clone(), new_render)
           });
         },
       };
     });
+
+    // Steer each player's entity toward where they last asked to go
+    let dt_s = delta.dt_s();
+    (&players, &controllers)
+      .iter()
+      .filter(|&(player, _)| player.connected)
+      .foreach(|(player, controller)| {
+        let input = applied_inputs.0.get(&player.session);
+        let subject = synchro_to_entity.get(&controller.subject);
+        if let (Some(input), Some(subject)) = (input, subject) {
+          physicals.get_mut(subject.clone()).map(|physical| {
+            physical.vel = movement::steer(physical.vel, input.movement, dt_s);
+          });
+        }
+      });
   }
 }
@@ -0,0 +1,117 @@
+use common::aspects::PhysicalAspect;
+
+/// How much further than the radius an entity already in view may go before it's dropped, so
+/// entities on the edge don't flicker in and out
+const LEAVE_RADIUS_FACTOR: f32 = 1.1;
+
+/**
+ * Hides entities a player is in range of but shouldn't see, like those behind a bulkhead
+ */
+pub trait Occlusion: Send + Sync {
+  fn occludes(&self, viewer: &PhysicalAspect, subject: &PhysicalAspect) -> bool;
+}
+
+/**
+ * Hides nothing
+ */
+pub struct NoOcclusion;
+
+impl Occlusion for NoOcclusion {
+  fn occludes(&self, _: &PhysicalAspect, _: &PhysicalAspect) -> bool {
+    false
+  }
+}
+
+/**
+ * Decides which entities are relevant to a player, and so go in their snapshots
+ *
+ * An entity is relevant when it's within the radius of the entity the player controls and isn't
+ * occluded. Entities without a position are always relevant, as is everything to a player who
+ * doesn't control a positioned entity.
+ */
+pub struct InterestFilter {
+  radius: f32,
+  occlusion: Box<Occlusion>,
+}
+
+impl InterestFilter {
+  pub fn new(radius: f32) -> InterestFilter {
+    InterestFilter {
+      radius: radius,
+      occlusion: Box::new(NoOcclusion),
+    }
+  }
+
+  pub fn set_occlusion(&mut self, occlusion: Box<Occlusion>) {
+    self.occlusion = occlusion;
+  }
+
+  /**
+   * Whether the subject belongs in the view of a player at the viewpoint, given whether it was
+   * in their view last time
+   */
+  pub fn is_relevant(&self,
+                     viewpoint: Option<&PhysicalAspect>,
+                     subject: Option<&PhysicalAspect>,
+                     was_relevant: bool)
+                     -> bool {
+    let (viewer, subject) = match (viewpoint, subject) {
+      (Some(viewer), Some(subject)) => (viewer, subject),
+      _ => return true,
+    };
+
+    let radius = if was_relevant {
+      self.radius * LEAVE_RADIUS_FACTOR
+    } else {
+      self.radius
+    };
+    let (dx, dy, dz) = (subject.pos.0 - viewer.pos.0,
+                        subject.pos.1 - viewer.pos.1,
+                        subject.pos.2 - viewer.pos.2);
+
+    dx * dx + dy * dy + dz * dz <= radius * radius && !self.occlusion.occludes(viewer, subject)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{InterestFilter, Occlusion};
+  use common::aspects::PhysicalAspect;
+
+  fn at(x: f32) -> PhysicalAspect {
+    PhysicalAspect::new((x, 0.0, 0.0), (0.0, 0.0, 0.0))
+  }
+
+  #[test]
+  fn entities_leave_view_a_little_further_out_than_they_enter() {
+    let filter = InterestFilter::new(10.0);
+
+    assert!(filter.is_relevant(Some(&at(0.0)), Some(&at(9.0)), false));
+    assert!(!filter.is_relevant(Some(&at(0.0)), Some(&at(10.5)), false));
+    assert!(filter.is_relevant(Some(&at(0.0)), Some(&at(10.5)), true));
+    assert!(!filter.is_relevant(Some(&at(0.0)), Some(&at(12.0)), true));
+  }
+
+  #[test]
+  fn unpositioned_entities_are_always_relevant() {
+    let filter = InterestFilter::new(10.0);
+
+    assert!(filter.is_relevant(None, Some(&at(1000.0)), false));
+    assert!(filter.is_relevant(Some(&at(0.0)), None, false));
+  }
+
+  #[test]
+  fn occluded_entities_are_hidden() {
+    struct Bulkhead;
+    impl Occlusion for Bulkhead {
+      fn occludes(&self, viewer: &PhysicalAspect, subject: &PhysicalAspect) -> bool {
+        (viewer.pos.0 < 5.0) != (subject.pos.0 < 5.0)
+      }
+    }
+    let mut filter = InterestFilter::new(10.0);
+    filter.set_occlusion(Box::new(Bulkhead));
+
+    assert!(filter.is_relevant(Some(&at(0.0)), Some(&at(4.0)), false));
+    assert!(!filter.is_relevant(Some(&at(0.0)), Some(&at(6.0)), false));
+  }
+}
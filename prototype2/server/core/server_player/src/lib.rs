@@ -1,6 +1,8 @@
 extern crate specs;
 extern crate time;
 extern crate itertools;
+extern crate toml;
+extern crate serde_json;
 
 extern crate common;
 extern crate aspects;
@@ -8,17 +10,26 @@ extern crate server_network as network;
 extern crate server_state as state;
 extern crate pubsub;
 
+mod budget;
 mod connection;
 mod health_check;
 mod input;
+mod interest;
+mod permission;
+mod ping;
 mod snapshot;
 
 pub use snapshot::System as SnapshotSystem;
 pub use input::System as InputSystem;
 pub use health_check::System as HealthCheckSystem;
 pub use connection::System as ConnectionSystem;
+pub use ping::System as PingSystem;
 
 pub use snapshot::SnapshotAckEvent;
+pub use budget::{BandwidthBudget, SnapshotStats, SnapshotUsage};
 pub use connection::ConnectEvent;
 pub use health_check::HealthyEvent;
-pub use input::InputEvent;
+pub use input::{AppliedInputs, InputEvent};
+pub use interest::{InterestFilter, NoOcclusion, Occlusion};
+pub use ping::{KeepAliveEvent, PlayerLinkStats};
+pub use permission::{Permission, PermissionConfig};
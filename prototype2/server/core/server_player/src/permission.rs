@@ -0,0 +1,190 @@
+use toml;
+
+use aspects::Role;
+use common::protocol::ClientEvent;
+
+/**
+ * Something a ClientEvent asks the server to do on a player's behalf
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+  /// Steer the entity the player controls
+  MoveSelf,
+  CreateEntity,
+  MutateEntity,
+  DeleteEntity,
+}
+
+impl Permission {
+  pub fn required_for(event: &ClientEvent) -> Permission {
+    match event {
+      &ClientEvent::PlayerInput(_) => Permission::MoveSelf,
+      &ClientEvent::CreateEntity => Permission::CreateEntity,
+      &ClientEvent::MutatePhysicalAspect(..) => Permission::MutateEntity,
+      &ClientEvent::MutateRenderAspect(..) => Permission::MutateEntity,
+      &ClientEvent::DeleteEntity(..) => Permission::DeleteEntity,
+    }
+  }
+
+  pub fn granted_to(&self, role: &Role) -> bool {
+    match (*role, *self) {
+      (Role::Admin, _) => true,
+      (Role::Crew, Permission::MoveSelf) => true,
+      _ => false,
+    }
+  }
+}
+
+/**
+ * Decides the role of each connecting player
+ *
+ * Players presenting the admin secret in their ConnectRequest are admins, and everyone else gets
+ * the default role. Addresses are never trusted, since they can be spoofed or shared. Without a
+ * secret, nobody can become an admin. Read from the permissions section of the server config:
+ *
+ * ```toml
+ * [permissions]
+ * default_role = "crew"
+ * admin_secret = "correct horse battery staple"
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionConfig {
+  pub default_role: Role,
+  pub admin_secret: Option<String>,
+}
+
+impl PermissionConfig {
+  /**
+   * Makes everyone crew
+   */
+  pub fn new() -> PermissionConfig {
+    PermissionConfig {
+      default_role: Role::Crew,
+      admin_secret: None,
+    }
+  }
+
+  pub fn from_toml(value: &toml::Value) -> Result<PermissionConfig, String> {
+    let mut config = PermissionConfig::new();
+
+    if let Some(role) = value.lookup("permissions.default_role") {
+      config.default_role = try!(role.as_str()
+        .ok_or("permissions.default_role should be a string".to_owned())
+        .and_then(role_from_str));
+    }
+
+    if let Some(secret) = value.lookup("permissions.admin_secret") {
+      let secret = try!(secret.as_str()
+        .ok_or("permissions.admin_secret should be a string".to_owned()));
+      if secret.is_empty() {
+        return Err("permissions.admin_secret should not be empty".to_owned());
+      }
+      config.admin_secret = Some(secret.to_owned());
+    }
+
+    Ok(config)
+  }
+
+  /**
+   * Describes this config as the contents of a permissions section
+   */
+  pub fn to_toml(&self) -> toml::Value {
+    let mut table = toml::Table::new();
+    table.insert("default_role".to_owned(),
+                 toml::Value::String(role_to_str(&self.default_role).to_owned()));
+    if let Some(ref secret) = self.admin_secret {
+      table.insert("admin_secret".to_owned(), toml::Value::String(secret.clone()));
+    }
+    toml::Value::Table(table)
+  }
+
+  /**
+   * Decides the role of a new session, given the secret its ConnectRequest presented (if any)
+   */
+  pub fn role_for(&self, presented: Option<&String>) -> Role {
+    match (self.admin_secret.as_ref(), presented) {
+      (Some(secret), Some(presented)) if secrets_match(secret, presented) => Role::Admin,
+      _ => self.default_role,
+    }
+  }
+}
+
+/**
+ * Compares every byte whatever the first mismatch, so timing doesn't give away how much of a
+ * guess was right
+ */
+fn secrets_match(secret: &String, presented: &String) -> bool {
+  let (secret, presented) = (secret.as_bytes(), presented.as_bytes());
+  if secret.len() != presented.len() {
+    return false;
+  }
+  secret.iter().zip(presented.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn role_to_str(role: &Role) -> &'static str {
+  match role {
+    &Role::Admin => "admin",
+    &Role::Crew => "crew",
+    &Role::Observer => "observer",
+  }
+}
+
+fn role_from_str(s: &str) -> Result<Role, String> {
+  match s {
+    "admin" => Ok(Role::Admin),
+    "crew" => Ok(Role::Crew),
+    "observer" => Ok(Role::Observer),
+    _ => Err(format!("{} is not a role (admin, crew or observer)", s)),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use toml;
+
+  use aspects::Role;
+  use common::protocol::{ClientEvent, PlayerInput};
+
+  fn parse(contents: &str) -> Result<PermissionConfig, String> {
+    let table = toml::Parser::new(contents).parse().unwrap();
+    PermissionConfig::from_toml(&toml::Value::Table(table))
+  }
+
+  #[test]
+  fn only_admins_may_edit_the_world() {
+    let create = Permission::required_for(&ClientEvent::CreateEntity);
+    let steer = Permission::required_for(&ClientEvent::PlayerInput(PlayerInput {
+      seq: 0,
+      movement: (1.0, 0.0, 0.0),
+      look: (0.0, 0.0),
+      buttons: 0,
+    }));
+
+    assert!(create.granted_to(&Role::Admin));
+    assert!(!create.granted_to(&Role::Crew));
+    assert!(steer.granted_to(&Role::Crew));
+    assert!(!steer.granted_to(&Role::Observer));
+  }
+
+  #[test]
+  fn admins_are_those_presenting_the_secret() {
+    let config = parse("[permissions]\ndefault_role = \"observer\"\nadmin_secret = \"hunter2\"")
+      .unwrap();
+
+    assert_eq!(config.role_for(Some(&"hunter2".to_owned())), Role::Admin);
+    assert_eq!(config.role_for(Some(&"hunter3".to_owned())), Role::Observer);
+    assert_eq!(config.role_for(Some(&"hunter".to_owned())), Role::Observer);
+    assert_eq!(config.role_for(None), Role::Observer);
+    assert_eq!(PermissionConfig::new().role_for(Some(&String::new())), Role::Crew);
+  }
+
+  #[test]
+  fn bad_configs_are_rejected() {
+    assert_eq!(parse("").unwrap(), PermissionConfig::new());
+    assert!(parse("[permissions]\ndefault_role = \"captain\"").is_err());
+    assert!(parse("[permissions]\nadmin_secret = 7").is_err());
+    assert!(parse("[permissions]\nadmin_secret = \"\"").is_err());
+  }
+}
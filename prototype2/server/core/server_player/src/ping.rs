@@ -0,0 +1,100 @@
+use specs;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use aspects::PlayerAspect;
+use common::network::{LinkStats, now_ms};
+use common::protocol::{ServerEcho, ServerNetworkEvent, SessionToken};
+use network::OutboundEvent;
+use state::Delta;
+
+use itertools::Itertools;
+use pubsub::{PubSubStore, SubscriberToken};
+
+/**
+ * A KeepAlive from a connected player
+ */
+#[derive(Debug, Clone)]
+pub struct KeepAliveEvent {
+  pub session: SessionToken,
+  pub address: SocketAddr,
+  pub client_ms: u64,
+  pub echo: Option<ServerEcho>,
+  /// When it arrived, by the server's clock
+  pub received_ms: u64,
+}
+
+/**
+ * How the link to each connected player is behaving, by their session
+ */
+pub struct PlayerLinkStats(pub HashMap<SessionToken, LinkStats>);
+
+/**
+ * Answers KeepAlives with the server's tick and clock, so clients can time their link
+ *
+ * Clients hand the server's clock readings back in their next KeepAlive, which times the link from
+ * this end too. Answers are restamped when the network actually sends them, a tick later.
+ *
+ * Inputs: KeepAliveEvent, Players
+ * Outputs: OutboundEvent, PlayerLinkStats
+ */
+pub struct System {
+  keep_alive_event_sub_token: SubscriberToken<KeepAliveEvent>,
+}
+
+impl System {
+  pub fn new(world: &mut specs::World) -> System {
+    world.add_resource::<PlayerLinkStats>(PlayerLinkStats(HashMap::new()));
+
+    System { keep_alive_event_sub_token: world.register_subscriber() }
+  }
+}
+
+impl specs::System<Delta> for System {
+  fn run(&mut self, arg: specs::RunArg, delta: Delta) {
+    use specs::Join;
+
+    let (players, mut keep_alive_events, mut outbound_events, mut link_stats) =
+      arg.fetch(|w| {
+        (w.read::<PlayerAspect>(),
+         w.fetch_subscriber(&self.keep_alive_event_sub_token).collected(),
+         w.fetch_publisher::<OutboundEvent>(),
+         w.write_resource::<PlayerLinkStats>())
+      });
+
+    let PlayerLinkStats(ref mut stats_by_session) = *link_stats;
+
+    let now = now_ms();
+    keep_alive_events.drain(..).foreach(|event| {
+      outbound_events.push(OutboundEvent::Directed {
+        dest: event.address,
+        event: ServerNetworkEvent::KeepAlive {
+          client_ms: event.client_ms,
+          server_tick: delta.tick,
+          server_ms: now,
+          held_ms: now.saturating_sub(event.received_ms),
+        },
+      });
+
+      if let Some(echo) = event.echo {
+        stats_by_session.entry(event.session)
+          .or_insert_with(LinkStats::new)
+          .record(echo.server_ms, event.received_ms, echo.held_ms, None);
+      }
+    });
+
+    // Forget players that have left
+    let connected = players.iter()
+      .filter(|player| player.connected)
+      .map(|player| player.session)
+      .collect::<Vec<SessionToken>>();
+    let departed = stats_by_session.keys()
+      .filter(|session| !connected.contains(session))
+      .cloned()
+      .collect::<Vec<SessionToken>>();
+    departed.iter().foreach(|session| {
+      stats_by_session.remove(session);
+    });
+  }
+}
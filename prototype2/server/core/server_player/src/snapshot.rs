@@ -4,59 +4,197 @@ use specs;
 
 use std::net::SocketAddr;
 
+use itertools::Itertools;
+
 use network::{Fragmentable, OutboundEvent};
-use common::protocol::ServerNetworkEvent;
+use common::codec::CodecKind;
+use common::network::now_ms;
 use common::aspects::{CommonWorld, DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
+use common::protocol::{Capability, ServerNetworkEvent, SessionToken, SnapshotEvent};
+use common::snapshot::WorldDelta;
+use common::util::Newness;
 use aspects::{ControllerAspect, PlayerAspect};
+use budget::{BandwidthBudget, SnapshotStats, SnapshotUsage};
+use input::AppliedInputs;
+use interest::{InterestFilter, Occlusion};
 use state::Delta;
 use pubsub::{PubSubStore, SubscriberToken};
 
+/**
+ * How many snapshots we remember sending to a client without hearing an ack.
+ *
+ * Baselines older than this are abandoned in favor of a full snapshot, so a client that stopped
+ * acking can always catch back up.
+ */
+const MAX_UNACKED_SNAPSHOTS: u16 = 64;
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct SnapshotAckEvent {
-  address: SocketAddr,
+  session: SessionToken,
   idx: u16,
 }
 
 impl SnapshotAckEvent {
-  pub fn new(address: SocketAddr, idx: u16) -> SnapshotAckEvent {
+  pub fn new(session: SessionToken, idx: u16) -> SnapshotAckEvent {
     SnapshotAckEvent {
-      address: address,
+      session: session,
       idx: idx,
     }
   }
 }
 
+/**
+ * The snapshots sent to a single client, and the most recent one it has acked
+ *
+ * A client that reclaims its session from a new address may not remember any of these, so the
+ * history only holds for the address it was sent to.
+ */
+struct SnapshotHistory {
+  address: SocketAddr,
+  acked: Option<(u16, CommonWorld)>,
+  unacked: HashMap<u16, CommonWorld>,
+  /// The entities that were relevant to the client in the latest snapshot
+  in_view: HashSet<SynchronizedAspect>,
+  /// How much priority each entity held back from the client has built up
+  deferred: HashMap<SynchronizedAspect, f32>,
+}
+
+impl SnapshotHistory {
+  pub fn new(address: SocketAddr) -> SnapshotHistory {
+    SnapshotHistory {
+      address: address,
+      acked: None,
+      unacked: HashMap::new(),
+      in_view: HashSet::new(),
+      deferred: HashMap::new(),
+    }
+  }
+
+  /**
+   * Promotes an unacked snapshot to be the baseline for future deltas
+   *
+   * Acks for snapshots older than the current baseline are ignored, as are acks for snapshots we
+   * have already forgotten.
+   */
+  pub fn ack(&mut self, idx: u16) {
+    let is_newest_ack = match self.acked {
+      Some((acked_idx, _)) => idx.is_newer_than(&acked_idx),
+      None => true,
+    };
+
+    if !is_newest_ack {
+      return;
+    }
+
+    if let Some(world) = self.unacked.remove(&idx) {
+      self.acked = Some((idx, world));
+      self.forget_where(|sent_idx| !sent_idx.is_newer_than(&idx));
+    }
+  }
+
+  /**
+   * Remembers a sent snapshot so it can become a baseline once it is acked
+   */
+  pub fn record(&mut self, idx: u16, world: CommonWorld) {
+    self.forget_where(|sent_idx| idx.wrapping_sub(sent_idx) >= MAX_UNACKED_SNAPSHOTS);
+
+    let baseline_expired = match self.acked {
+      Some((acked_idx, _)) => idx.wrapping_sub(acked_idx) >= MAX_UNACKED_SNAPSHOTS,
+      None => false,
+    };
+    if baseline_expired {
+      self.acked = None;
+    }
+
+    self.unacked.insert(idx, world);
+  }
+
+  /**
+   * Builds the delta from the client's acked baseline, or a full snapshot if there isn't one
+   */
+  pub fn delta_to(&self, world: &CommonWorld) -> WorldDelta {
+    WorldDelta::between(self.acked.as_ref().map(|&(idx, ref acked)| (idx, acked)), world)
+  }
+
+  fn forget_where<F: Fn(u16) -> bool>(&mut self, predicate: F) {
+    let forgotten = self.unacked
+      .keys()
+      .cloned()
+      .filter(|sent_idx| predicate(*sent_idx))
+      .collect::<Vec<u16>>();
+
+    forgotten.iter().foreach(|sent_idx| {
+      self.unacked.remove(sent_idx);
+    });
+  }
+}
+
 /**
  * Manages the broadcast of state snapshots, and the receipt of ack for those snapshots
  *
- * Input: SnapshotAckEvent, ClientState(PlayerAspect, PhysicalAspec, RenderAspect, DisabledAspect,
- * ControllerAspect
+ * Each client that negotiated DeltaSnapshots is sent a delta against the last snapshot it acked,
+ * so only added, removed and changed entities and aspects go over the wire. Other clients are sent
+ * the whole world every time.
+ *
+ * Every snapshot is stamped with the tick it was taken on, so clients can interpolate between them,
+ * and with the latest of the client's moves it includes, so they can replay the rest.
+ *
+ * Each client only hears about the entities relevant to it, by the InterestFilter. Entities that
+ * come into view show up in its snapshots as added, and those that leave as removed. Updates to
+ * them are then fitted into the client's BandwidthBudget, with the rest held back until later
+ * snapshots. Clients that can't take deltas are sent everything in view, whatever it costs.
+ * Snapshots are only taken in the last tick of each frame.
+ *
+ * Input: SnapshotAckEvent, AppliedInputs, ClientState(PlayerAspect, PhysicalAspec,
+ * RenderAspect, DisabledAspect, ControllerAspect
+ * Output: OutboundEvent, SnapshotStats
  */
 pub struct System {
   snapshot_idx: u16,
+  fragment_size: usize,
+  codec: CodecKind,
+  histories: HashMap<SessionToken, SnapshotHistory>,
   snapshot_ack_sub_token: SubscriberToken<SnapshotAckEvent>,
+  interest: InterestFilter,
+  budget: BandwidthBudget,
 }
 
 impl System {
-  pub fn new(world: &mut specs::World) -> System {
+  pub fn new(world: &mut specs::World,
+             fragment_size: usize,
+             codec: CodecKind,
+             interest_radius: f32,
+             budget_bytes: usize)
+             -> System {
+    world.add_resource::<SnapshotStats>(SnapshotStats(HashMap::new()));
+
     System {
       snapshot_idx: 0,
+      fragment_size: fragment_size,
+      codec: codec,
+      histories: HashMap::new(),
       snapshot_ack_sub_token: world.register_subscriber(),
+      interest: InterestFilter::new(interest_radius),
+      budget: BandwidthBudget::new(budget_bytes, codec),
     }
   }
+
+  /**
+   * Hides entities from players that are in range of them but can't see them
+   */
+  pub fn with_occlusion(mut self, occlusion: Box<Occlusion>) -> System {
+    self.interest.set_occlusion(occlusion);
+    self
+  }
 }
 
 #[allow(unused_variables, unused_imports)]
 impl specs::System<Delta> for System {
-  fn run(&mut self, arg: specs::RunArg, _: Delta) {
+  fn run(&mut self, arg: specs::RunArg, delta: Delta) {
     use specs::Join;
-    use itertools::Itertools;
-
-    self.snapshot_idx = self.snapshot_idx.wrapping_add(1);
 
     let (mut snapshot_ack_events,
+         applied_inputs,
          entities,
          synchronized,
          player,
@@ -64,8 +202,10 @@ impl specs::System<Delta> for System {
          render,
          disabled,
          controller,
-         mut outbound_events) = arg.fetch(|w| {
+         mut outbound_events,
+         mut snapshot_stats) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.snapshot_ack_sub_token).collected(),
+       w.read_resource::<AppliedInputs>(),
        w.entities(),
        w.read::<SynchronizedAspect>(),
        w.read::<PlayerAspect>(),
@@ -73,11 +213,35 @@ impl specs::System<Delta> for System {
        w.read::<RenderAspect>(),
        w.read::<DisabledAspect>(),
        w.read::<ControllerAspect>(),
-       w.fetch_publisher::<OutboundEvent>())
+       w.fetch_publisher::<OutboundEvent>(),
+       w.write_resource::<SnapshotStats>())
+    });
+
+    // Move each client's baseline up to the newest snapshot it has acked
+    snapshot_ack_events.drain(..).foreach(|event| {
+      self.histories.get_mut(&event.session).map(|history| history.ack(event.idx));
+    });
+
+    // Forget clients that have gone away, so they start from a full snapshot when they return
+    let connected_sessions = player.iter()
+      .filter(|ply| ply.connected)
+      .map(|ply| ply.session)
+      .collect::<HashSet<SessionToken>>();
+    let departed_sessions = self.histories
+      .keys()
+      .filter(|session| !connected_sessions.contains(*session))
+      .cloned()
+      .collect::<Vec<SessionToken>>();
+    departed_sessions.iter().foreach(|session| {
+      self.histories.remove(session);
+      snapshot_stats.0.remove(session);
     });
 
-    // TODO(acmcarther): Something useful with this event
-    snapshot_ack_events.drain(..);
+    // A frame that runs several ticks to catch up only sends the last one's snapshot
+    if !delta.last_of_frame {
+      return;
+    }
+    self.snapshot_idx = self.snapshot_idx.wrapping_add(1);
 
     let mut entity_set = HashSet::new();
     let mut physical_map = HashMap::new();
@@ -106,30 +270,92 @@ impl specs::System<Delta> for System {
     });
 
     // Add outbound state snapshot events per player
+    let snapshot_idx = self.snapshot_idx;
+    let (tick, server_ms) = (delta.tick, now_ms());
+    let (fragment_size, codec) = (self.fragment_size, self.codec);
+    let histories = &mut self.histories;
+    let interest = &self.interest;
+    let budget = &self.budget;
     (&player, &entities)
       .iter()
       .filter(|&(ply, _)| ply.connected)
-      .flat_map(|(ply, entity)| {
+      .foreach(|(ply, entity)| {
+        let history = histories.entry(ply.session)
+          .or_insert_with(|| SnapshotHistory::new(ply.address.clone()));
+        if history.address != ply.address {
+          *history = SnapshotHistory::new(ply.address.clone());
+        }
+
+        let own_entity = controller.get(entity).map(|v| v.subject.clone());
+        let viewpoint = own_entity.as_ref().and_then(|own| physical_map.get(&own.to_string()));
+        let in_view = entity_set.iter()
+          .filter(|synchro| {
+            own_entity.as_ref() == Some(*synchro) ||
+            interest.is_relevant(viewpoint,
+                                 physical_map.get(&synchro.to_string()),
+                                 history.in_view.contains(*synchro))
+          })
+          .cloned()
+          .collect::<HashSet<SynchronizedAspect>>();
+        let in_view_keys = in_view.iter().map(|synchro| synchro.to_string()).collect();
+
         let common_world = CommonWorld {
-          own_entity: controller.get(entity).map(|v| v.subject.clone()),
-          entities: entity_set.clone(),
-          rendered: render_map.clone(),
-          physical: physical_map.clone(),
-          disabled: disabled_map.clone(),
+          own_entity: own_entity,
+          entities: in_view.clone(),
+          rendered: only_in_view(&render_map, &in_view_keys),
+          physical: only_in_view(&physical_map, &in_view_keys),
+          disabled: only_in_view(&disabled_map, &in_view_keys),
+        };
+        history.in_view = in_view;
+        let (delta, common_world, mut usage) = if ply.supports(&Capability::DeltaSnapshots) {
+          let (fitted, usage) = budget.fit(history.acked.as_ref().map(|&(_, ref acked)| acked),
+                                           &common_world,
+                                           viewpoint,
+                                           &mut history.deferred);
+          (history.delta_to(&fitted), fitted, usage)
+        } else {
+          let usage = SnapshotUsage {
+            estimated_bytes: budget.cost_of_world(&common_world),
+            sent_bytes: 0,
+            deferred: 0,
+          };
+          (WorldDelta::between(None, &common_world), common_world, usage)
         };
+        history.record(snapshot_idx, common_world);
 
-        common_world.fragment_to_events(self.snapshot_idx)
-          .into_iter()
-          .map(|partial| (ply.address.clone(), partial))
-          .collect::<Vec<(SocketAddr, ServerNetworkEvent)>>()
-          .into_iter()
-      })
-      .map(|(addr, event)| {
-        OutboundEvent::Directed {
-          dest: addr,
-          event: event,
-        }
-      })
-      .foreach(|e| outbound_events.push(e));
+        let events = delta.stamped(tick, server_ms)
+          .acknowledging(applied_inputs.0.get(&ply.session).map(|input| input.seq))
+          .fragment_to_events(snapshot_idx, fragment_size, codec);
+        usage.sent_bytes = events.iter().fold(0, |total, event| total + fragment_len(event));
+        snapshot_stats.0.insert(ply.session, usage);
+
+        events.into_iter().foreach(|event| {
+          outbound_events.push(OutboundEvent::Directed {
+            dest: ply.address.clone(),
+            event: event,
+          })
+        });
+      });
+  }
+}
+
+/**
+ * The part of an aspect map (keyed by stringified synchro) about entities in view
+ */
+fn only_in_view<T: Clone>(map: &HashMap<String, T>,
+                          in_view_keys: &HashSet<String>)
+                          -> HashMap<String, T> {
+  map.iter()
+    .filter(|&(key, _)| in_view_keys.contains(key))
+    .map(|(key, aspect)| (key.clone(), aspect.clone()))
+    .collect()
+}
+
+fn fragment_len(event: &ServerNetworkEvent) -> usize {
+  match event {
+    &ServerNetworkEvent::Snapshot(SnapshotEvent::PartialSnapshot(ref fragment)) => {
+      fragment.payload.len()
+    },
+    _ => 0,
   }
 }
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use common::aspects::SynchronizedAspect;
+
+/**
+ * Two simulated entities starting or stopping touching
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollisionEvent {
+  /// With where they first touched, and roughly the impulse b gave a, both in game space
+  ///
+  /// The impulse isn't the solver's. It's what it would take to stop the two closing: their
+  /// reduced mass times how fast they were closing along the contact normal going into the tick,
+  /// pointed back along that normal.
+  Started {
+    a: SynchronizedAspect,
+    b: SynchronizedAspect,
+    point: (f32, f32, f32),
+    estimated_impulse: (f32, f32, f32),
+  },
+  Ended {
+    a: SynchronizedAspect,
+    b: SynchronizedAspect,
+  },
+}
+
+impl CollisionEvent {
+  /**
+   * The end of the touch this event started
+   */
+  pub fn ended(&self) -> CollisionEvent {
+    match self {
+      &CollisionEvent::Started { ref a, ref b, .. } |
+      &CollisionEvent::Ended { ref a, ref b } => {
+        CollisionEvent::Ended {
+          a: a.clone(),
+          b: b.clone(),
+        }
+      },
+    }
+  }
+}
+
+/**
+ * A simulated entity passing into or out of a sensor volume
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeEvent {
+  Entered {
+    volume: SynchronizedAspect,
+    subject: SynchronizedAspect,
+  },
+  Left {
+    volume: SynchronizedAspect,
+    subject: SynchronizedAspect,
+  },
+}
+
+/**
+ * Tells which pairs of bodies started and stopped something between ticks, given every pair
+ * doing it on each tick
+ *
+ * Pairs are keyed by the two things they're between, and keep whatever was known about them when
+ * they started, so an ending can still be told after one of the bodies is gone.
+ */
+pub struct PairTracker<K, T> {
+  current: HashMap<(K, K), T>,
+}
+
+impl<K: Hash + Eq, T: Clone> PairTracker<K, T> {
+  pub fn new() -> PairTracker<K, T> {
+    PairTracker { current: HashMap::new() }
+  }
+
+  /**
+   * Takes the pairs as of this tick, and gives those that have started and those that have ended
+   * since the last
+   */
+  pub fn update(&mut self, mut pairs: HashMap<(K, K), T>) -> (Vec<T>, Vec<T>) {
+    let started = pairs.iter()
+      .filter(|&(key, _)| !self.current.contains_key(key))
+      .map(|(_, value)| value.clone())
+      .collect::<Vec<T>>();
+    let ended = self.current
+      .iter()
+      .filter(|&(key, _)| !pairs.contains_key(key))
+      .map(|(_, value)| value.clone())
+      .collect::<Vec<T>>();
+
+    // Ongoing pairs keep what they started with
+    for (key, value) in self.current.drain() {
+      if pairs.contains_key(&key) {
+        pairs.insert(key, value);
+      }
+    }
+    self.current = pairs;
+
+    (started, ended)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::PairTracker;
+  use std::collections::HashMap;
+
+  fn pairs(entries: Vec<((usize, usize), &'static str)>) -> HashMap<(usize, usize), &'static str> {
+    entries.into_iter().collect()
+  }
+
+  #[test]
+  fn pairs_start_once_and_end_with_what_they_started_with() {
+    let mut tracker = PairTracker::new();
+
+    assert_eq!(tracker.update(pairs(vec![((1, 2), "first")])), (vec!["first"], vec![]));
+    assert_eq!(tracker.update(pairs(vec![((1, 2), "again"), ((2, 3), "second")])),
+               (vec!["second"], vec![]));
+    assert_eq!(tracker.update(pairs(vec![((2, 3), "second")])), (vec![], vec!["first"]));
+    assert_eq!(tracker.update(HashMap::new()), (vec![], vec!["second"]));
+  }
+}
@@ -8,27 +8,71 @@ extern crate common;
 extern crate aspects;
 extern crate server_state as state;
 extern crate pubsub;
+#[cfg(test)]
+extern crate time;
 
-use common::aspects::{DisabledAspect, PhysicalAspect};
-use common::model::ModelType;
+mod events;
+
+pub use events::{CollisionEvent, VolumeEvent};
+use events::PairTracker;
+
+use common::aspects::{DisabledAspect, PhysicalAspect, SynchronizedAspect};
+use common::space;
 use state::Delta;
-use aspects::CollisionAspect;
-use ncollide::shape::{Ball, Plane, Cuboid};
+use aspects::{BodyKind, CollisionAspect, Shape};
+use ncollide::shape::{Ball, Capsule, Compound, Convex, Cuboid, Plane, ShapeHandle};
 use nalgebra::Translation;
 use nalgebra::Rotation;
 use nphysics3d::world::World;
-use nphysics3d::object::{RigidBody, RigidBodyHandle};
-use nphysics3d::math::Vector;
+use nphysics3d::object::{RigidBody, RigidBodyHandle, Sensor, SensorHandle, WorldObject};
+use nphysics3d::math::{Matrix, Point, Vector};
+use pubsub::{PubSubStore, Publisher};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 
 /**
  * Simulates the world.
  *
- * Inputs: Physicals, Collisions
- * Outputs: Physicals
+ * Each simulated entity keeps the same rigid body from tick to tick, so contacts, sleeping and
+ * solver warm-starting carry over. Bodies are added as entities gain a collision and removed as
+ * they lose it or are disabled, and rebuilt when it changes. Changes made to a PhysicalAspect by
+ * other systems are pushed into its body, and a body's state is only written back when it moved.
+ *
+ * Static and kinematic entities get immovable bodies. Kinematic ones are carried along by their
+ * own velocity here, and push dynamic bodies aside without being pushed back. Their bodies have no
+ * velocity in the simulation though, so what rests on them isn't carried along by friction. Sensor
+ * entities get volumes that nothing collides with.
+ *
+ * Synchronized entities coming into and out of contact, or into and out of volumes, are published
+ * as they happen.
+ *
+ * Inputs: Physicals, Collisions, Disableds, Synchronizeds
+ * Outputs: Physicals, CollisionEvent, VolumeEvent
  */
 pub struct System {
   world: World<f32>,
+  bodies: HashMap<specs::Entity, TrackedBody>,
+  contacts: PairTracker<specs::Entity, CollisionEvent>,
+  occupants: PairTracker<specs::Entity, (SynchronizedAspect, SynchronizedAspect)>,
+}
+
+/**
+ * Where an entity is in the physics world
+ */
+enum Handle {
+  Body(RigidBodyHandle<f32>),
+  Volume(SensorHandle<f32>),
+}
+
+/**
+ * A rigid body or sensor volume standing in for an entity
+ */
+struct TrackedBody {
+  handle: Handle,
+  collision: CollisionAspect,
+  /// The state last written to (or read from) the entity, to tell when someone else changed it
+  synced: PhysicalAspect,
 }
 
 // Lets us make physics sync
@@ -56,22 +100,324 @@ impl DerefMut for InternalWorld {
   }
 }
 
+/// Builds the shape in physics space, and hands it to the expression under the given name
+macro_rules! with_shape {
+  ($shape:expr, $built:ident => $use_shape:expr) => {
+    match *$shape {
+      Shape::Box { half_extents } => {
+        let $built = cuboid(half_extents);
+        $use_shape
+      },
+      Shape::Sphere { radius } => {
+        let $built = Ball::new(radius);
+        $use_shape
+      },
+      // Capsules stand along y in physics space, which is z in game space
+      Shape::Capsule { half_height, radius } => {
+        let $built = Capsule::new(half_height, radius);
+        $use_shape
+      },
+      Shape::ConvexHull { ref points } => {
+        let $built = convex(points);
+        $use_shape
+      },
+      Shape::Compound { ref parts } => {
+        let $built = compound(parts);
+        $use_shape
+      },
+    }
+  }
+}
+
 impl System {
-  pub fn new(_: &mut specs::World) -> System {
+  /**
+   * Builds the simulation, with gravity given in world coordinates (z is up)
+   */
+  pub fn new(world: &mut specs::World, gravity: (f32, f32, f32)) -> System {
+    // Gameplay systems may not be listening yet
+    world.register_publisher::<CollisionEvent>();
+    world.register_publisher::<VolumeEvent>();
+
     // Configure world
-    let mut world = World::new();
-    world.set_gravity(Vector::new(0.0, -0.981, 0.0));
+    let mut sim_world = World::new();
+    sim_world.set_gravity(to_physics(gravity));
 
     // Add base plane
-    let plane_geometry = Plane::new(Vector::new(0.0, 1.0, 0.0));
+    let plane_geometry = Plane::new(to_physics((0.0, 0.0, 1.0)));
     let plane = RigidBody::new_static(plane_geometry, 0.3, 0.6);
 
-    world.add_rigid_body(plane);
+    sim_world.add_rigid_body(plane);
 
     System {
-      world: world
+      world: sim_world,
+      bodies: HashMap::new(),
+      contacts: PairTracker::new(),
+      occupants: PairTracker::new(),
+    }
+  }
+
+  fn add_body(&mut self, physical: &PhysicalAspect, collision: &CollisionAspect) -> TrackedBody {
+    let handle = if collision.kind == BodyKind::Sensor {
+      let mut volume = with_shape!(&collision.shape, shape => Sensor::new(shape, None));
+      volume.enable_interfering_bodies_collection();
+      volume.set_relative_position(placement(physical));
+      Handle::Volume(self.world.add_sensor(volume))
+    } else {
+      let mut body = build_body(collision);
+      write_to_body(physical, collision.kind, &mut body);
+      Handle::Body(self.world.add_rigid_body(body))
+    };
+
+    TrackedBody {
+      handle: handle,
+      collision: collision.clone(),
+      synced: physical.clone(),
     }
   }
+
+  fn remove_body(&mut self, tracked: &TrackedBody) {
+    match tracked.handle {
+      Handle::Body(ref body) => self.world.remove_rigid_body(body),
+      Handle::Volume(ref volume) => self.world.remove_sensor(volume),
+    }
+  }
+
+  /**
+   * Works out which synchronized entities are touching, and which are in which volumes, and
+   * publishes what's changed since the last tick
+   *
+   * Impulses are estimated from the velocities the bodies had going into the step, as by the end
+   * of it the collision has already been resolved. Pairs are told apart by the entities in them.
+   */
+  fn publish_events(&mut self,
+                    owners: &HashMap<specs::Entity, SynchronizedAspect>,
+                    collision_events: &mut Publisher<CollisionEvent>,
+                    volume_events: &mut Publisher<VolumeEvent>) {
+    // Bodies are only known by their handles in the physics world, so they're found by where
+    // those point, which holds for as long as the bodies are there
+    let entities = self.bodies
+      .iter()
+      .map(|(entity, tracked)| {
+        let address = match tracked.handle {
+          Handle::Body(ref body) => body_address(body),
+          Handle::Volume(ref volume) => volume_address(volume),
+        };
+        (address, entity.clone())
+      })
+      .collect::<HashMap<usize, specs::Entity>>();
+
+    let mut raw_contacts = Vec::new();
+    self.world.contacts(|a, b, contact| {
+      if let (&WorldObject::RigidBody(ref a), &WorldObject::RigidBody(ref b)) = (a, b) {
+        raw_contacts.push((body_address(a),
+                           body_address(b),
+                           reduced_mass(&a.borrow(), &b.borrow()),
+                           contact.world1,
+                           contact.world2,
+                           contact.normal));
+      }
+    });
+
+    let mut touching = HashMap::new();
+    raw_contacts.into_iter().foreach(|(a_address, b_address, mass, a_point, b_point, normal)| {
+      let (a_entity, b_entity) = match (entities.get(&a_address), entities.get(&b_address)) {
+        (Some(a_entity), Some(b_entity)) => (a_entity.clone(), b_entity.clone()),
+        _ => return,
+      };
+      let (a, b) = match (owners.get(&a_entity), owners.get(&b_entity)) {
+        (Some(a), Some(b)) => (a.clone(), b.clone()),
+        _ => return,
+      };
+      let pair = if a_entity.get_id() < b_entity.get_id() {
+        (a_entity, b_entity)
+      } else {
+        (b_entity, a_entity)
+      };
+      if touching.contains_key(&pair) {
+        return;
+      }
+
+      // The bodies' own velocities are already resolved, so the ones they went into the step with
+      // are used instead
+      let a_vel = self.bodies[&a_entity].synced.vel;
+      let b_vel = self.bodies[&b_entity].synced.vel;
+      let normal = from_physics(&normal);
+      let closing_speed = (b_vel.0 - a_vel.0) * normal.0 + (b_vel.1 - a_vel.1) * normal.1 +
+                          (b_vel.2 - a_vel.2) * normal.2;
+      let strength = mass * closing_speed.abs();
+      let point = space::vector_from_physics(((a_point.x + b_point.x) / 2.0,
+                                              (a_point.y + b_point.y) / 2.0,
+                                              (a_point.z + b_point.z) / 2.0));
+
+      touching.insert(pair,
+                      CollisionEvent::Started {
+                        a: a,
+                        b: b,
+                        point: point,
+                        // The normal points from a to b, and b pushes back the other way
+                        estimated_impulse: (-normal.0 * strength,
+                                            -normal.1 * strength,
+                                            -normal.2 * strength),
+                      });
+    });
+
+    let (started, ended) = self.contacts.update(touching);
+    started.into_iter().foreach(|event| collision_events.push(event));
+    ended.into_iter().foreach(|event| collision_events.push(event.ended()));
+
+    let mut occupying = HashMap::new();
+    self.bodies.iter().foreach(|(volume_entity, tracked)| {
+      if let Handle::Volume(ref volume) = tracked.handle {
+        let volume_synchro = match owners.get(volume_entity) {
+          Some(synchro) => synchro,
+          None => return,
+        };
+        volume.borrow().interfering_bodies().map(|bodies| {
+          bodies.iter().foreach(|body| {
+            let subject_entity = match entities.get(&body_address(body)) {
+              Some(subject_entity) => subject_entity,
+              None => return,
+            };
+            owners.get(subject_entity).map(|subject| {
+              occupying.insert((volume_entity.clone(), subject_entity.clone()),
+                               (volume_synchro.clone(), subject.clone()))
+            });
+          });
+        });
+      }
+    });
+
+    let (entered, left) = self.occupants.update(occupying);
+    entered.into_iter().foreach(|(volume, subject)| {
+      volume_events.push(VolumeEvent::Entered {
+        volume: volume,
+        subject: subject,
+      })
+    });
+    left.into_iter().foreach(|(volume, subject)| {
+      volume_events.push(VolumeEvent::Left {
+        volume: volume,
+        subject: subject,
+      })
+    });
+  }
+}
+
+/**
+ * Builds a body of the collision's kind and material
+ */
+fn build_body(collision: &CollisionAspect) -> RigidBody<f32> {
+  let material = &collision.material;
+  with_shape!(&collision.shape, shape => {
+    if collision.kind == BodyKind::Dynamic {
+      RigidBody::new_dynamic(shape, material.density, material.restitution, material.friction)
+    } else {
+      RigidBody::new_static(shape, material.restitution, material.friction)
+    }
+  })
+}
+
+fn shape_handle(shape: &Shape) -> ShapeHandle<Point<f32>, Matrix<f32>> {
+  with_shape!(shape, built => ShapeHandle::new(built))
+}
+
+fn cuboid(half_extents: (f32, f32, f32)) -> Cuboid<Vector<f32>> {
+  // Sizes have no direction, so y and z only trade places
+  Cuboid::new(Vector::new(half_extents.0, half_extents.2, half_extents.1))
+}
+
+fn convex(points: &Vec<(f32, f32, f32)>) -> Convex<Point<f32>> {
+  Convex::new(points.iter()
+    .map(|&point| {
+      let (x, y, z) = space::vector_to_physics(point);
+      Point::new(x, y, z)
+    })
+    .collect())
+}
+
+fn compound(parts: &Vec<((f32, f32, f32), Shape)>) -> Compound<Point<f32>, Matrix<f32>> {
+  Compound::new(parts.iter()
+    .map(|&(offset, ref shape)| {
+      (Matrix::new(to_physics(offset), Vector::new(0.0, 0.0, 0.0)), shape_handle(shape))
+    })
+    .collect())
+}
+
+/**
+ * Moves a game space vector into the physics world (y is up)
+ */
+fn to_physics(v: (f32, f32, f32)) -> Vector<f32> {
+  let (x, y, z) = space::vector_to_physics(v);
+  Vector::new(x, y, z)
+}
+
+/**
+ * Moves a physics world vector back into game space
+ */
+fn from_physics(v: &Vector<f32>) -> (f32, f32, f32) {
+  space::vector_from_physics((v.x, v.y, v.z))
+}
+
+/**
+ * Where the aspect puts something, in physics space
+ */
+fn placement(physical: &PhysicalAspect) -> Matrix<f32> {
+  Matrix::new(to_physics(physical.pos),
+              to_physics(space::to_rotation_vector(physical.ang)))
+}
+
+/**
+ * Where a body is held, which tells it apart from the others for as long as it's in the world
+ */
+fn body_address(body: &RigidBodyHandle<f32>) -> usize {
+  &**body as *const RefCell<RigidBody<f32>> as usize
+}
+
+fn volume_address(volume: &SensorHandle<f32>) -> usize {
+  &**volume as *const RefCell<Sensor<f32>> as usize
+}
+
+/**
+ * The mass that resists two bodies coming together, where immovable bodies resist completely
+ */
+fn reduced_mass(a: &RigidBody<f32>, b: &RigidBody<f32>) -> f32 {
+  match (a.mass(), b.mass()) {
+    (Some(a), Some(b)) => a * b / (a + b),
+    (Some(mass), None) | (None, Some(mass)) => mass,
+    (None, None) => 0.0,
+  }
+}
+
+/**
+ * Moves a body to where the aspect says it is
+ *
+ * Bodies hold their orientation as a rotation vector, which moves between spaces like any other.
+ * Only dynamic bodies are given velocities, as the rest can't be moved by the simulation.
+ */
+fn write_to_body(physical: &PhysicalAspect, kind: BodyKind, body: &mut RigidBody<f32>) {
+  body.set_rotation(to_physics(space::to_rotation_vector(physical.ang)));
+  body.set_translation(to_physics(physical.pos));
+  if kind == BodyKind::Dynamic {
+    body.set_lin_vel(to_physics(physical.vel));
+    body.set_ang_vel(to_physics(physical.ang_vel));
+  }
+
+  // A sleeping body won't notice it was moved until it's woken
+  if let Some(threshold) = body.deactivation_threshold() {
+    body.activate(threshold);
+  }
+}
+
+/**
+ * Reads where the body ended up into a copy of the aspect
+ */
+fn read_from_body(body: &RigidBody<f32>, physical: &PhysicalAspect) -> PhysicalAspect {
+  let mut aspect = physical.clone();
+  aspect.pos = from_physics(&body.position().translation());
+  aspect.vel = from_physics(&body.lin_vel());
+  aspect.ang = space::from_rotation_vector(from_physics(&body.position().rotation()));
+  aspect.ang_vel = from_physics(&body.ang_vel());
+  aspect
 }
 
 impl specs::System<Delta> for System {
@@ -80,50 +426,220 @@ impl specs::System<Delta> for System {
     use itertools::Itertools;
     use std::ops::Not;
 
-    let (mut physicals, collisions, disabled) =
-      arg.fetch(|w| (w.write::<PhysicalAspect>(), w.read::<CollisionAspect>(), w.read::<DisabledAspect>()));
+    let (entities,
+         mut physicals,
+         collisions,
+         disabled,
+         synchronized,
+         mut collision_events,
+         mut volume_events) = arg.fetch(|w| {
+      (w.entities(),
+       w.write::<PhysicalAspect>(),
+       w.read::<CollisionAspect>(),
+       w.read::<DisabledAspect>(),
+       w.read::<SynchronizedAspect>(),
+       w.fetch_publisher::<CollisionEvent>(),
+       w.fetch_publisher::<VolumeEvent>())
+    });
+
+    let dt_s = delta.dt_s();
+
+    // Add bodies for newly simulated entities, and push outside changes into existing ones
+    let mut simulated = HashSet::new();
+    (&entities, &mut physicals, &collisions, disabled.not()).iter().foreach(|(entity,
+                                                                              physical,
+                                                                              collision,
+                                                                              _)| {
+      simulated.insert(entity);
+
+      if collision.kind == BodyKind::Kinematic {
+        physical.pos = (physical.pos.0 + physical.vel.0 * dt_s,
+                        physical.pos.1 + physical.vel.1 * dt_s,
+                        physical.pos.2 + physical.vel.2 * dt_s);
+        physical.ang = space::integrate(physical.ang, physical.ang_vel, dt_s);
+      }
+
+      let is_rebuilt = self.bodies.get(&entity).map(|tracked| tracked.collision != *collision);
+      match is_rebuilt {
+        Some(false) => {
+          let tracked = self.bodies.get_mut(&entity).unwrap();
+          if tracked.synced != *physical {
+            match tracked.handle {
+              Handle::Body(ref body) => {
+                write_to_body(physical, collision.kind, &mut body.borrow_mut())
+              },
+              Handle::Volume(ref volume) => {
+                volume.borrow_mut().set_relative_position(placement(physical))
+              },
+            }
+            tracked.synced = physical.clone();
+          }
+        },
+        Some(true) => {
+          let old = self.bodies.remove(&entity).unwrap();
+          self.remove_body(&old);
+          let tracked = self.add_body(physical, collision);
+          self.bodies.insert(entity, tracked);
+        },
+        None => {
+          let tracked = self.add_body(physical, collision);
+          self.bodies.insert(entity, tracked);
+        },
+      }
+    });
 
-    let dt_s = (delta.dt.num_milliseconds() as f32) / 1000.0;
-    let sim_objects = (&mut physicals, &collisions, disabled.not())
+    // Drop bodies for entities that were deleted, disabled or lost their collision
+    let departed = self.bodies
+      .keys()
+      .filter(|entity| !simulated.contains(*entity))
+      .cloned()
+      .collect::<Vec<specs::Entity>>();
+    departed.iter().foreach(|entity| {
+      self.bodies.remove(entity).map(|tracked| self.remove_body(&tracked));
+    });
+
+    self.world.step(dt_s);
+
+    let owners = (&entities, &synchronized)
       .iter()
-      .map(|(physical, collision, _)| {
+      .filter(|&(entity, _)| self.bodies.contains_key(&entity))
+      .map(|(entity, synchro)| (entity, synchro.clone()))
+      .collect::<HashMap<specs::Entity, SynchronizedAspect>>();
+    self.publish_events(&owners, &mut collision_events, &mut volume_events);
 
-        let mut entity = match collision.model  {
-          ModelType::Cube => RigidBody::new_dynamic(Cuboid::new(Vector::new(1.0, 1.0, 1.0)), 1.0, 0.3, 0.6),
-          ModelType::Icosphere0 | ModelType::Icosphere1 | ModelType::Icosphere2 | ModelType::Icosphere3 => RigidBody::new_dynamic(Ball::new(1.0), 1.0, 0.3, 0.6),
+    // Write back only the dynamic bodies that moved, leaving sleeping ones alone
+    (&entities, &mut physicals).iter().foreach(|(entity, physical)| {
+      if let Some(tracked) = self.bodies.get_mut(&entity) {
+        let body = match tracked.handle {
+          Handle::Body(ref body) if tracked.collision.kind == BodyKind::Dynamic => body,
+          _ => return,
         };
 
-        entity.append_rotation(&Vector::new(physical.ang.0, physical.ang.1, physical.ang.2));
-        entity.append_translation(&Vector::new(physical.pos.0, physical.pos.2, physical.pos.1));
-        entity.set_lin_vel(Vector::new(physical.vel.0, physical.vel.2, physical.vel.1));
-        entity.set_ang_vel(Vector::new(physical.ang_vel.0, physical.ang_vel.1, physical.ang_vel.2));
+        let stepped = read_from_body(&body.borrow(), physical);
+        if stepped != *physical {
+          *physical = stepped.clone();
+        }
+        tracked.synced = stepped;
+      }
+    });
+  }
+}
 
-        let handle = self.world.add_rigid_body(entity);
-        (physical, handle)
-      })
-      .collect::<Vec<(&mut PhysicalAspect, RigidBodyHandle<f32>)>>();
+#[cfg(test)]
+mod test {
+  use super::{CollisionEvent, Handle, System, build_body, cuboid};
+  use aspects::{BodyKind, CollisionAspect, Material, Shape};
+  use common::aspects::{DisabledAspect, PhysicalAspect, SynchronizedAspect};
+  use pubsub::PubSubStore;
+  use specs;
+  use state::Delta;
+  use std::f32::consts::PI;
+  use time;
+
+  fn sphere() -> Shape {
+    Shape::Sphere { radius: 1.0 }
+  }
 
-      self.world.step(dt_s);
+  #[test]
+  fn boxes_trade_y_and_z_on_the_way_into_physics_space() {
+    let built = cuboid((1.0, 2.0, 3.0));
+    let half_extents = built.half_extents();
 
-      sim_objects.into_iter().foreach(|(aspect, handle)| {
-        aspect.vel.0 = handle.borrow().lin_vel().translation().x;
-        aspect.vel.1 = handle.borrow().lin_vel().translation().z;
-        aspect.vel.2 = handle.borrow().lin_vel().translation().y;
+    assert_eq!((half_extents.x, half_extents.y, half_extents.z), (1.0, 3.0, 2.0));
+  }
+
+  #[test]
+  fn dynamic_bodies_are_as_heavy_as_their_shape_and_material_make_them() {
+    let mut material = Material::new();
+    material.density = 2.0;
+    material.friction = 0.25;
+    material.restitution = 0.5;
+    let ball_mass = 2.0 * 4.0 / 3.0 * PI;
+
+    let ball = CollisionAspect::new_with(sphere(), material.clone(), BodyKind::Dynamic).unwrap();
+    let body = build_body(&ball);
+    assert!((body.mass().unwrap() - ball_mass).abs() < 0.001);
+    assert_eq!((body.friction(), body.restitution()), (0.25, 0.5));
+
+    let pair = Shape::Compound {
+      parts: vec![((-2.0, 0.0, 0.0), sphere()), ((2.0, 0.0, 0.0), sphere())],
+    };
+    let body = build_body(&CollisionAspect::new_with(pair, material, BodyKind::Dynamic).unwrap());
+    assert!((body.mass().unwrap() - 2.0 * ball_mass).abs() < 0.01);
+  }
+
+  #[test]
+  fn only_dynamic_bodies_can_be_moved_and_sensors_become_volumes() {
+    for kind in vec![BodyKind::Static, BodyKind::Kinematic].into_iter() {
+      let collision = CollisionAspect::new_with(sphere(), Material::new(), kind).unwrap();
+      assert_eq!(build_body(&collision).mass(), None);
+    }
 
-        aspect.pos.0 = handle.borrow().position().translation().x;
-        aspect.pos.1 = handle.borrow().position().translation().z;
-        aspect.pos.2 = handle.borrow().position().translation().y;
+    let mut system = System::new(&mut specs::World::new(), (0.0, 0.0, -9.8));
+    let physical = PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0));
+    let sensor = CollisionAspect::new_with(sphere(), Material::new(), BodyKind::Sensor).unwrap();
+    match system.add_body(&physical, &sensor).handle {
+      Handle::Volume(_) => (),
+      Handle::Body(_) => panic!("Sensor was given a body"),
+    }
+    match system.add_body(&physical, &CollisionAspect::new()).handle {
+      Handle::Body(_) => (),
+      Handle::Volume(_) => panic!("Crate was given a volume"),
+    }
+  }
 
-        aspect.ang_vel.0 = handle.borrow().ang_vel().x;
-        aspect.ang_vel.1 = handle.borrow().ang_vel().y;
-        aspect.ang_vel.2 = handle.borrow().ang_vel().z;
+  fn step(planner: &mut specs::Planner<Delta>, tick: u64) {
+    planner.dispatch(Delta {
+      dt: time::Duration::milliseconds(16),
+      now: time::now(),
+      tick: tick,
+      first_of_frame: true,
+      last_of_frame: true,
+    });
+    planner.wait();
+  }
 
-        aspect.ang.0 = handle.borrow().position().rotation().x;
-        aspect.ang.1 = handle.borrow().position().rotation().y;
-        aspect.ang.2 = handle.borrow().position().rotation().z;
-        /**/
-        self.world.remove_rigid_body(&handle);
-      });
+  #[test]
+  fn entities_coming_into_and_out_of_contact_are_published() {
+    let mut world = specs::World::new();
+    world.register::<PhysicalAspect>();
+    world.register::<CollisionAspect>();
+    world.register::<DisabledAspect>();
+    world.register::<SynchronizedAspect>();
+    // Without gravity, nothing but the crates themselves moves them
+    let system = System::new(&mut world, (0.0, 0.0, 0.0));
+    let collisions = world.register_subscriber::<CollisionEvent>();
+
+    // Crates two units across, overlapping by half a unit
+    let (a, b) = (SynchronizedAspect::new(), SynchronizedAspect::new());
+    world.create_now()
+      .with(a.clone())
+      .with(PhysicalAspect::new((0.0, 0.0, 5.0), (0.0, 0.0, 0.0)))
+      .with(CollisionAspect::new())
+      .build();
+    let b_entity = world.create_now()
+      .with(b.clone())
+      .with(PhysicalAspect::new((1.5, 0.0, 5.0), (0.0, 0.0, 0.0)))
+      .with(CollisionAspect::new())
+      .build();
+    let mut planner = specs::Planner::new(world, 1);
+    planner.add_system(system, "physics", 1);
+
+    step(&mut planner, 1);
+    let started = planner.mut_world().fetch_subscriber(&collisions).collected();
+    assert_eq!(started.len(), 1);
+    match started[0] {
+      CollisionEvent::Started { a: ref first, b: ref second, .. } => {
+        assert!((first, second) == (&a, &b) || (first, second) == (&b, &a))
+      },
+      CollisionEvent::Ended { .. } => panic!("Touch ended before it started"),
+    }
 
+    planner.mut_world()
+      .write::<PhysicalAspect>()
+      .insert(b_entity, PhysicalAspect::new((20.0, 0.0, 5.0), (0.0, 0.0, 0.0)));
+    step(&mut planner, 2);
+    let ended = planner.mut_world().fetch_subscriber(&collisions).collected();
+    assert_eq!(ended, vec![started[0].ended()]);
   }
 }
@@ -2,6 +2,30 @@ extern crate time;
 
 #[derive(Debug, Clone)]
 pub struct Delta {
+  /// The fixed simulation step, however long the tick actually took
   pub dt: time::Duration,
   pub now: time::Tm,
+  /// Counts up by one every tick, starting from 1
+  pub tick: u64,
+  /// Whether this is the first tick of its frame, when what the last frame produced is sent
+  pub first_of_frame: bool,
+  /// Whether this is the last tick of its frame, when snapshots are taken
+  pub last_of_frame: bool,
+}
+
+impl Delta {
+  /// The simulation step, in seconds
+  pub fn dt_s(&self) -> f32 {
+    self.dt.num_nanoseconds().unwrap_or(0) as f32 / 1000000000.0
+  }
+}
+
+/**
+ * Whether the server is carrying on, or on its way down
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunState {
+  Running,
+  /// Turning away new players, with the reason given to everyone
+  ShuttingDown(String),
 }
@@ -0,0 +1,383 @@
+use std::fs::File;
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+
+use toml;
+
+use common::codec::CodecKind;
+use common::network::LinkConditions;
+use player::PermissionConfig;
+
+/**
+ * Everything about a server that can be changed without recompiling
+ *
+ * Loaded from a TOML file like the one below (every value is optional, and these are the
+ * defaults), then overridden from the command line.
+ *
+ * ```toml
+ * [network]
+ * bind_address = "0.0.0.0"
+ * port = 7090
+ * codec = "binary"
+ * fragment_size = 128
+ * health_timeout_ms = 3000
+ * # Bytes of entity updates each client is sent per tick, before compression
+ * snapshot_budget = 8192
+ *
+ * [simulation]
+ * tick_rate = 66
+ * # Most ticks run back to back to catch up after a stall; any more are skipped
+ * max_catch_up = 5
+ * gravity = [0.0, 0.0, -0.981]
+ * planner_threads = 2
+ *
+ * # Players only hear about entities this close to the one they control
+ * [interest]
+ * radius = 100.0
+ *
+ * [permissions]
+ * default_role = "crew"
+ * # Players presenting this when they connect are admins; without it, nobody is
+ * # admin_secret = "..."
+ *
+ * # Simulates a bad connection to every client
+ * [conditions]
+ * latency_ms = 0
+ * jitter_ms = 0
+ * loss_percent = 0.0
+ * reorder_window = 0
+ * duplicate_percent = 0.0
+ * seed = 0
+ *
+ * [shutdown]
+ * grace_ms = 2000
+ * # Where to save the world on the way down, if anywhere
+ * # save_path = "world.json"
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+  pub bind_address: IpAddr,
+  pub port: u16,
+  pub codec: CodecKind,
+  /// Largest snapshot fragment payload, in bytes
+  pub fragment_size: usize,
+  /// How long a player may go unheard before they're disconnected
+  pub health_timeout_ms: u64,
+  /// Bytes of entity updates per client per tick, though their own entity is always sent
+  pub snapshot_budget: usize,
+  /// Ticks per second
+  pub tick_rate: u32,
+  /// Most ticks run back to back to catch up after a stall
+  pub max_catch_up_ticks: u32,
+  /// In world coordinates (z is up)
+  pub gravity: (f32, f32, f32),
+  pub planner_threads: usize,
+  /// In world units
+  pub interest_radius: f32,
+  pub permissions: PermissionConfig,
+  pub link_conditions: LinkConditions,
+  /// How long players are warned before a shutdown, and given to ack what's still unacked
+  pub shutdown_grace_ms: u64,
+  pub save_path: Option<String>,
+}
+
+impl ServerConfig {
+  pub fn new() -> ServerConfig {
+    ServerConfig {
+      bind_address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+      port: 7090,
+      codec: CodecKind::Binary,
+      fragment_size: 128,
+      health_timeout_ms: 3000,
+      snapshot_budget: 8192,
+      tick_rate: 66,
+      max_catch_up_ticks: 5,
+      gravity: (0.0, 0.0, -0.981),
+      planner_threads: 2,
+      interest_radius: 100.0,
+      permissions: PermissionConfig::new(),
+      link_conditions: LinkConditions::perfect(),
+      shutdown_grace_ms: 2000,
+      save_path: None,
+    }
+  }
+
+  pub fn load(path: &Path) -> Result<ServerConfig, String> {
+    let mut contents = String::new();
+    try!(File::open(path)
+      .and_then(|mut file| file.read_to_string(&mut contents))
+      .map_err(|err| err.to_string()));
+
+    let mut parser = toml::Parser::new(&contents);
+    match parser.parse() {
+      Some(table) => ServerConfig::from_toml(&toml::Value::Table(table)),
+      None => {
+        let errors = parser.errors.iter().map(|err| err.to_string()).collect::<Vec<_>>();
+        Err(errors.join(", "))
+      },
+    }
+  }
+
+  /**
+   * Reads a config, using the default for anything left out
+   */
+  pub fn from_toml(value: &toml::Value) -> Result<ServerConfig, String> {
+    let defaults = ServerConfig::new();
+
+    Ok(ServerConfig {
+      bind_address: try!(read(value, "network.bind_address", "an IP address", |v| {
+          v.as_str().and_then(|s| IpAddr::from_str(s).ok())
+        }))
+        .unwrap_or(defaults.bind_address),
+      port: try!(read(value, "network.port", "a port number", |v| {
+          v.as_integer().and_then(|i| bounded(i, 0, u16::max_value() as i64)).map(|i| i as u16)
+        }))
+        .unwrap_or(defaults.port),
+      codec: try!(read(value, "network.codec", "\"binary\" or \"json\"", |v| {
+          v.as_str().and_then(|s| CodecKind::from_str(s).ok())
+        }))
+        .unwrap_or(defaults.codec),
+      fragment_size: try!(read(value, "network.fragment_size", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, i64::max_value())).map(|i| i as usize)
+        }))
+        .unwrap_or(defaults.fragment_size),
+      health_timeout_ms: try!(read(value, "network.health_timeout_ms", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, i64::max_value())).map(|i| i as u64)
+        }))
+        .unwrap_or(defaults.health_timeout_ms),
+      snapshot_budget: try!(read(value, "network.snapshot_budget", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, i64::max_value())).map(|i| i as usize)
+        }))
+        .unwrap_or(defaults.snapshot_budget),
+      tick_rate: try!(read(value, "simulation.tick_rate", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, u32::max_value() as i64)).map(|i| i as u32)
+        }))
+        .unwrap_or(defaults.tick_rate),
+      max_catch_up_ticks: try!(read(value, "simulation.max_catch_up", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, u32::max_value() as i64)).map(|i| i as u32)
+        }))
+        .unwrap_or(defaults.max_catch_up_ticks),
+      gravity: try!(read(value, "simulation.gravity", "a list of three floats", |v| {
+          v.as_slice().and_then(|vals| {
+            let floats = vals.iter().filter_map(|v| v.as_float()).collect::<Vec<f64>>();
+            if floats.len() == 3 && vals.len() == 3 {
+              Some((floats[0] as f32, floats[1] as f32, floats[2] as f32))
+            } else {
+              None
+            }
+          })
+        }))
+        .unwrap_or(defaults.gravity),
+      planner_threads: try!(read(value, "simulation.planner_threads", "a positive integer", |v| {
+          v.as_integer().and_then(|i| bounded(i, 1, i64::max_value())).map(|i| i as usize)
+        }))
+        .unwrap_or(defaults.planner_threads),
+      interest_radius: try!(read(value, "interest.radius", "a positive number", |v| {
+          v.as_float()
+            .or(v.as_integer().map(|i| i as f64))
+            .and_then(|f| if f > 0.0 { Some(f as f32) } else { None })
+        }))
+        .unwrap_or(defaults.interest_radius),
+      permissions: try!(PermissionConfig::from_toml(value)),
+      link_conditions: try!(link_conditions_from_toml(value)),
+      shutdown_grace_ms: try!(read(value, "shutdown.grace_ms", "a whole number", |v| {
+          v.as_integer().and_then(|i| bounded(i, 0, i64::max_value())).map(|i| i as u64)
+        }))
+        .unwrap_or(defaults.shutdown_grace_ms),
+      save_path: try!(read(value, "shutdown.save_path", "a path", |v| {
+          v.as_str().map(|s| s.to_owned())
+        }))
+        .or(defaults.save_path),
+    })
+  }
+
+  /**
+   * Puts a config changed since it was read (say, from the command line) through the same checks
+   * as one read from a file
+   */
+  pub fn validate(&self) -> Result<(), String> {
+    ServerConfig::from_toml(&self.to_toml()).map(|_| ())
+  }
+
+  pub fn to_toml(&self) -> toml::Value {
+    let mut network = toml::Table::new();
+    network.insert("bind_address".to_owned(),
+                   toml::Value::String(self.bind_address.to_string()));
+    network.insert("port".to_owned(), toml::Value::Integer(self.port as i64));
+    network.insert("codec".to_owned(), toml::Value::String(self.codec.to_string()));
+    network.insert("fragment_size".to_owned(),
+                   toml::Value::Integer(self.fragment_size as i64));
+    network.insert("health_timeout_ms".to_owned(),
+                   toml::Value::Integer(self.health_timeout_ms as i64));
+    network.insert("snapshot_budget".to_owned(),
+                   toml::Value::Integer(self.snapshot_budget as i64));
+
+    let mut simulation = toml::Table::new();
+    simulation.insert("tick_rate".to_owned(), toml::Value::Integer(self.tick_rate as i64));
+    simulation.insert("max_catch_up".to_owned(),
+                      toml::Value::Integer(self.max_catch_up_ticks as i64));
+    simulation.insert("gravity".to_owned(),
+                      toml::Value::Array(vec![toml::Value::Float(self.gravity.0 as f64),
+                                              toml::Value::Float(self.gravity.1 as f64),
+                                              toml::Value::Float(self.gravity.2 as f64)]));
+    simulation.insert("planner_threads".to_owned(),
+                      toml::Value::Integer(self.planner_threads as i64));
+
+    let mut interest = toml::Table::new();
+    interest.insert("radius".to_owned(),
+                    toml::Value::Float(self.interest_radius as f64));
+
+    let mut shutdown = toml::Table::new();
+    shutdown.insert("grace_ms".to_owned(),
+                    toml::Value::Integer(self.shutdown_grace_ms as i64));
+    self.save_path
+      .as_ref()
+      .map(|path| shutdown.insert("save_path".to_owned(), toml::Value::String(path.clone())));
+
+    let mut root = toml::Table::new();
+    root.insert("network".to_owned(), toml::Value::Table(network));
+    root.insert("simulation".to_owned(), toml::Value::Table(simulation));
+    root.insert("interest".to_owned(), toml::Value::Table(interest));
+    root.insert("permissions".to_owned(), self.permissions.to_toml());
+    root.insert("conditions".to_owned(),
+                link_conditions_to_toml(&self.link_conditions));
+    root.insert("shutdown".to_owned(), toml::Value::Table(shutdown));
+    toml::Value::Table(root)
+  }
+
+  pub fn socket_address(&self) -> SocketAddr {
+    SocketAddr::new(self.bind_address, self.port)
+  }
+}
+
+fn link_conditions_from_toml(value: &toml::Value) -> Result<LinkConditions, String> {
+  let defaults = LinkConditions::perfect();
+  let millis = |v: &toml::Value| {
+    v.as_integer().and_then(|i| bounded(i, 0, u32::max_value() as i64)).map(|i| i as u32)
+  };
+  let percent = |v: &toml::Value| {
+    v.as_float()
+      .or(v.as_integer().map(|i| i as f64))
+      .and_then(|f| if f >= 0.0 && f <= 100.0 { Some(f as f32) } else { None })
+  };
+
+  Ok(LinkConditions {
+    latency_ms: try!(read(value, "conditions.latency_ms", "a whole number", &millis))
+      .unwrap_or(defaults.latency_ms),
+    jitter_ms: try!(read(value, "conditions.jitter_ms", "a whole number", &millis))
+      .unwrap_or(defaults.jitter_ms),
+    loss_percent: try!(read(value, "conditions.loss_percent", "a percentage", &percent))
+      .unwrap_or(defaults.loss_percent),
+    reorder_window: try!(read(value, "conditions.reorder_window", "a whole number", &millis))
+      .unwrap_or(defaults.reorder_window),
+    duplicate_percent: try!(read(value, "conditions.duplicate_percent", "a percentage", &percent))
+      .unwrap_or(defaults.duplicate_percent),
+    seed: try!(read(value, "conditions.seed", "a whole number", &millis)).unwrap_or(defaults.seed),
+  })
+}
+
+fn link_conditions_to_toml(conditions: &LinkConditions) -> toml::Value {
+  let mut table = toml::Table::new();
+  table.insert("latency_ms".to_owned(),
+               toml::Value::Integer(conditions.latency_ms as i64));
+  table.insert("jitter_ms".to_owned(),
+               toml::Value::Integer(conditions.jitter_ms as i64));
+  table.insert("loss_percent".to_owned(),
+               toml::Value::Float(conditions.loss_percent as f64));
+  table.insert("reorder_window".to_owned(),
+               toml::Value::Integer(conditions.reorder_window as i64));
+  table.insert("duplicate_percent".to_owned(),
+               toml::Value::Float(conditions.duplicate_percent as f64));
+  table.insert("seed".to_owned(), toml::Value::Integer(conditions.seed as i64));
+  toml::Value::Table(table)
+}
+
+/**
+ * Converts the value at the path, if there is one
+ */
+fn read<T, F>(value: &toml::Value,
+              path: &str,
+              expected: &str,
+              convert: F)
+              -> Result<Option<T>, String>
+  where F: Fn(&toml::Value) -> Option<T>
+{
+  match value.lookup(path) {
+    Some(found) => convert(found).map(Some).ok_or(format!("{} should be {}", path, expected)),
+    None => Ok(None),
+  }
+}
+
+fn bounded(value: i64, min: i64, max: i64) -> Option<i64> {
+  if value >= min && value <= max {
+    Some(value)
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use toml;
+
+  fn parse(contents: &str) -> Result<ServerConfig, String> {
+    let table = toml::Parser::new(contents).parse().unwrap();
+    ServerConfig::from_toml(&toml::Value::Table(table))
+  }
+
+  #[test]
+  fn printed_config_reads_back() {
+    let mut config = ServerConfig::new();
+    config.port = 8888;
+    config.gravity = (0.0, 0.0, -1.5);
+    config.link_conditions.loss_percent = 2.5;
+    config.save_path = Some("world.json".to_owned());
+    config.permissions.admin_secret = Some("hunter2".to_owned());
+
+    assert_eq!(parse(&config.to_toml().to_string()), Ok(config));
+  }
+
+  #[test]
+  fn missing_values_are_defaulted() {
+    let config = parse("[simulation]\ntick_rate = 30").unwrap();
+
+    assert_eq!(config.tick_rate, 30);
+    assert_eq!(config.port, ServerConfig::new().port);
+  }
+
+  #[test]
+  fn bad_values_are_rejected() {
+    assert!(parse("[network]\nport = 70000").is_err());
+    assert!(parse("[simulation]\ngravity = [0.0, -1.0]").is_err());
+    assert!(parse("[network]\ncodec = \"xml\"").is_err());
+    assert!(parse("[conditions]\nloss_percent = 150.0").is_err());
+    assert!(parse("[interest]\nradius = 0").is_err());
+    assert!(parse("[network]\nsnapshot_budget = 0").is_err());
+    assert!(parse("[simulation]\nmax_catch_up = 0").is_err());
+  }
+
+  #[test]
+  fn changed_values_are_validated() {
+    let mut config = ServerConfig::new();
+    assert_eq!(config.validate(), Ok(()));
+
+    config.fragment_size = 0;
+    assert!(config.validate().is_err());
+
+    config = ServerConfig::new();
+    config.tick_rate = 0;
+    assert!(config.validate().is_err());
+
+    config = ServerConfig::new();
+    config.planner_threads = 0;
+    assert!(config.validate().is_err());
+
+    config = ServerConfig::new();
+    config.link_conditions.duplicate_percent = 101.0;
+    assert!(config.validate().is_err());
+  }
+}
@@ -1,7 +1,13 @@
 use specs;
 
-use common::protocol::ClientPayload;
-use player::{ConnectEvent, HealthyEvent, InputEvent, SnapshotAckEvent};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use aspects::PlayerAspect;
+use common::network::now_ms;
+use common::protocol::{ClientPayload, ServerNetworkEvent, SessionToken};
+use network::OutboundEvent;
+use player::{ConnectEvent, HealthyEvent, InputEvent, KeepAliveEvent, SnapshotAckEvent};
 use pubsub::{PubSubStore, SubscriberToken};
 use state::Delta;
 
@@ -10,8 +16,13 @@ use itertools::Itertools;
 /**
  * Directs ClientPayloads to the individual event buses
  *
- * Inputs: ClientPayload
- * Outputs: ConnectEvent, SnapshotAckEvent, ClientEvent, HealthyEvent,
+ * Anything but a Connect must carry the session issued to a connected player, and come from that
+ * player's address. Everything else is dropped here, so downstream systems can trust the session
+ * on the events they receive.
+ *
+ * Inputs: ClientPayload, Players
+ * Outputs: ConnectEvent, SnapshotAckEvent, ClientEvent, HealthyEvent, KeepAliveEvent,
+ * OutboundEvent
  */
 pub struct System {
   client_payload_sub_token: SubscriberToken<ClientPayload>,
@@ -26,28 +37,69 @@ impl System {
 impl specs::System<Delta> for System {
   fn run(&mut self, arg: specs::RunArg, _: Delta) {
     use common::protocol::ClientNetworkEvent::*;
+    use specs::Join;
 
     let (mut inbound_events,
+         player,
          mut connect_events,
          mut snapshot_ack_events,
          mut input_events,
-         mut healthy_events) = arg.fetch(|w| {
+         mut healthy_events,
+         mut keep_alive_events,
+         mut outbound_events) = arg.fetch(|w| {
       (w.fetch_subscriber(&self.client_payload_sub_token).collected(),
+       w.read::<PlayerAspect>(),
        w.fetch_publisher::<ConnectEvent>(),
        w.fetch_publisher::<SnapshotAckEvent>(),
        w.fetch_publisher::<InputEvent>(),
-       w.fetch_publisher::<HealthyEvent>())
+       w.fetch_publisher::<HealthyEvent>(),
+       w.fetch_publisher::<KeepAliveEvent>(),
+       w.fetch_publisher::<OutboundEvent>())
     });
 
+    let session_addresses = player.iter()
+      .filter(|ply| ply.connected)
+      .map(|ply| (ply.session, ply.address.clone()))
+      .collect::<HashMap<SessionToken, SocketAddr>>();
+
     // Convert our single message type to several and ship em to different busses
     inbound_events.drain(..).foreach(|payload| {
-      healthy_events.push(HealthyEvent::new(payload.address.clone()));
-      match payload.event {
-        Connect => connect_events.push(ConnectEvent::Connect(payload.address)),
-        Disconnect => connect_events.push(ConnectEvent::Disconnect(payload.address)),
-        SnapshotAck(idx) => snapshot_ack_events.push(SnapshotAckEvent::new(payload.address, idx)),
-        DomainEvent(event) => input_events.push(InputEvent::new(payload.address, event)),
-        KeepAlive => (), // TODO: pingback svc
+      let address = payload.address;
+      let authenticated_session = match payload.session {
+        Some(session) if session_addresses.get(&session) == Some(&address) => Some(session),
+        _ => None,
+      };
+
+      if let Some(session) = authenticated_session {
+        healthy_events.push(HealthyEvent::new(session));
+      }
+
+      match (payload.event, authenticated_session) {
+        (Connect(request), _) => {
+          connect_events.push(ConnectEvent::Connect(address, payload.session, request))
+        },
+        (Disconnect, None) => {
+          outbound_events.push(OutboundEvent::Directed {
+            dest: address,
+            event: ServerNetworkEvent::Error("Tried to disconnect, but not connected to server"
+              .to_owned()),
+          })
+        },
+        (_, None) => (), // Not from a connected player
+        (Disconnect, Some(session)) => connect_events.push(ConnectEvent::Disconnect(session)),
+        (SnapshotAck(idx), Some(session)) => {
+          snapshot_ack_events.push(SnapshotAckEvent::new(session, idx))
+        },
+        (DomainEvent(event), Some(session)) => input_events.push(InputEvent::new(session, event)),
+        (KeepAlive { client_ms, echo }, Some(session)) => {
+          keep_alive_events.push(KeepAliveEvent {
+            session: session,
+            address: address,
+            client_ms: client_ms,
+            echo: echo,
+            received_ms: now_ms(),
+          })
+        },
       }
     });
   }
@@ -1,19 +1,27 @@
 pub mod io;
+pub mod timestep;
 
 use time;
 
+use config::ServerConfig;
 use world::ServerWorld;
 
 use specs;
 
-use state::Delta;
+use common::network::{ConditionedTransport, Transport, UdpTransport};
+use common::protocol::ServerNetworkEvent;
+
+use pubsub::PubSubStore;
+use state::{Delta, RunState};
 use physics::System as PhysicsSystem;
-use network::AdapterSystem;
-use player::{ConnectionSystem, HealthCheckSystem, InputSystem, SnapshotSystem};
+use network::{AdapterSystem, Network, OutboundEvent, UnackedEvents};
+use player::{ConnectionSystem, HealthCheckSystem, InputSystem, PingSystem, PlayerLinkStats,
+             SnapshotStats, SnapshotSystem};
 
 const NETWORK_IO_PRIORITY: specs::Priority = 100;
 const NETWORK_EVENT_DISTRIBUTION_PRIORITY: specs::Priority = 80;
 const NETWORK_HEALTH_CHECK_PRIORITY: specs::Priority = 70;
+const NETWORK_PING_PRIORITY: specs::Priority = 60;
 const PHYSICS_PRIORITY: specs::Priority = 9;
 const PLAYER_CONNECTION_PRIORITY: specs::Priority = 8;
 const PLAYER_SNAPSHOT_PRIORITY: specs::Priority = 6;
@@ -21,22 +29,47 @@ const PLAYER_INPUT_PRIORITY: specs::Priority = 5;
 
 pub struct Engine {
   pub planner: specs::Planner<Delta>,
+  /// The number of the latest tick run
+  tick: u64,
 }
 
 
 impl Engine {
-  pub fn new(port: u16) -> Engine {
+  pub fn new(config: &ServerConfig) -> Engine {
+    let transport = UdpTransport::bind(config.socket_address()).unwrap();
+    if config.link_conditions.is_perfect() {
+      Engine::with_transport(config, Box::new(transport))
+    } else {
+      let conditioned = ConditionedTransport::new(Box::new(transport),
+                                                  config.link_conditions.clone());
+      Engine::with_transport(config, Box::new(conditioned))
+    }
+  }
+
+  /**
+   * Builds an engine that reaches clients over the given transport instead of the config's address
+   */
+  pub fn with_transport(config: &ServerConfig, transport: Box<Transport>) -> Engine {
     let mut world = ServerWorld::new().world;
+    world.add_resource::<RunState>(RunState::Running);
+
+    let health_timeout = time::Duration::milliseconds(config.health_timeout_ms as i64);
 
-    let network_adapter_system = AdapterSystem::new(port, &mut world);
+    let network = Network::with_transport(transport, config.codec);
+    let network_adapter_system = AdapterSystem::new(network, &mut world);
     let event_distribution_system = io::event_distribution::System::new(&mut world);
-    let health_check_system = HealthCheckSystem::new(&mut world);
-    let physics_system = PhysicsSystem::new(&mut world);
-    let connection_system = ConnectionSystem::new(&mut world);
-    let snapshot_system = SnapshotSystem::new(&mut world);
+    let health_check_system = HealthCheckSystem::new(&mut world, health_timeout);
+    let ping_system = PingSystem::new(&mut world);
+    let physics_system = PhysicsSystem::new(&mut world, config.gravity);
+    let connection_system = ConnectionSystem::new(&mut world, config.permissions.clone());
+    let snapshot_system = SnapshotSystem::new(&mut world,
+                                              config.fragment_size,
+                                              config.codec,
+                                              config.interest_radius,
+                                              config.snapshot_budget);
     let player_input_system = InputSystem::new(&mut world);
 
-    let mut planner = specs::Planner::new(world, 2 /* Threads, arbitrary */);
+    let mut planner = specs::Planner::new(world, config.planner_threads);
     planner.add_system(network_adapter_system, "network::io", NETWORK_IO_PRIORITY);
     planner.add_system(event_distribution_system,
                        "network::event_distribution",
@@ -44,6 +77,7 @@ impl Engine {
     planner.add_system(health_check_system,
                        "network::health_check",
                        NETWORK_HEALTH_CHECK_PRIORITY);
+    planner.add_system(ping_system, "network::ping", NETWORK_PING_PRIORITY);
     planner.add_system(physics_system, "physics", PHYSICS_PRIORITY);
     planner.add_system(connection_system,
                        "player::connection",
@@ -53,13 +87,81 @@ impl Engine {
                        PLAYER_SNAPSHOT_PRIORITY);
     planner.add_system(player_input_system, "player::input", PLAYER_INPUT_PRIORITY);
 
-    Engine { planner: planner }
+    Engine {
+      planner: planner,
+      tick: 0,
+    }
   }
 
+  /**
+   * Runs the next tick, advancing the simulation by the fixed step dt, as a frame of its own
+   */
   pub fn tick(&mut self, dt: &time::Duration) {
-    self.planner.dispatch(Delta {
-      dt: dt.clone(),
-      now: time::now(),
-    });
+    self.run_frame(dt, 1);
+  }
+
+  /**
+   * Runs the given number of ticks in a row, each advancing the simulation by the fixed step dt
+   *
+   * Output goes out once for the whole frame: what the last frame produced is sent in the first
+   * tick, and snapshots are only taken in the last.
+   */
+  pub fn run_frame(&mut self, dt: &time::Duration, ticks: u32) {
+    for idx in 0..ticks {
+      self.tick = self.tick + 1;
+
+      self.planner.dispatch(Delta {
+        dt: dt.clone(),
+        now: time::now(),
+        tick: self.tick,
+        first_of_frame: idx == 0,
+        last_of_frame: idx + 1 == ticks,
+      });
+    }
+  }
+
+  /**
+   * Turns away new players, and warns everyone that the server is going away
+   */
+  pub fn begin_shutdown(&mut self, reason: String, eta_ms: u64) {
+    let world = self.planner.mut_world();
+    *world.write_resource::<RunState>() = RunState::ShuttingDown(reason.clone());
+    world.fetch_publisher::<OutboundEvent>()
+      .push(OutboundEvent::Undirected(ServerNetworkEvent::ShuttingDown {
+        reason: reason,
+        eta_ms: eta_ms,
+      }));
+  }
+
+  /**
+   * Describes each connected player's link and latest snapshot, a line per player
+   */
+  pub fn link_report(&mut self) -> Vec<String> {
+    let world = self.planner.mut_world();
+    let link_stats = world.read_resource::<PlayerLinkStats>();
+    let snapshot_stats = world.read_resource::<SnapshotStats>();
+    let report = snapshot_stats.0
+      .iter()
+      .map(|(session, usage)| {
+        let link = link_stats.0
+          .get(session)
+          .map(|stats| format!("rtt {:.0}ms, jitter {:.0}ms", stats.rtt_ms, stats.jitter_ms))
+          .unwrap_or("rtt unknown".to_owned());
+        format!("Player {}: {}, snapshot {} bytes sent ({} estimated), {} entities deferred",
+                session,
+                link,
+                usage.sent_bytes,
+                usage.estimated_bytes,
+                usage.deferred)
+      })
+      .collect();
+    report
+  }
+
+  /**
+   * Whether connected players have acked everything reliable sent to them, as of the last tick
+   */
+  pub fn drained(&mut self) -> bool {
+    *self.planner.mut_world().read_resource::<UnackedEvents>() == UnackedEvents(0)
   }
 }
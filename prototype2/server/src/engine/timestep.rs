@@ -0,0 +1,73 @@
+use time::Duration;
+
+/**
+ * Turns real time into a whole number of fixed simulation steps
+ *
+ * Real time builds up between frames, and each step's worth is spent on one tick, so the
+ * simulation advances by the same amount every tick however the frames fall. After a stall, no
+ * more than the catch-up limit is run in one go and the rest of the backlog is dropped, so a slow
+ * server doesn't fall further and further behind.
+ */
+pub struct FixedTimestep {
+  step: Duration,
+  max_catch_up: u32,
+  accumulated: Duration,
+}
+
+impl FixedTimestep {
+  pub fn new(tick_rate: u32, max_catch_up: u32) -> FixedTimestep {
+    FixedTimestep {
+      step: Duration::nanoseconds(1000000000 / tick_rate as i64),
+      max_catch_up: max_catch_up,
+      accumulated: Duration::zero(),
+    }
+  }
+
+  pub fn step(&self) -> Duration {
+    self.step
+  }
+
+  /**
+   * Adds the real time that's passed, and gives how many steps are due now and how many were
+   * dropped for being too far behind
+   */
+  pub fn advance(&mut self, elapsed: Duration) -> (u32, u32) {
+    self.accumulated = self.accumulated + elapsed;
+
+    let mut due = 0;
+    while self.accumulated >= self.step {
+      self.accumulated = self.accumulated - self.step;
+      due = due + 1;
+    }
+
+    if due > self.max_catch_up {
+      (self.max_catch_up, due - self.max_catch_up)
+    } else {
+      (due, 0)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::FixedTimestep;
+  use time::Duration;
+
+  #[test]
+  fn leftover_time_carries_into_the_next_frame() {
+    let mut timestep = FixedTimestep::new(100, 5);
+
+    assert_eq!(timestep.advance(Duration::milliseconds(6)), (0, 0));
+    assert_eq!(timestep.advance(Duration::milliseconds(6)), (1, 0));
+    assert_eq!(timestep.advance(Duration::milliseconds(25)), (2, 0));
+    assert_eq!(timestep.advance(Duration::milliseconds(3)), (1, 0));
+  }
+
+  #[test]
+  fn catching_up_after_a_stall_is_capped() {
+    let mut timestep = FixedTimestep::new(100, 5);
+
+    assert_eq!(timestep.advance(Duration::milliseconds(1005)), (5, 95));
+    assert_eq!(timestep.advance(Duration::milliseconds(5)), (1, 0));
+  }
+}
@@ -1,15 +1,22 @@
 extern crate itertools;
 extern crate specs;
 extern crate time;
+extern crate toml;
+extern crate serde_json;
+extern crate chan_signal;
 
 extern crate common;
 extern crate pubsub;
 extern crate aspects;
 extern crate server_state as state;
 extern crate server_network as network;
-extern crate server_player as player;
+pub extern crate server_player as player;
 extern crate physics;
 
+/// Describes the settings a server is started with
+///
+pub mod config;
+
 /// Manages main loop and coordination of application components
 ///
 pub mod engine;
@@ -18,37 +25,104 @@ pub mod engine;
 ///
 pub mod world;
 
+use std::path::Path;
+use std::sync::mpsc;
 use std::thread;
 use std::time::Duration as StdDuration;
 
-use time::Duration;
+use chan_signal::Signal;
+use itertools::Itertools;
+use time::{Duration, SteadyTime};
 
 use engine::Engine;
+use engine::timestep::FixedTimestep;
+
+/// How often each player's link and snapshot usage is logged, in seconds
+const LINK_REPORT_INTERVAL_S: i64 = 30;
+
+pub use config::ServerConfig;
 
-pub fn start(port: u16) {
-  println!("Starting server on {}", port);
-  // TODO(acmcarther): Passing just 'port' to engine seems weird
-  let mut engine = Engine::new(port);
-  let running = true;
-  let tick_rate = 66;
-  let time_step = 1.0 / (tick_rate as f32); //s
+/**
+ * Runs the server until it's interrupted or terminated
+ *
+ * On the first SIGINT or SIGTERM, players are warned and new ones turned away, and the server keeps
+ * ticking until everything sent has been acked (or the grace period runs out). A second signal
+ * cuts that short. The world is saved on the way out, if the config says where.
+ *
+ * The simulation runs in fixed steps at the tick rate, however the frames fall, so its results
+ * don't depend on scheduling hiccups. A frame that runs several steps to catch up sends its output
+ * once. Every so often, each player's link and snapshot usage is logged.
+ */
+pub fn start(config: ServerConfig) {
+  // Has to happen before any other threads start, so they leave these signals to us
+  let signals = chan_signal::notify(&[Signal::INT, Signal::TERM]);
+  let (signal_sender, signal_receiver) = mpsc::channel();
+  thread::spawn(move || {
+    while let Some(signal) = signals.recv() {
+      if signal_sender.send(signal).is_err() {
+        break;
+      }
+    }
+  });
 
-  let mut last_time = time::now();
-  let mut next_time = time::now();
-  let mut now;
+  println!("Starting server on {}", config.socket_address());
+  let mut engine = Engine::new(&config);
+  let mut running = true;
+  let mut shutdown_deadline = None;
+  let mut frames_since_shutdown = 0;
+  let mut timestep = FixedTimestep::new(config.tick_rate, config.max_catch_up_ticks);
+  // Steady, so the clock being set doesn't stall the simulation or rush it
+  let mut last_time = SteadyTime::now();
+  let mut last_report = last_time;
 
   println!("Server Started!");
   while running {
-    now = time::now();
-    if now > next_time {
-      let dt = now - last_time;
+    if let Ok(signal) = signal_receiver.try_recv() {
+      if shutdown_deadline.is_some() {
+        println!("Received {:?} again, stopping now", signal);
+        running = false;
+      } else {
+        println!("Received {:?}, shutting down", signal);
+        engine.begin_shutdown("Server is shutting down".to_owned(), config.shutdown_grace_ms);
+        shutdown_deadline =
+          Some(SteadyTime::now() + Duration::milliseconds(config.shutdown_grace_ms as i64));
+      }
+    }
+
+    let now = SteadyTime::now();
+    let (due, dropped) = timestep.advance(now - last_time);
+    last_time = now;
+    if dropped > 0 {
+      println!("Fell behind, skipping {} ticks", dropped);
+    }
 
-      engine.tick(&dt);
+    if due > 0 {
+      engine.run_frame(&timestep.step(), due);
 
-      last_time = now;
-      next_time = next_time + Duration::milliseconds((time_step * 1000.0) as i64);
-    } else {
+      if let Some(deadline) = shutdown_deadline {
+        // The first frame sends the warning, so it can't have been acked before the second
+        frames_since_shutdown = frames_since_shutdown + 1;
+        if now > deadline || (frames_since_shutdown > 1 && engine.drained()) {
+          running = false;
+        }
+      }
+    }
+
+    if now - last_report >= Duration::seconds(LINK_REPORT_INTERVAL_S) {
+      engine.link_report().iter().foreach(|line| println!("{}", line));
+      last_report = now;
+    }
+
+    if due == 0 {
       thread::sleep(StdDuration::from_millis(2))
     }
   }
+
+  if let Some(ref path) = config.save_path {
+    match world::save(engine.planner.mut_world(), Path::new(path)) {
+      Ok(()) => println!("Saved world to {}", path),
+      Err(err) => println!("Could not save world to {}: {}", path, err),
+    }
+  }
+  println!("Server stopped");
 }
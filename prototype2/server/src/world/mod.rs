@@ -1,5 +1,12 @@
-use common::aspects::{DisabledAspect, PhysicalAspect, RenderAspect, SynchronizedAspect};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
 
+use common::aspects::{CommonWorld, DisabledAspect, PhysicalAspect, RenderAspect,
+                      SynchronizedAspect};
+
+use itertools::Itertools;
+use serde_json;
 use specs;
 use aspects::{CollisionAspect, ControllerAspect, PlayerAspect};
 
@@ -21,3 +28,34 @@ impl ServerWorld {
     ServerWorld { world: w }
   }
 }
+
+/**
+ * Writes every synchronized entity and its shared aspects to the path, as JSON
+ */
+pub fn save(world: &specs::World, path: &Path) -> io::Result<()> {
+  use specs::Join;
+
+  let (synchronized, physical, render, disabled) = (world.read::<SynchronizedAspect>(),
+                                                    world.read::<PhysicalAspect>(),
+                                                    world.read::<RenderAspect>(),
+                                                    world.read::<DisabledAspect>());
+
+  let mut saved = CommonWorld::new();
+  synchronized.iter().foreach(|synchro| {
+    saved.entities.insert(synchro.clone());
+  });
+  (&synchronized, &physical).iter().foreach(|(synchro, aspect)| {
+    saved.physical.insert(synchro.to_string(), aspect.clone());
+  });
+  (&synchronized, &render).iter().foreach(|(synchro, aspect)| {
+    saved.rendered.insert(synchro.to_string(), aspect.clone());
+  });
+  (&synchronized, &disabled).iter().foreach(|(synchro, aspect)| {
+    saved.disabled.insert(synchro.to_string(), aspect.clone());
+  });
+
+  let contents = try!(serde_json::to_string(&saved)
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string())));
+  let mut file = try!(File::create(path));
+  file.write_all(contents.as_bytes())
+}
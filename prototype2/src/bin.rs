@@ -4,28 +4,90 @@ extern crate clap;
 
 use std::str::FromStr;
 use std::net::ToSocketAddrs;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use clap::AppSettings::SubcommandRequired;
 use std::convert::TryFrom;
 
-static EXAMPLE_SERVER_COMMAND: &'static str = "space_coop server -p 8888";
+use prototype2::client::engine::input::ScriptedInput;
+use prototype2::client::input_map::Bindings;
+use prototype2::client::network::ShutdownPolicy;
+use prototype2::common::network::LinkConditions;
+use prototype2::server::ServerConfig;
+
+static EXAMPLE_SERVER_COMMAND: &'static str = "space_coop server --config server.toml -p 8888";
 static EXAMPLE_CLIENT_COMMAND: &'static str = "space_coop client -p 9999 -s 192.168.0.1:8888";
+static EXAMPLE_BOT_COMMAND: &'static str = "space_coop bot --script crew.bot --count 20 -s \
+                                            192.168.0.1:8888";
 static EXAMPLE_CLIENT_DEPS_COMMAND: &'static str = "space_coop client-deps -p 9999 -s \
                                                     192.168.0.1:8888";
 
 fn main() {
   let matches = App::new("space coop")
-    .usage(format!("\t{}\n\t{}", EXAMPLE_SERVER_COMMAND, EXAMPLE_CLIENT_COMMAND).as_ref())
+    .usage(format!("\t{}\n\t{}\n\t{}",
+                   EXAMPLE_SERVER_COMMAND,
+                   EXAMPLE_CLIENT_COMMAND,
+                   EXAMPLE_BOT_COMMAND)
+      .as_ref())
     .settings(&[SubcommandRequired])
     .subcommand(SubCommand::with_name("server")
       .usage(EXAMPLE_SERVER_COMMAND)
+      .arg(Arg::with_name("config")
+        .long("config")
+        .help("TOML server config, overridden by any other flags given")
+        .takes_value(true)
+        .value_name("FILE"))
+      .arg(Arg::with_name("print config")
+        .long("print-config")
+        .help("Prints the resulting config instead of starting the server"))
       .arg(Arg::with_name("port")
         .short("p")
         .long("port")
         .help("Server's port")
         .takes_value(true)
-        .default_value("7090")
-        .value_name("PORT")))
+        .value_name("PORT"))
+      .arg(Arg::with_name("bind address")
+        .long("bind_address")
+        .help("Address to listen on")
+        .takes_value(true)
+        .value_name("IP"))
+      .arg(Arg::with_name("codec")
+        .short("c")
+        .long("codec")
+        .help("Wire format, must match the clients'")
+        .takes_value(true)
+        .possible_value("binary")
+        .possible_value("json")
+        .value_name("CODEC"))
+      .arg(Arg::with_name("tick rate")
+        .long("tick_rate")
+        .help("Simulation ticks per second")
+        .takes_value(true)
+        .value_name("HZ"))
+      .arg(Arg::with_name("health timeout")
+        .long("health_timeout")
+        .help("Milliseconds a player may go unheard before they're disconnected")
+        .takes_value(true)
+        .value_name("MS"))
+      .arg(Arg::with_name("gravity")
+        .long("gravity")
+        .help("Gravity, z up")
+        .takes_value(true)
+        .value_name("X,Y,Z"))
+      .arg(Arg::with_name("fragment size")
+        .long("fragment_size")
+        .help("Largest snapshot fragment, in bytes")
+        .takes_value(true)
+        .value_name("BYTES"))
+      .arg(Arg::with_name("planner threads")
+        .long("planner_threads")
+        .help("Threads to run systems on")
+        .takes_value(true)
+        .value_name("COUNT"))
+      .args(&link_condition_args()))
     .subcommand(SubCommand::with_name("client")
       .usage(EXAMPLE_CLIENT_COMMAND)
       .arg(Arg::with_name("port")
@@ -42,7 +104,94 @@ fn main() {
         .value_name("ADDRESS:PORT")
         .takes_value(true)
         .default_value("127.0.0.1:7090")
-        .required(true)))
+        .required(true))
+      .arg(Arg::with_name("codec")
+        .short("c")
+        .long("codec")
+        .help("Wire format, must match the server's")
+        .takes_value(true)
+        .possible_value("binary")
+        .possible_value("json")
+        .default_value("binary")
+        .value_name("CODEC"))
+      .arg(Arg::with_name("interpolation delay")
+        .long("interpolation_delay")
+        .help("How far behind the server other entities are drawn, in milliseconds")
+        .takes_value(true)
+        .default_value("100")
+        .value_name("MS"))
+      .arg(Arg::with_name("bindings")
+        .long("bindings")
+        .help("TOML key bindings, written to by the bind and unbind console commands")
+        .takes_value(true)
+        .default_value("bindings.toml")
+        .value_name("FILE"))
+      .arg(Arg::with_name("headless")
+        .long("headless")
+        .help("Runs without a window or graphics device"))
+      .arg(Arg::with_name("input script")
+        .long("input_script")
+        .help("Input for a headless client to play, one tick per line")
+        .takes_value(true)
+        .requires("headless")
+        .value_name("FILE"))
+      .arg(Arg::with_name("reconnect")
+        .long("reconnect")
+        .help("Reconnects when the server shuts down, in case it's restarting"))
+      .arg(Arg::with_name("admin secret")
+        .long("admin_secret")
+        .help("Presented when connecting, to be made an admin if it matches the server's")
+        .takes_value(true)
+        .value_name("SECRET"))
+      .args(&link_condition_args()))
+    .subcommand(SubCommand::with_name("bot")
+      .usage(EXAMPLE_BOT_COMMAND)
+      .arg(Arg::with_name("script")
+        .long("script")
+        .help("Bot behaviour file; create and set_model need --admin_secret")
+        .takes_value(true)
+        .required(true)
+        .value_name("FILE"))
+      .arg(Arg::with_name("count")
+        .short("n")
+        .long("count")
+        .help("How many bots to run, on consecutive ports")
+        .takes_value(true)
+        .default_value("1")
+        .value_name("COUNT"))
+      .arg(Arg::with_name("port")
+        .short("p")
+        .long("port")
+        .help("First bot's port")
+        .takes_value(true)
+        .default_value("7190")
+        .value_name("PORT"))
+      .arg(Arg::with_name("server address")
+        .short("s")
+        .help("Server's address and port")
+        .long("server_address")
+        .value_name("ADDRESS:PORT")
+        .takes_value(true)
+        .default_value("127.0.0.1:7090")
+        .required(true))
+      .arg(Arg::with_name("codec")
+        .short("c")
+        .long("codec")
+        .help("Wire format, must match the server's")
+        .takes_value(true)
+        .possible_value("binary")
+        .possible_value("json")
+        .default_value("binary")
+        .value_name("CODEC"))
+      .arg(Arg::with_name("reconnect")
+        .long("reconnect")
+        .help("Reconnects when the server shuts down, in case it's restarting"))
+      .arg(Arg::with_name("admin secret")
+        .long("admin_secret")
+        .help("Presented when connecting, to be made an admin if it matches the server's")
+        .takes_value(true)
+        .value_name("SECRET"))
+      .args(&link_condition_args()))
     .subcommand(SubCommand::with_name("client-deps")
       .usage(EXAMPLE_CLIENT_DEPS_COMMAND)
       .arg(Arg::with_name("output file")
@@ -66,15 +215,71 @@ fn main() {
     .get_matches();
 
   if let Some(server_matches) = matches.subcommand_matches("server") {
-    prototype2::server::start(port_from(&server_matches))
+    let config = server_config_from(&server_matches);
+    if server_matches.is_present("print config") {
+      println!("{}", config.to_toml());
+    } else {
+      prototype2::server::start(config)
+    }
   } else if let Some(client_matches) = matches.subcommand_matches("client") {
-    prototype2::client::start(port_from(&client_matches), addr_from(&client_matches))
+    prototype2::client::start(port_from(&client_matches),
+                              addr_from(&client_matches),
+                              codec_from(&client_matches),
+                              link_conditions_from(&client_matches),
+                              shutdown_policy_from(&client_matches),
+                              admin_secret_from(&client_matches),
+                              interpolation_delay_from(&client_matches),
+                              bindings_from(&client_matches),
+                              client_matches.value_of("bindings").map(|path| path.to_owned()),
+                              headless_input_from(&client_matches))
+  } else if let Some(bot_matches) = matches.subcommand_matches("bot") {
+    let passed = prototype2::client::start_bots(port_from(&bot_matches),
+                                                addr_from(&bot_matches),
+                                                codec_from(&bot_matches),
+                                                link_conditions_from(&bot_matches),
+                                                shutdown_policy_from(&bot_matches),
+                                                admin_secret_from(&bot_matches),
+                                                script_from(&bot_matches),
+                                                count_from(&bot_matches));
+    if !passed {
+      process::exit(1)
+    }
   } else if let Some(client_deps_matches) = matches.subcommand_matches("client-deps") {
     prototype2::client::dependencies(output_file_from(&client_deps_matches),
                                      dependency_mode_from(&client_deps_matches))
   }
 }
 
+fn shutdown_policy_from(matches: &ArgMatches) -> ShutdownPolicy {
+  if matches.is_present("reconnect") {
+    ShutdownPolicy::Reconnect
+  } else {
+    ShutdownPolicy::Leave
+  }
+}
+
+fn admin_secret_from(matches: &ArgMatches) -> Option<String> {
+  matches.value_of("admin secret").map(|secret| secret.to_owned())
+}
+
+fn interpolation_delay_from(matches: &ArgMatches) -> u64 {
+  let raw = matches.value_of("interpolation delay").unwrap();
+  u64::from_str(raw).unwrap_or_else(|_| exit_with(format!("Invalid interpolation delay: {}", raw)))
+}
+
+/**
+ * Loads the bindings file if there is one yet, or else the default bindings
+ */
+fn bindings_from(matches: &ArgMatches) -> Bindings {
+  let path = matches.value_of("bindings").unwrap();
+  if Path::new(path).exists() {
+    Bindings::load(Path::new(path))
+      .unwrap_or_else(|err| exit_with(format!("Could not load bindings from {}: {}", path, err)))
+  } else {
+    Bindings::defaults()
+  }
+}
+
 fn port_from(matches: &ArgMatches) -> u16 {
   matches.value_of("port").and_then(|v| u16::from_str(&v).ok()).unwrap()
 }
@@ -86,6 +291,150 @@ fn addr_from(matches: &ArgMatches) -> std::net::SocketAddr {
     .unwrap()
 }
 
+fn codec_from(matches: &ArgMatches) -> prototype2::common::codec::CodecKind {
+  matches.value_of("codec")
+    .and_then(|v| prototype2::common::codec::CodecKind::from_str(&v).ok())
+    .unwrap()
+}
+
+fn script_from(matches: &ArgMatches) -> prototype2::client::bot::Script {
+  let path = matches.value_of("script").unwrap();
+  let mut contents = String::new();
+  File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut contents))
+    .unwrap_or_else(|err| exit_with(format!("Could not read script {}: {}", path, err)));
+
+  prototype2::client::bot::Script::parse(&contents)
+    .unwrap_or_else(|err| exit_with(format!("Invalid script {}: {}", path, err)))
+}
+
+/**
+ * Reads the input a headless client should play, which is none at all without a script
+ */
+fn headless_input_from(matches: &ArgMatches) -> Option<ScriptedInput> {
+  if !matches.is_present("headless") {
+    return None;
+  }
+
+  let path = match matches.value_of("input script") {
+    Some(path) => path,
+    None => return Some(ScriptedInput::idle()),
+  };
+  let mut contents = String::new();
+  File::open(path)
+    .and_then(|mut file| file.read_to_string(&mut contents))
+    .unwrap_or_else(|err| exit_with(format!("Could not read input script {}: {}", path, err)));
+
+  Some(ScriptedInput::parse(&contents)
+    .unwrap_or_else(|err| exit_with(format!("Invalid input script {}: {}", path, err))))
+}
+
+fn count_from(matches: &ArgMatches) -> u16 {
+  let raw = matches.value_of("count").unwrap();
+  u16::from_str(raw).unwrap_or_else(|_| exit_with(format!("Invalid count: {}", raw)))
+}
+
+fn server_config_from(matches: &ArgMatches) -> ServerConfig {
+  let mut config = match matches.value_of("config") {
+    Some(path) => {
+      ServerConfig::load(Path::new(path)).unwrap_or_else(|err| {
+        exit_with(format!("Could not load config from {}: {}", path, err))
+      })
+    },
+    None => ServerConfig::new(),
+  };
+
+  override_from(matches, "port", &mut config.port);
+  override_from(matches, "bind address", &mut config.bind_address);
+  override_from(matches, "codec", &mut config.codec);
+  override_from(matches, "tick rate", &mut config.tick_rate);
+  override_from(matches, "health timeout", &mut config.health_timeout_ms);
+  override_from(matches, "fragment size", &mut config.fragment_size);
+  override_from(matches, "planner threads", &mut config.planner_threads);
+  override_link_conditions(matches, &mut config.link_conditions);
+  if let Some(gravity) = matches.value_of("gravity") {
+    let components = gravity.split(',')
+      .map(|v| f32::from_str(v.trim()).ok())
+      .collect::<Option<Vec<f32>>>();
+    match components {
+      Some(ref xyz) if xyz.len() == 3 => config.gravity = (xyz[0], xyz[1], xyz[2]),
+      _ => exit_with(format!("Invalid gravity: {}", gravity)),
+    }
+  }
+
+  config.validate().unwrap_or_else(|err| exit_with(format!("Invalid config: {}", err)));
+  config
+}
+
+/**
+ * Flags for simulating a bad connection, shared by everything that talks over the network
+ */
+fn link_condition_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![Arg::with_name("latency")
+         .long("latency")
+         .help("Simulated delay each way, in milliseconds")
+         .takes_value(true)
+         .value_name("MS"),
+       Arg::with_name("jitter")
+         .long("jitter")
+         .help("Most simulated variation in delay, in milliseconds")
+         .takes_value(true)
+         .value_name("MS"),
+       Arg::with_name("loss")
+         .long("loss")
+         .help("Percentage of datagrams to drop")
+         .takes_value(true)
+         .value_name("PERCENT"),
+       Arg::with_name("reorder")
+         .long("reorder")
+         .help("How many datagrams ahead a datagram may jump")
+         .takes_value(true)
+         .value_name("COUNT"),
+       Arg::with_name("duplicate")
+         .long("duplicate")
+         .help("Percentage of datagrams to deliver twice")
+         .takes_value(true)
+         .value_name("PERCENT"),
+       Arg::with_name("seed")
+         .long("seed")
+         .help("Seeds the simulated conditions, to reproduce a run")
+         .takes_value(true)
+         .value_name("SEED")]
+}
+
+fn link_conditions_from(matches: &ArgMatches) -> LinkConditions {
+  let mut conditions = LinkConditions::perfect();
+  override_link_conditions(matches, &mut conditions);
+  conditions
+}
+
+fn override_link_conditions(matches: &ArgMatches, conditions: &mut LinkConditions) {
+  override_from(matches, "latency", &mut conditions.latency_ms);
+  override_from(matches, "jitter", &mut conditions.jitter_ms);
+  override_percent_from(matches, "loss", &mut conditions.loss_percent);
+  override_from(matches, "reorder", &mut conditions.reorder_window);
+  override_percent_from(matches, "duplicate", &mut conditions.duplicate_percent);
+  override_from(matches, "seed", &mut conditions.seed);
+}
+
+fn override_from<T: FromStr>(matches: &ArgMatches, name: &str, value: &mut T) {
+  if let Some(raw) = matches.value_of(name) {
+    *value = T::from_str(raw).unwrap_or_else(|_| exit_with(format!("Invalid {}: {}", name, raw)));
+  }
+}
+
+fn override_percent_from(matches: &ArgMatches, name: &str, value: &mut f32) {
+  override_from(matches, name, value);
+  if !(*value >= 0.0 && *value <= 100.0) {
+    exit_with::<()>(format!("Invalid {}: {} is not between 0 and 100", name, value));
+  }
+}
+
+fn exit_with<T>(message: String) -> T {
+  println!("{}", message);
+  process::exit(1)
+}
+
 fn output_file_from(matches: &ArgMatches) -> String {
   matches.value_of("output file").map(|v| v.to_owned()).unwrap()
 }
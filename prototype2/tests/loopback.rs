@@ -0,0 +1,149 @@
+extern crate prototype2;
+extern crate specs;
+extern crate time;
+
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use specs::Join;
+
+use prototype2::client;
+use prototype2::client::bot::Script;
+use prototype2::client::engine::input::ScriptedInput;
+use prototype2::client::network::ConnectionStatus;
+use prototype2::common::aspects::SynchronizedAspect;
+use prototype2::common::codec::CodecKind;
+use prototype2::common::network::{ConditionedTransport, LinkConditions, Loopback};
+use prototype2::server;
+use prototype2::server::ServerConfig;
+use prototype2::server::player::AppliedInputs;
+
+/// Most ticks to give the client to connect before failing the test
+const MAX_CONNECT_TICKS: u32 = 300;
+
+fn entity_count(client: &mut client::engine::Engine) -> usize {
+  client.planner.mut_world().read::<SynchronizedAspect>().iter().count()
+}
+
+fn connection_status(client: &mut client::engine::Engine) -> ConnectionStatus {
+  client.planner.mut_world().read_resource::<ConnectionStatus>().clone()
+}
+
+/**
+ * Steps the two in lockstep until the client has connected
+ */
+fn connect(client: &mut client::engine::Engine,
+           server: &mut server::engine::Engine,
+           dt: &time::Duration) {
+  let mut connect_ticks = 0;
+  while !connection_status(client).is_connected() {
+    assert!(connect_ticks < MAX_CONNECT_TICKS, "Client could not connect");
+    client.tick(dt);
+    server.tick(dt);
+    connect_ticks = connect_ticks + 1;
+    // Gives lost Connects time to be resent
+    thread::sleep(StdDuration::from_millis(10));
+  }
+}
+
+/**
+ * Has a bot create an entity, yielding how many entities its client saw before and after
+ *
+ * Everything either side sends passes through the given conditions.
+ */
+fn entities_around_create(conditions: LinkConditions, ticks_after: u32) -> (usize, usize) {
+  let loopback = Loopback::new();
+  let server_addr = SocketAddr::from_str("127.0.0.1:7090").unwrap();
+  let client_addr = SocketAddr::from_str("127.0.0.1:7190").unwrap();
+  let dt = time::Duration::milliseconds(15);
+
+  let mut config = ServerConfig::new();
+  config.permissions.admin_secret = Some("loopback".to_owned());
+  let mut client_conditions = conditions.clone();
+  client_conditions.seed = conditions.seed.wrapping_add(1);
+  let server_transport = ConditionedTransport::new(Box::new(loopback.bind(server_addr)),
+                                                   conditions);
+  let client_transport = ConditionedTransport::new(Box::new(loopback.bind(client_addr)),
+                                                   client_conditions);
+  let mut server = server::engine::Engine::with_transport(&config, Box::new(server_transport));
+
+  let network = client::network::Network::with_transport(Box::new(client_transport),
+                                                         server_addr,
+                                                         CodecKind::Binary);
+
+  // The two are stepped in lockstep, and the bot's script starts once it has connected
+  // It waits long enough for even a bad link to have delivered a first snapshot
+  let script = Script::parse("wait 60\ncreate").unwrap();
+  let mut client = client::engine::Engine::new_bot(network, script);
+  client.set_admin_secret(Some("loopback".to_owned()));
+  connect(&mut client, &mut server, &dt);
+  for _ in 0..50 {
+    client.tick(&dt);
+    server.tick(&dt);
+  }
+  let entities_before = entity_count(&mut client);
+
+  for _ in 0..ticks_after {
+    client.tick(&dt);
+    server.tick(&dt);
+  }
+
+  (entities_before, entity_count(&mut client))
+}
+
+#[test]
+fn created_entities_reach_the_client() {
+  let (before, after) = entities_around_create(LinkConditions::perfect(), 30);
+
+  assert_eq!(after, before + 1);
+}
+
+#[test]
+fn snapshots_survive_a_bad_link() {
+  let mut conditions = LinkConditions::perfect();
+  conditions.loss_percent = 20.0;
+  conditions.reorder_window = 4;
+  conditions.duplicate_percent = 10.0;
+  conditions.seed = 1;
+
+  let (before, after) = entities_around_create(conditions, 120);
+
+  assert_eq!(after, before + 1);
+}
+
+#[test]
+fn headless_clients_send_their_scripted_input() {
+  let loopback = Loopback::new();
+  let server_addr = SocketAddr::from_str("127.0.0.1:7090").unwrap();
+  let client_addr = SocketAddr::from_str("127.0.0.1:7190").unwrap();
+  let dt = time::Duration::milliseconds(15);
+
+  let mut server = server::engine::Engine::with_transport(&ServerConfig::new(),
+                                                          Box::new(loopback.bind(server_addr)));
+  let network = client::network::Network::with_transport(Box::new(loopback.bind(client_addr)),
+                                                         server_addr,
+                                                         CodecKind::Binary);
+  let input = ScriptedInput::parse("# Walk forward, away from the camera\npress W").unwrap();
+  let mut client = client::engine::Engine::new_headless(network, Box::new(input));
+  connect(&mut client, &mut server, &dt);
+  for _ in 0..10 {
+    client.tick(&dt);
+    server.tick(&dt);
+  }
+
+  let report = server.link_report();
+  assert_eq!(report.len(), 1);
+  assert!(report[0].contains("bytes sent"));
+
+  let applied = server.planner.mut_world().read_resource::<AppliedInputs>().0.clone();
+  assert_eq!(applied.len(), 1);
+  let input = applied.values().next().unwrap();
+  // The camera starts at (3, -10, 6), so forward is toward the origin from there
+  let length = (3.0f32 * 3.0 + 10.0 * 10.0).sqrt();
+  let (forward_x, forward_y) = (-3.0 / length, 10.0 / length);
+  assert!((input.movement.0 - forward_x).abs() < 0.001);
+  assert!((input.movement.1 - forward_y).abs() < 0.001);
+  assert_eq!(input.movement.2, 0.0);
+}